@@ -64,7 +64,7 @@ pub fn large_map_parsing(c: &mut Criterion) {
             //Get json structure from the response string and then save for the future
             let json: OverpassResponse = serde_json::from_str(&response)
                 .expect("Was not able to parse json from response!");
-            let _ = json.save_blocking(large_graph_file_path)
+            json.save_blocking(large_graph_file_path)
                 .expect("Was not able to save json to file!");
 
             json
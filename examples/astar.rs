@@ -127,7 +127,7 @@ fn query_and_save(filepath: &str) -> Result<OverpassResponse, Box<dyn Error>> {
 
     //Get json structure from the response string and then save for the future
     let json: OverpassResponse = serde_json::from_str(&response)?;
-    let _ = json.save_blocking(filepath)?;
+    json.save_blocking(filepath)?;
  
     Ok(json)
 }
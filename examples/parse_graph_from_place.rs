@@ -77,7 +77,7 @@ fn display(image_location: &str, graph: OSMGraph) -> Result<(), Box<dyn std::err
 
             PathElement::new(
                 vec![(source.lon(), source.lat()), (target.lon(), target.lat())],
-                &BLACK,
+                BLACK,
             )
         }),
     )?;
@@ -97,7 +97,7 @@ fn query_and_save(filepath: &str) -> Result<OverpassResponse, Box<dyn Error>> {
 
     //Get json structure from the response string and then save for the future
     let json: OverpassResponse = serde_json::from_str(&response)?;
-    let _ = json.save_blocking(filepath)?;
+    json.save_blocking(filepath)?;
  
     Ok(json)
 }
@@ -0,0 +1,232 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Serialize, Deserialize};
+
+use tokio::fs;
+
+use crate::Error;
+use crate::runtime::block_on;
+
+/// `CacheEntry` describes a response stored in a [`QueryCache`].
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CacheEntry {
+    endpoint: String,
+    query: String,
+    created: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp_osm_base: Option<String>
+}
+
+impl CacheEntry {
+
+    /// Get the endpoint that answered the query.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+    /// Get the text of the query.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+    /// Get the time at which the response was stored.
+    pub fn created(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.created)
+    }
+    /// Get how old the OSM data behind the response is, as reported by Overpass in
+    /// `osm3s.timestamp_osm_base` (such as `"2024-11-18T15:04:05Z"`).
+    pub fn timestamp_osm_base(&self) -> Option<&str> {
+        self.timestamp_osm_base.as_deref()
+    }
+}
+
+/// `QueryCache` stores Overpass responses on disk so that the same query is not sent twice. Give
+/// one to a [`crate::api::QueryEngine`] with [`crate::api::QueryEngine::with_cache`] and every
+/// query the engine sends goes through it.
+///
+/// Each response is stored under a hash of the endpoint and the final text of the query, next to
+/// a small file that records the query, when it was stored and the `osm3s.timestamp_osm_base` of
+/// the response. Responses older than the time to live are fetched again. Only complete responses
+/// are stored.
+///
+/// In offline mode the network is never used: every stored response is used however old it is,
+/// and a query without one fails right away with [`Error::CacheMiss`]. This is useful for tests
+/// and for machines without network access, given a cache directory filled somewhere else.
+///
+/// ```rust
+/// # #[cfg(feature = "reqwest")] {
+/// use std::time::Duration;
+/// use osmgraph::api::{QueryEngine, QueryCache};
+///
+/// let engine = QueryEngine::new()
+///     .with_cache(QueryCache::new("./osm_cache")
+///         .with_ttl(Some(Duration::from_secs(7 * 24 * 60 * 60)))
+///         .with_offline(std::env::var("CI").is_ok()));
+/// # }
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct QueryCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+    offline: bool
+}
+
+impl QueryCache {
+
+    /// Create a cache that stores responses in `dir`. The directory is created when the first
+    /// response is stored. Responses are kept for a day by default.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: Some(Duration::from_secs(24 * 60 * 60)),
+            offline: false
+        }
+    }
+
+    /// Getter for the directory the responses are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    /// Getter for how long a stored response is used for. `None` means forever.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+    /// Getter for whether the cache is in offline mode.
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Set how long a stored response is used for. `None` means forever. Offline mode ignores it.
+    /// Meant to be used in a functional style
+    pub fn with_ttl(&self, ttl: Option<Duration>) -> Self {
+        Self {
+            ttl,
+            ..self.clone()
+        }
+    }
+    /// Set whether only the cache is used, never the network. Stored responses are then used
+    /// however old they are. Meant to be used in a functional style
+    pub fn with_offline(&self, offline: bool) -> Self {
+        Self {
+            offline,
+            ..self.clone()
+        }
+    }
+
+    /// The key a response is stored under: a 64 bit FNV-1a hash of the endpoint and the query, in
+    /// hex.
+    pub fn key(endpoint: &str, query: &str) -> String {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in endpoint.bytes().chain([0]).chain(query.bytes()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{hash:016x}")
+    }
+
+    //Responses may be json or xml, so the body has no extension
+    fn body_path(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+
+    fn meta_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.meta.json"))
+    }
+
+    /// Get the description of the stored response for a query, whether or not it is still fresh.
+    /// A response stored for a different query or endpoint under the same key is not returned.
+    pub async fn entry(&self, endpoint: &str, query: &str) -> Result<Option<CacheEntry>, Error> {
+        let entry: CacheEntry = match fs::read(self.meta_path(&Self::key(endpoint, query))).await {
+            Ok(meta) => serde_json::from_slice(&meta)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into())
+        };
+
+        //Keys are hashes, so two queries may share one
+        match entry.endpoint == endpoint && entry.query == query {
+            true => Ok(Some(entry)),
+            false => Ok(None)
+        }
+    }
+
+    /// Behaves the same as [`QueryCache::entry`], but will wait for the function to finish before continuing.
+    pub fn entry_blocking(&self, endpoint: &str, query: &str) -> Result<Option<CacheEntry>, Error> {
+        block_on(self.entry(endpoint, query))
+    }
+
+    /// Remove every stored response.
+    pub async fn clear(&self) -> Result<(), Error> {
+        match fs::remove_dir_all(&self.dir).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(())
+        }
+    }
+
+    /// Behaves the same as [`QueryCache::clear`], but will wait for the function to finish before continuing.
+    pub fn clear_blocking(&self) -> Result<(), Error> {
+        block_on(self.clear())
+    }
+
+    /// Read the stored response for a query, if there is one and it is still fresh. In offline
+    /// mode it does not need to be fresh.
+    pub(crate) async fn get(&self, endpoint: &str, query: &str) -> Result<Option<Vec<u8>>, Error> {
+
+        let Some(entry) = self.entry(endpoint, query).await? else {
+            return Ok(None)
+        };
+
+        let age: Duration = SystemTime::now()
+            .duration_since(entry.created())
+            .unwrap_or_default();
+        if !self.offline && self.ttl.is_some_and(|ttl| age >= ttl) {
+            return Ok(None)
+        }
+
+        match fs::read(self.body_path(&Self::key(endpoint, query))).await {
+            Ok(body) => Ok(Some(body)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into())
+        }
+    }
+
+    /// Store the response to a query, along with the `timestamp_osm_base` it reports. The body is
+    /// written before the description, so a response is never found without its body, and each
+    /// file is written under a temporary name and then renamed, so a file is never read half
+    /// written.
+    pub(crate) async fn put(&self, endpoint: &str, query: &str, body: &[u8], timestamp_osm_base: Option<String>) -> Result<(), Error> {
+
+        let key: String = Self::key(endpoint, query);
+        let entry = CacheEntry {
+            endpoint: endpoint.to_string(),
+            query: query.to_string(),
+            created: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            timestamp_osm_base
+        };
+
+        fs::create_dir_all(&self.dir).await?;
+        write_atomic(&self.body_path(&key), body).await?;
+        write_atomic(&self.meta_path(&key), &serde_json::to_vec_pretty(&entry)?).await?;
+
+        Ok(())
+    }
+}
+
+/// Write a file under a temporary name in the same directory and rename it into place. The name
+/// is unique to this process and write, so concurrent writers do not clash.
+async fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), Error> {
+
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".{}.{}.tmp", std::process::id(), WRITES.fetch_add(1, Ordering::Relaxed)));
+    let temp = PathBuf::from(temp);
+
+    fs::write(&temp, contents).await?;
+    if let Err(e) = fs::rename(&temp, path).await {
+        let _ = fs::remove_file(&temp).await;
+        return Err(e.into())
+    }
+
+    Ok(())
+}
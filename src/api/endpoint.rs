@@ -0,0 +1,140 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::Error;
+
+/// `EndpointHealth` is a record of how requests to one Overpass endpoint have gone. A
+/// [`crate::api::QueryEngine`] keeps one for each of its endpoints, which you can read with
+/// [`crate::api::QueryEngine::endpoint_health`].
+///
+/// Every request that the endpoint does not answer properly counts against it, such as when it
+/// cannot be reached, is overloaded, times out or gives an error page. A query that the endpoint
+/// rejects as invalid (a 400 or a parse error) is not recorded at all, since any other endpoint
+/// would reject it too.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct EndpointHealth {
+    url: String,
+    successes: u64,
+    failures: u64,
+    consecutive_failures: u64,
+    last_error: Option<String>
+}
+
+impl EndpointHealth {
+
+    /// Get the url of the endpoint.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    /// Get the number of requests that this endpoint answered.
+    pub fn successes(&self) -> u64 {
+        self.successes
+    }
+    /// Get the number of requests that failed because of this endpoint.
+    pub fn failures(&self) -> u64 {
+        self.failures
+    }
+    /// Get the number of requests that failed in a row since the last success.
+    pub fn consecutive_failures(&self) -> u64 {
+        self.consecutive_failures
+    }
+    /// Get a description of the last failure, if there has been one.
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+    /// Returns true if the last request to this endpoint did not fail (or there has not been one).
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures == 0
+    }
+}
+
+/// The list of endpoints of a query engine, along with their shared health records. Clones share
+/// the health records and the round robin counter.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Endpoints {
+    urls: Vec<String>,
+    round_robin: bool,
+    next: Arc<AtomicUsize>,
+    health: Arc<Mutex<Vec<EndpointHealth>>>
+}
+
+impl Endpoints {
+
+    pub(crate) fn new(urls: Vec<String>) -> Self {
+        let health: Vec<EndpointHealth> = urls.iter()
+            .map(|url| EndpointHealth { url: url.clone(), ..Default::default() })
+            .collect();
+
+        Self {
+            urls,
+            round_robin: false,
+            next: Arc::new(AtomicUsize::new(0)),
+            health: Arc::new(Mutex::new(health))
+        }
+    }
+
+    pub(crate) fn urls(&self) -> &Vec<String> {
+        &self.urls
+    }
+
+    pub(crate) fn round_robin(&self) -> bool {
+        self.round_robin
+    }
+
+    pub(crate) fn with_round_robin(&self, round_robin: bool) -> Self {
+        Self {
+            round_robin,
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn health(&self) -> Vec<EndpointHealth> {
+        self.health.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// The order in which to try the endpoints for the next request. Without round robin this
+    /// starts at the first endpoint, otherwise each request starts one endpoint further along.
+    /// Endpoints whose last request failed are moved to the back, so a mirror that is down does
+    /// not slow down every request.
+    pub(crate) fn order(&self) -> Vec<String> {
+
+        if self.urls.is_empty() {
+            return vec![]
+        }
+
+        let start: usize = match self.round_robin {
+            true => self.next.fetch_add(1, Ordering::Relaxed) % self.urls.len(),
+            false => 0
+        };
+
+        let health = self.health();
+        let mut order: Vec<usize> = (0..self.urls.len())
+            .map(|i| (start + i) % self.urls.len())
+            .collect();
+        order.sort_by_key(|i| !health[*i].is_healthy());
+
+        order.into_iter().map(|i| self.urls[i].clone()).collect()
+    }
+
+    pub(crate) fn record_success(&self, url: &str) {
+        self.update(url, |health| {
+            health.successes += 1;
+            health.consecutive_failures = 0;
+        });
+    }
+
+    pub(crate) fn record_failure(&self, url: &str, error: &Error) {
+        self.update(url, |health| {
+            health.failures += 1;
+            health.consecutive_failures += 1;
+            health.last_error = Some(error.to_string());
+        });
+    }
+
+    fn update(&self, url: &str, f: impl FnOnce(&mut EndpointHealth)) {
+        let mut health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(health) = health.iter_mut().find(|h| h.url == url) {
+            f(health);
+        }
+    }
+}
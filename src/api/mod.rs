@@ -1,6 +1,7 @@
 //! This module gives us all of the tools needed for interacting with the Overpass / OSM API
-//! endpoint. This crate relies on [reqwest](https://docs.rs/reqwest/latest/reqwest/) to make this
-//! possible but conviently hides the details of making HTTP requests to the endpoint.
+//! endpoint. By default this crate relies on [reqwest](https://docs.rs/reqwest/latest/reqwest/) to
+//! make this possible but conviently hides the details of making HTTP requests to the endpoint.
+//! Requests can instead go through any [`crate::api::transport::Transport`].
 //!
 //! The API module provides to primary tools for the developer: the
 //! [`crate::api::query_engine::QueryEngine`] and the
@@ -15,9 +16,56 @@
 //! can automatically parse json strings into structures, provided the structures have a shape that
 //! matches the json. The response type from Overpass is very regular, so we can leverage this to
 //! our advantage. If you are not using the Overpass API, you don't need this structure.
+//!
+//! Data that is already on disk can be read without the API: [`crate::api::stream`] reads saved
+//! json responses one element at a time, and [`crate::api::osm_xml`] reads OSM XML, such as `.osm`
+//! files exported from JOSM. With the `pbf` feature, [`crate::api::pbf`] reads `.osm.pbf` extracts.
 
 pub mod query_engine;
 pub use query_engine::*;
 
 pub mod overpass_response;
 pub use overpass_response::*;
+
+pub mod tags;
+pub use tags::*;
+
+pub mod retry;
+pub use retry::RetryPolicy;
+
+pub mod endpoint;
+pub use endpoint::EndpointHealth;
+
+pub mod query_builder;
+pub use query_builder::*;
+
+pub mod way_filter;
+pub use way_filter::{WayFilter, WayMatcher};
+
+pub mod network_type;
+pub use network_type::NetworkType;
+
+pub mod stream;
+pub use stream::{ElementReader, AsyncElementReader};
+
+pub mod polygon;
+pub use polygon::Polygon;
+
+pub mod tiles;
+pub use tiles::{TileOptions, TileProgress, TileStatus};
+
+pub mod cache;
+pub use cache::{QueryCache, CacheEntry};
+
+pub mod transport;
+pub use transport::{Transport, TransportFuture, HttpRequest, HttpResponse, Method};
+#[cfg(feature = "reqwest")]
+pub use transport::ReqwestTransport;
+
+pub mod osm_xml;
+pub use osm_xml::OsmXmlReader;
+
+#[cfg(feature = "pbf")]
+pub mod pbf;
+#[cfg(feature = "pbf")]
+pub use pbf::PbfReader;
@@ -0,0 +1,110 @@
+use crate::api::{TagFilter, WayFilter};
+
+/// `NetworkType` is a preset [`WayFilter`] for a kind of traveller, along the lines of the
+/// `network_type` of [osmnx](https://osmnx.readthedocs.io). Each preset keeps the ways with a
+/// `highway` tag that the traveller can use, leaving out areas, ways that are not built yet or no
+/// longer exist, and private ways. Give it to [`crate::api::QueryEngine::with_network_type`], or
+/// build a graph from data you already have with [`crate::graph::create_graph_with_filter`].
+///
+/// ```rust
+/// use osmgraph::api::{NetworkType, OverpassResponse};
+/// use osmgraph::graph::{OSMGraph, create_graph_with_filter};
+///
+/// let response: OverpassResponse = OverpassResponse::load_blocking("./assets/test.json")
+///     .expect("Was not able to load json!");
+///
+/// let graph: OSMGraph = create_graph_with_filter(response.elements(), &NetworkType::Walk.way_filter())
+///     .expect("Was not able to create the graph!");
+///
+/// assert!(graph.edge_weights().any(|edge| edge.highway_type() == "footway"));
+/// assert!(graph.edge_weights().all(|edge| edge.highway_type() != "motorway"));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum NetworkType {
+    /// Public roads that can be driven on, without service roads.
+    Drive,
+    /// Public roads that can be driven on, along with service roads other than parking aisles,
+    /// driveways into private property and emergency access.
+    Service,
+    /// Everything that can be walked on, such as footways, paths, steps, pedestrian streets and
+    /// living streets, along with roads other than motorways. Ways tagged `foot=no` are left out.
+    Walk,
+    /// Everything that can be cycled on, such as cycleways, paths and roads other than
+    /// motorways. Ways tagged `bicycle=no` are left out.
+    Bike,
+    /// Every public way with a `highway` tag.
+    All
+}
+
+impl NetworkType {
+
+    /// Get the [`WayFilter`] of the preset.
+    pub fn way_filter(&self) -> WayFilter {
+
+        //Values of the highway tag that are left out of the network
+        let excluded: &[&str] = match self {
+            NetworkType::Drive => &[
+                "abandoned", "bridleway", "bus_guideway", "construction", "corridor", "cycleway",
+                "elevator", "escalator", "footway", "no", "path", "pedestrian", "planned", "platform",
+                "proposed", "raceway", "razed", "service", "steps", "track"
+            ],
+            NetworkType::Service => &[
+                "abandoned", "bridleway", "bus_guideway", "construction", "corridor", "cycleway",
+                "elevator", "escalator", "footway", "no", "path", "pedestrian", "planned", "platform",
+                "proposed", "raceway", "razed", "steps", "track"
+            ],
+            NetworkType::Walk => &[
+                "abandoned", "bus_guideway", "construction", "cycleway", "motorway", "motorway_link",
+                "no", "planned", "platform", "proposed", "raceway", "razed"
+            ],
+            NetworkType::Bike => &[
+                "abandoned", "bus_guideway", "construction", "corridor", "elevator", "escalator",
+                "footway", "motorway", "motorway_link", "no", "planned", "platform", "proposed",
+                "raceway", "razed", "steps"
+            ],
+            NetworkType::All => &[
+                "abandoned", "construction", "no", "planned", "platform", "proposed", "raceway", "razed"
+            ]
+        };
+
+        let mut filters: Vec<WayFilter> = vec![
+            WayFilter::tag(TagFilter::exists("highway")),
+            WayFilter::tag(TagFilter::not_equals("area", "yes")),
+            WayFilter::tag(TagFilter::not_regex("highway", &format!("^({})$", excluded.join("|")))),
+            WayFilter::tag(TagFilter::not_equals("access", "private")),
+        ];
+
+        //Tags that close a way to the traveller, and kinds of service road they have no use for
+        filters.extend(match self {
+            NetworkType::Drive => vec![
+                TagFilter::not_equals("motor_vehicle", "no"),
+                TagFilter::not_equals("motorcar", "no"),
+                TagFilter::not_regex("service", "^(alley|driveway|emergency_access|parking|parking_aisle|private)$")
+            ],
+            NetworkType::Service => vec![
+                TagFilter::not_equals("motor_vehicle", "no"),
+                TagFilter::not_equals("motorcar", "no"),
+                TagFilter::not_regex("service", "^(driveway|emergency_access|parking|parking_aisle|private)$")
+            ],
+            NetworkType::Walk => vec![
+                TagFilter::not_equals("foot", "no"),
+                TagFilter::not_equals("service", "private")
+            ],
+            NetworkType::Bike => vec![
+                TagFilter::not_equals("bicycle", "no"),
+                TagFilter::not_equals("service", "private")
+            ],
+            NetworkType::All => vec![
+                TagFilter::not_equals("service", "private")
+            ]
+        }.into_iter().map(WayFilter::tag));
+
+        WayFilter::all(filters)
+    }
+}
+
+impl From<NetworkType> for WayFilter {
+    fn from(network_type: NetworkType) -> Self {
+        network_type.way_filter()
+    }
+}
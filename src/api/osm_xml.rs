@@ -0,0 +1,550 @@
+//! Reading the OSM XML format, as written by Overpass (`[out:xml]`), JOSM and the main OSM API,
+//! and the `<action>` blocks that Overpass writes for `[diff:]` and `[adiff:]` queries.
+//!
+//! A whole document can be read into an [`crate::api::OverpassResponse`] with
+//! [`crate::api::OverpassResponse::from_xml`] or [`crate::api::OverpassResponse::load_xml`], and
+//! a document that is too large for that can be read one element at a time with an
+//! [`OsmXmlReader`]. Either way the elements are the same as the ones in a json response, tags
+//! and metadata included, so everything in [`crate::graph`] works on them.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::Error;
+use crate::api::{Bounds, Coordinate, Element, Metadata, MemberType, RelationMember, Tags};
+use crate::api::overpass_response::is_runtime_error;
+
+/// What happened to an element between the two dates of a diff query.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum ActionType {
+    Create,
+    Modify,
+    Delete
+}
+
+/// One `<action>` block of a diff. `old` holds the elements inside `<old>` and `new` the elements
+/// inside `<new>`, or directly inside the action. Elements marked `visible="false"`, which
+/// Overpass writes as the new version of a deleted element, are left out.
+pub(crate) struct Action {
+    pub(crate) action: ActionType,
+    pub(crate) old: Vec<Element>,
+    pub(crate) new: Vec<Element>
+}
+
+/// The actions of a diff, along with the `osm_base` timestamp.
+pub(crate) struct OsmDiff {
+    pub(crate) actions: Vec<Action>,
+    pub(crate) osm_base: Option<String>
+}
+
+/// Read the `<action>` blocks of the answer to a `[diff:]` or `[adiff:]` query.
+pub(crate) fn parse_diff(xml: &[u8]) -> Result<OsmDiff, Error> {
+
+    let mut parser = Parser::new(xml);
+    let mut actions: Vec<Action> = vec![];
+    while let Some(item) = parser.next_item()? {
+        if let Item::Action(action) = item {
+            actions.push(action);
+        }
+    }
+
+    Ok(OsmDiff { actions, osm_base: parser.header.osm_base })
+}
+
+/// `OsmXmlReader` reads the nodes, ways and relations of an OSM XML document one at a time from
+/// any [`BufRead`], such as a `.osm` file exported from JOSM or the body of an `[out:xml]`
+/// Overpass response. Only the element being read is held in memory, so it can be given to
+/// [`crate::graph::create_graph_from_stream`] to build a graph out of a file that is much larger
+/// than the graph.
+///
+/// The reader is an iterator of `Result<Element, Error>`. XML that does not parse, including a
+/// document that ends before all of its tags are closed, returns an [`Error::Parse`] and ends the
+/// iterator. Elements that JOSM marks as deleted
+/// (`action="delete"`) or that are not visible are left out. Objects that were created in JOSM
+/// and never uploaded have negative IDs, which are not valid here and return an error. If the
+/// `<remark>` of an Overpass response reports a runtime error, the last item is an
+/// [`Error::Incomplete`], since the elements before it are only part of the result.
+///
+/// The attributes of the document, such as the `osm_base` timestamp that Overpass writes in a
+/// `<meta>` element, can be read once the reader has got past them.
+///
+/// ```rust
+/// use osmgraph::api::OsmXmlReader;
+/// use osmgraph::graph::{OSMGraph, create_graph_from_stream};
+///
+/// let xml = br#"<?xml version="1.0" encoding="UTF-8"?>
+/// <osm version="0.6" generator="JOSM">
+///   <node id="1" lat="40.0" lon="-76.0"/>
+///   <node id="2" lat="40.001" lon="-76.0"/>
+///   <way id="10" version="3" user="someone">
+///     <nd ref="1"/>
+///     <nd ref="2"/>
+///     <tag k="highway" v="residential"/>
+///   </way>
+/// </osm>"#;
+///
+/// let mut reader = OsmXmlReader::new(&xml[..]);
+/// let graph: OSMGraph = create_graph_from_stream(reader.by_ref())
+///     .expect("Was not able to create the graph!");
+///
+/// assert_eq!(graph.edge_count(), 1);
+/// assert_eq!(reader.generator(), Some("JOSM"));
+/// ```
+pub struct OsmXmlReader<R> {
+    parser: Parser<R>,
+    finished: bool
+}
+
+impl<R: BufRead> OsmXmlReader<R> {
+
+    /// Create a reader of the document in `reader`.
+    pub fn new(reader: R) -> Self {
+        Self { parser: Parser::new(reader), finished: false }
+    }
+}
+
+impl OsmXmlReader<BufReader<File>> {
+
+    /// Open a file to read the elements of.
+    pub fn open(filepath: &str) -> Result<Self, Error> {
+        Ok(Self::new(BufReader::new(File::open(filepath)?)))
+    }
+}
+
+impl<R> OsmXmlReader<R> {
+
+    /// Get the `version` attribute of the `<osm>` element, if it has been read.
+    pub fn version(&self) -> Option<&str> {
+        self.parser.header.version.as_deref()
+    }
+    /// Get the `generator` attribute of the `<osm>` element, if it has been read.
+    pub fn generator(&self) -> Option<&str> {
+        self.parser.header.generator.as_deref()
+    }
+    /// Get the `osm_base` timestamp that Overpass writes in a `<meta>` element, if it has been
+    /// read.
+    pub fn osm_base(&self) -> Option<&str> {
+        self.parser.header.osm_base.as_deref()
+    }
+    /// Get the text of the `<remark>` that Overpass writes when something went wrong while running
+    /// the query, if it has been read. Overpass writes it after the elements.
+    pub fn remark(&self) -> Option<&str> {
+        self.parser.header.remark.as_deref()
+    }
+}
+
+impl<R: BufRead> Iterator for OsmXmlReader<R> {
+    type Item = Result<Element, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            match self.parser.next_item() {
+                Ok(Some(Item::Element(element))) => return Some(Ok(element)),
+                Ok(Some(Item::Action(_))) => {},
+                Ok(None) => {
+                    self.finished = true;
+                    //Overpass gives up part way and says so in a remark after the elements
+                    if let Some(remark) = self.remark().filter(|remark| is_runtime_error(remark)) {
+                        return Some(Err(Error::Incomplete { remark: remark.to_string(), body: String::new() }))
+                    }
+                },
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e))
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The attributes of a document, as opposed to its elements.
+#[derive(Default)]
+pub(crate) struct Header {
+    pub(crate) version: Option<String>,
+    pub(crate) generator: Option<String>,
+    pub(crate) osm_base: Option<String>,
+    pub(crate) remark: Option<String>
+}
+
+/// Read a whole document, keeping the elements outside of any action along with the attributes of
+/// the document.
+pub(crate) fn parse(xml: &[u8]) -> Result<(Vec<Element>, Header), Error> {
+
+    let mut parser = Parser::new(xml);
+    let mut elements: Vec<Element> = vec![];
+    while let Some(item) = parser.next_item()? {
+        if let Item::Element(element) = item {
+            elements.push(element);
+        }
+    }
+
+    Ok((elements, parser.header))
+}
+
+/// Keeps track of the lines that have been read, so that errors can say where they are. Only the
+/// lines of the event being read are kept, since errors are always about that event.
+struct Lines<R> {
+    inner: R,
+    offset: u64,
+
+    //The line at the start of the event, where that line starts, and any newlines since
+    line: usize,
+    line_start: u64,
+    newlines: Vec<u64>
+}
+
+impl<R> Lines<R> {
+
+    fn new(inner: R) -> Self {
+        Self { inner, offset: 0, line: 1, line_start: 0, newlines: vec![] }
+    }
+
+    /// Forget about the newlines of the last event.
+    fn mark(&mut self) {
+        if let Some(last) = self.newlines.last() {
+            self.line += self.newlines.len();
+            self.line_start = last + 1;
+            self.newlines.clear();
+        }
+    }
+
+    /// Get the line and column of a position in the event being read.
+    fn position(&self, position: u64) -> (usize, usize) {
+        let newlines: usize = self.newlines.iter().take_while(|newline| **newline < position).count();
+        let line_start: u64 = match newlines {
+            0 => self.line_start,
+            n => self.newlines[n - 1] + 1
+        };
+        (self.line + newlines, (position.saturating_sub(line_start) + 1) as usize)
+    }
+}
+
+impl<R: BufRead> Read for Lines<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available: &[u8] = self.fill_buf()?;
+        let read: usize = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Lines<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        //The bytes are already buffered, so this does not read anything
+        if let Ok(available) = self.inner.fill_buf() {
+            let offset: u64 = self.offset;
+            self.newlines.extend(available[..amount.min(available.len())]
+                .iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte == b'\n')
+                .map(|(i, _)| offset + i as u64));
+        }
+        self.offset += amount as u64;
+        self.inner.consume(amount);
+    }
+}
+
+/// An element that has been opened but not closed yet, along with its child elements so far.
+struct Open {
+    name: String,
+    attributes: HashMap<String, String>,
+    tags: Vec<(String, String)>,
+    nodes: Vec<u64>,
+    geometry: Vec<Option<Coordinate>>,
+    members: Vec<RelationMember>,
+    bounds: Option<Bounds>,
+    center: Option<Coordinate>,
+    line: usize,
+    column: usize
+}
+
+impl Open {
+
+    fn error(&self, message: String) -> Error {
+        Error::Parse { message, line: self.line, column: self.column }
+    }
+
+    fn required<T: std::str::FromStr>(&self, key: &str) -> Result<T, Error> {
+        self.attributes.get(key)
+            .ok_or_else(|| self.error(format!("<{}> is missing `{key}`", self.name)))?
+            .parse()
+            .map_err(|_| self.error(format!("<{}> has an invalid `{key}`", self.name)))
+    }
+
+    fn optional<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        self.attributes.get(key).and_then(|value| value.parse().ok())
+    }
+
+    fn is_visible(&self) -> bool {
+        self.attributes.get("visible").is_none_or(|visible| visible != "false")
+    }
+
+    /// JOSM keeps elements that were deleted in the editor, marked with `action="delete"`.
+    fn is_deleted(&self) -> bool {
+        self.attributes.get("action").is_some_and(|action| action == "delete")
+    }
+
+    fn into_element(self) -> Result<Element, Error> {
+
+        let id: u64 = self.required("id")?;
+        let meta = Metadata::new(
+            self.optional("version"),
+            self.attributes.get("timestamp").cloned(),
+            self.optional("changeset"),
+            self.attributes.get("user").cloned(),
+            self.optional("uid")
+        );
+        let tags: Option<Tags> = match self.tags.is_empty() {
+            true => None,
+            false => Some(self.tags.iter().cloned().collect())
+        };
+
+        Ok(match self.name.as_str() {
+            "node" => Element::Node {
+                id,
+                lat: self.required("lat")?,
+                lon: self.required("lon")?,
+                tags,
+                meta
+            },
+            "way" => Element::Way {
+                id,
+                nodes: self.nodes,
+                tags,
+                bounds: self.bounds,
+                center: self.center,
+                //Augmented diffs and `out geom` give the coordinates of every node of a way
+                geometry: match self.geometry.iter().any(|coordinate| coordinate.is_some()) {
+                    true => Some(self.geometry),
+                    false => None
+                },
+                meta
+            },
+            _ => Element::Relation {
+                id,
+                members: self.members,
+                tags,
+                bounds: self.bounds,
+                center: self.center,
+                meta
+            }
+        })
+    }
+}
+
+/// Something complete that was read out of a document.
+enum Item {
+    Element(Element),
+    Action(Action)
+}
+
+/// Reads a document one event at a time, putting together elements and actions.
+struct Parser<R> {
+    reader: Reader<Lines<R>>,
+    buffer: Vec<u8>,
+    header: Header,
+    open: Option<Open>,
+
+    //The action being read, and whether we are inside its <old> block
+    action: Option<Action>,
+    old: bool,
+
+    //Whether we are inside a <remark>
+    remark: bool,
+
+    //Names of the tags that have been opened but not closed yet
+    parents: Vec<String>
+}
+
+impl<R: BufRead> Parser<R> {
+
+    fn new(reader: R) -> Self {
+        Self {
+            reader: Reader::from_reader(Lines::new(reader)),
+            buffer: vec![],
+            header: Header::default(),
+            open: None,
+            action: None,
+            old: false,
+            remark: false,
+            parents: vec![]
+        }
+    }
+
+    /// Turn a position in the event being read into an [`Error::Parse`].
+    fn error(&self, position: u64, message: String) -> Error {
+        let (line, column) = self.reader.get_ref().position(position);
+        Error::Parse { message, line, column }
+    }
+
+    /// Collect the attributes of a start tag.
+    fn attributes(&self, position: u64, tag: &BytesStart) -> Result<HashMap<String, String>, Error> {
+        tag.attributes()
+            .map(|attribute| {
+                let attribute = attribute.map_err(|e| self.error(position, e.to_string()))?;
+                let key: String = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+                let value: String = attribute.unescape_value()
+                    .map_err(|e| self.error(position, e.to_string()))?
+                    .into_owned();
+                Ok((key, value))
+            })
+            .collect()
+    }
+
+    /// Read until an element outside of any action or a whole action has been read, or the
+    /// document ends.
+    fn next_item(&mut self) -> Result<Option<Item>, Error> {
+        loop {
+            self.buffer.clear();
+            self.reader.get_mut().mark();
+
+            let position: u64 = self.reader.buffer_position();
+            let event = match self.reader.read_event_into(&mut self.buffer) {
+                Ok(event) => event.into_owned(),
+                Err(e) => return Err(self.error(self.reader.error_position(), e.to_string()))
+            };
+
+            //An element that has been closed, if any
+            let mut closed: Option<Open> = None;
+
+            match event {
+                Event::Start(ref tag) | Event::Empty(ref tag) => {
+                    let name: String = String::from_utf8_lossy(tag.name().as_ref()).into_owned();
+                    let attributes: HashMap<String, String> = self.attributes(position, tag)?;
+                    let is_empty: bool = matches!(event, Event::Empty(_));
+                    if !is_empty {
+                        self.parents.push(name.clone());
+                    }
+                    let (line, column) = self.reader.get_ref().position(position);
+                    let error = |message: &str| Error::Parse { message: message.to_string(), line, column };
+                    let coordinate = || {
+                        let lat: Option<f64> = attributes.get("lat").and_then(|lat| lat.parse().ok());
+                        let lon: Option<f64> = attributes.get("lon").and_then(|lon| lon.parse().ok());
+                        lat.zip(lon).map(|(lat, lon)| Coordinate::new(lat, lon))
+                    };
+
+                    match (name.as_str(), self.open.as_mut()) {
+                        ("node" | "way" | "relation", None) => {
+                            let element = Open {
+                                name, attributes, tags: vec![], nodes: vec![], geometry: vec![], members: vec![],
+                                bounds: None, center: None, line, column
+                            };
+                            match is_empty {
+                                true => closed = Some(element),
+                                false => self.open = Some(element)
+                            }
+                        },
+                        ("tag", Some(element)) => {
+                            if let (Some(k), Some(v)) = (attributes.get("k"), attributes.get("v")) {
+                                element.tags.push((k.clone(), v.clone()));
+                            }
+                        },
+                        ("nd", Some(element)) => {
+                            let node: u64 = attributes.get("ref")
+                                .and_then(|r| r.parse().ok())
+                                .ok_or_else(|| error("<nd> has no valid `ref`"))?;
+                            element.nodes.push(node);
+                            element.geometry.push(coordinate());
+                        },
+                        ("member", Some(element)) => {
+                            let member_type: MemberType = match attributes.get("type").map(|t| t.as_str()) {
+                                Some("node") => MemberType::Node,
+                                Some("way") => MemberType::Way,
+                                Some("relation") => MemberType::Relation,
+                                _ => return Err(error("<member> has no valid `type`"))
+                            };
+                            let reference: u64 = attributes.get("ref")
+                                .and_then(|r| r.parse().ok())
+                                .ok_or_else(|| error("<member> has no valid `ref`"))?;
+                            let role: String = attributes.get("role").cloned().unwrap_or_default();
+                            element.members.push(RelationMember::new(member_type, reference, role));
+                        },
+                        ("bounds", Some(element)) => {
+                            let get = |key: &str| attributes.get(key).and_then(|value| value.parse::<f64>().ok());
+                            if let (Some(minlat), Some(minlon), Some(maxlat), Some(maxlon)) =
+                                (get("minlat"), get("minlon"), get("maxlat"), get("maxlon")) {
+                                element.bounds = Some(Bounds::new(minlat, minlon, maxlat, maxlon));
+                            }
+                        },
+                        ("center", Some(element)) => element.center = coordinate(),
+                        ("osm", None) => {
+                            self.header.version = attributes.get("version").cloned();
+                            self.header.generator = attributes.get("generator").cloned();
+                        },
+                        ("meta", None) => {
+                            self.header.osm_base = attributes.get("osm_base").cloned();
+                        },
+                        ("remark", None) => self.remark = !is_empty,
+                        ("action", None) if !is_empty => {
+                            let action_type: ActionType = match attributes.get("type").map(|t| t.as_str()) {
+                                Some("create") => ActionType::Create,
+                                Some("modify") => ActionType::Modify,
+                                Some("delete") => ActionType::Delete,
+                                _ => return Err(error("<action> has no valid `type`"))
+                            };
+                            self.action = Some(Action { action: action_type, old: vec![], new: vec![] });
+                        },
+                        ("old", None) => self.old = !is_empty,
+                        ("new", None) => self.old = false,
+                        _ => {}
+                    }
+                },
+                Event::Text(ref text) if self.remark => {
+                    let text = text.unescape().map_err(|e| self.error(position, e.to_string()))?;
+                    let remark: &mut String = self.header.remark.get_or_insert_with(String::new);
+                    remark.push_str(&text);
+                    *remark = remark.trim().to_string();
+                },
+                Event::End(ref tag) => {
+                    self.parents.pop();
+                    let name = tag.name();
+                    if self.open.as_ref().is_some_and(|element| element.name.as_bytes() == name.as_ref()) {
+                        closed = self.open.take();
+                    } else if self.open.is_none() {
+                        match name.as_ref() {
+                            b"old" => self.old = false,
+                            b"remark" => self.remark = false,
+                            b"action" => {
+                                if let Some(action) = self.action.take() {
+                                    return Ok(Some(Item::Action(action)))
+                                }
+                            },
+                            _ => {}
+                        }
+                    }
+                },
+                Event::Eof => match self.parents.first() {
+                    Some(name) => return Err(self.error(position, format!("<{name}> is not closed before the end of the document"))),
+                    None => return Ok(None)
+                },
+                _ => {}
+            }
+
+            if let Some(element) = closed {
+                match self.action.as_mut() {
+                    None if element.is_visible() && !element.is_deleted() => {
+                        return Ok(Some(Item::Element(element.into_element()?)))
+                    },
+                    Some(action) if element.is_visible() => {
+                        let element: Element = element.into_element()?;
+                        match self.old {
+                            true => action.old.push(element),
+                            false => action.new.push(element)
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+}
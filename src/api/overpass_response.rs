@@ -1,15 +1,183 @@
-use std::io::Error;
-use std::collections::HashMap;
+use std::fmt;
+use std::collections::HashSet;
 
 use serde::{Serialize, Deserialize};
-use serde_json::Value;
+use serde_json::{Value, value::RawValue};
+
+use crate::api::{Tags, osm_xml};
+use crate::Error;
+use crate::runtime::block_on;
 
 use tokio::{
     fs::File,
     io::{AsyncWriteExt, AsyncReadExt},
-    runtime::Builder,
 };
 
+/// The type of element that a [`RelationMember`] refers to.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum MemberType {
+    Node,
+    Way,
+    Relation
+}
+
+/// A single member of an [`Element::Relation`]. Each member refers to another element by its
+/// type and ID (`ref` in the Overpass response) and gives it a role within the relation, such as
+/// `"from"`, `"via"` and `"to"` for turn restrictions or `"outer"` and `"inner"` for
+/// multipolygons. The role may be an empty string.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct RelationMember {
+    #[serde(rename = "type")]
+    member_type: MemberType,
+    #[serde(rename = "ref")]
+    reference: u64,
+    role: String
+}
+
+impl RelationMember {
+
+    /// Create a new `RelationMember` from fields.
+    pub fn new(member_type: MemberType, reference: u64, role: String) -> Self {
+        RelationMember { member_type, reference, role }
+    }
+
+    /// Get the type of element this member refers to.
+    pub fn member_type(&self) -> MemberType {
+        self.member_type
+    }
+    /// Get the ID of the element this member refers to.
+    pub fn reference(&self) -> u64 {
+        self.reference
+    }
+    /// Get the role of this member within the relation.
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+}
+
+/// `Metadata` holds the editing history that Overpass attaches to an element when a query ends
+/// with `out meta`. Every field is optional, since a plain `out body` leaves all of them out (and
+/// `user`/`uid` may be hidden depending on the server).
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Default)]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    changeset: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uid: Option<u64>
+}
+
+impl Metadata {
+
+    /// Create a new `Metadata` from fields.
+    pub fn new(
+        version: Option<u64>,
+        timestamp: Option<String>,
+        changeset: Option<u64>,
+        user: Option<String>,
+        uid: Option<u64>
+    ) -> Self {
+        Metadata { version, timestamp, changeset, user, uid }
+    }
+
+    /// Get the version of the element. This is incremented every time the element is edited.
+    pub fn version(&self) -> Option<u64> {
+        self.version
+    }
+    /// Get the time of the last edit as an ISO 8601 string, such as `"2024-11-18T15:04:05Z"`.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+    /// Get the ID of the changeset in which the element was last edited.
+    pub fn changeset(&self) -> Option<u64> {
+        self.changeset
+    }
+    /// Get the name of the user who last edited the element.
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+    /// Get the ID of the user who last edited the element.
+    pub fn uid(&self) -> Option<u64> {
+        self.uid
+    }
+    /// Returns true if none of the metadata fields are present.
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+}
+
+/// A single latitude/longitude pair. Overpass uses this shape for the `center` of an element
+/// (`out center`) and for each point of the inline `geometry` of a way (`out geom`).
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Copy, Default)]
+pub struct Coordinate {
+    lat: f64,
+    lon: f64
+}
+
+impl Coordinate {
+
+    /// Create a new `Coordinate` from fields.
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Coordinate { lat, lon }
+    }
+
+    /// Get the latitude.
+    pub fn lat(&self) -> f64 {
+        self.lat
+    }
+    /// Get the longitude.
+    pub fn lon(&self) -> f64 {
+        self.lon
+    }
+}
+
+/// The bounding box of an element, as returned by Overpass for `out bb` and `out geom`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Copy, Default)]
+pub struct Bounds {
+    minlat: f64,
+    minlon: f64,
+    maxlat: f64,
+    maxlon: f64
+}
+
+impl Bounds {
+
+    /// Create a new `Bounds` from fields.
+    pub fn new(minlat: f64, minlon: f64, maxlat: f64, maxlon: f64) -> Self {
+        Bounds { minlat, minlon, maxlat, maxlon }
+    }
+
+    /// Get the southern edge of the box.
+    pub fn minlat(&self) -> f64 {
+        self.minlat
+    }
+    /// Get the western edge of the box.
+    pub fn minlon(&self) -> f64 {
+        self.minlon
+    }
+    /// Get the northern edge of the box.
+    pub fn maxlat(&self) -> f64 {
+        self.maxlat
+    }
+    /// Get the eastern edge of the box.
+    pub fn maxlon(&self) -> f64 {
+        self.maxlon
+    }
+}
+
+/// `Element` is a single entry of the `elements` array of an Overpass response.
+///
+/// Ways and relations can carry extra shape information depending on the output mode of the
+/// query: `bounds` is filled in by `out bb` and `out geom`, `center` by `out center`, and the
+/// `geometry` of a way by `out geom`. The geometry of a way lines up with its `nodes`, so the
+/// i-th coordinate is the location of the i-th node. A coordinate is `None` when Overpass left it
+/// out, which happens when the geometry is clipped with `out geom(bbox)`.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(rename_all = "lowercase")] 
 #[serde(tag = "type")]
@@ -18,12 +186,54 @@ pub enum Element {
         id: u64,
         lat: f64,
         lon: f64,
-        tags: Option<HashMap<String, String>>
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tags: Option<Tags>,
+        #[serde(flatten)]
+        meta: Metadata
     }, 
     Way {
         id: u64,
         nodes: Vec<u64>,
-        tags: Option<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tags: Option<Tags>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bounds: Option<Bounds>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        center: Option<Coordinate>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        geometry: Option<Vec<Option<Coordinate>>>,
+        #[serde(flatten)]
+        meta: Metadata
+    },
+    Relation {
+        id: u64,
+        members: Vec<RelationMember>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tags: Option<Tags>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bounds: Option<Bounds>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        center: Option<Coordinate>,
+        #[serde(flatten)]
+        meta: Metadata
+    }
+}
+
+impl Element {
+
+    /// Get the ID of the element. IDs are only unique among elements of the same type.
+    pub fn id(&self) -> u64 {
+        match self {
+            Element::Node { id, .. } | Element::Way { id, .. } | Element::Relation { id, .. } => *id
+        }
+    }
+    /// Get the type of the element.
+    pub fn element_type(&self) -> MemberType {
+        match self {
+            Element::Node { .. } => MemberType::Node,
+            Element::Way { .. } => MemberType::Way,
+            Element::Relation { .. } => MemberType::Relation
+        }
     }
 }
 
@@ -31,7 +241,8 @@ pub enum Element {
 /// Serde JSON helps us parse this string into the correct data structure.
 ///
 /// Example:
-/// ```rust
+/// ```rust,no_run
+/// # #[cfg(feature = "reqwest")] {
 /// use osmgraph::api::{QueryEngine, OverpassResponse};
 ///
 /// let engine = QueryEngine::new();
@@ -51,6 +262,7 @@ pub enum Element {
 ///
 /// let json: OverpassResponse = serde_json::from_str(&response)
 ///     .expect("Was not able to parse json!");
+/// # }
 /// ```
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
 pub struct OverpassResponse {
@@ -61,11 +273,138 @@ pub struct OverpassResponse {
     //Metadata
     generator: Value,
     osm3s: Value,
-    version: Value
+    version: Value,
+
+    //Set by Overpass when something went wrong while running the query
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remark: Option<String>
+}
+
+/// `ElementDiagnostic` describes a single element that was skipped while parsing a response with
+/// [`OverpassResponse::from_str_lenient`]. It holds the index of the element within the
+/// `elements` array of the response and the reason it could not be parsed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ElementDiagnostic {
+    index: usize,
+    reason: String
+}
+
+impl ElementDiagnostic {
+
+    /// Get the index of the element within the `elements` array of the response.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    /// Get the reason the element was skipped.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl fmt::Display for ElementDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "element {}: {}", self.index, self.reason)
+    }
+}
+
+/// The same shape as `OverpassResponse`, but the elements are left unparsed so that they can be
+/// checked one at a time.
+#[derive(Deserialize)]
+struct LenientResponse<'a> {
+    #[serde(borrow)]
+    elements: Vec<&'a RawValue>,
+
+    #[serde(default)]
+    generator: Value,
+    #[serde(default)]
+    osm3s: Value,
+    #[serde(default)]
+    version: Value,
+    #[serde(default)]
+    remark: Option<String>
+}
+
+/// Just the `type` field of an element, used to explain why an element was skipped.
+#[derive(Deserialize)]
+struct ElementType {
+    #[serde(rename = "type")]
+    element_type: Option<String>
+}
+
+/// Returns true if a `remark` from Overpass reports that the query was aborted. Overpass also
+/// uses remarks for harmless notes (`runtime remark: ...`), which are not errors.
+pub(crate) fn is_runtime_error(remark: &str) -> bool {
+    remark.contains("runtime error")
+}
+
+/// Explain why `raw` could not be parsed as an [`Element`].
+fn diagnose(raw: &RawValue, error: serde_json::Error) -> String {
+    match serde_json::from_str::<ElementType>(raw.get()) {
+        Ok(ElementType { element_type: None }) => "element has no `type` field".to_string(),
+        Ok(ElementType { element_type: Some(t) }) if !matches!(t.as_str(), "node" | "way" | "relation") =>
+            format!("unknown element type `{t}`"),
+        _ => format!("malformed element: {error}")
+    }
 }
 
 impl OverpassResponse {
 
+    /// Parse an `OverpassResponse` from a json string without failing on individual elements.
+    ///
+    /// Parsing with `serde_json::from_str` fails for the whole response if a single element has a
+    /// type we do not know about (such as an `area` or a `count` row) or is missing a field (such
+    /// as a way without `nodes`). This function instead skips such elements and returns an
+    /// [`ElementDiagnostic`] for each of them alongside the rest of the response. An error is only
+    /// returned if the string is not json or does not contain an `elements` array.
+    ///
+    /// Example:
+    /// ```rust
+    /// use osmgraph::api::OverpassResponse;
+    ///
+    /// let (json, diagnostics) = OverpassResponse::from_str_lenient(r#"{
+    ///     "elements": [
+    ///         { "type": "node", "id": 1, "lat": 40.0, "lon": -76.0 },
+    ///         { "type": "area", "id": 3600000001 }
+    ///     ]
+    /// }"#).expect("Was not able to parse json!");
+    ///
+    /// assert_eq!(json.elements().len(), 1);
+    /// assert_eq!(diagnostics[0].index(), 1);
+    /// ```
+    pub fn from_str_lenient(json: &str) -> Result<(Self, Vec<ElementDiagnostic>), Error> {
+        Self::from_slice_lenient(json.as_bytes())
+    }
+
+    /// The bytes behind [`OverpassResponse::from_str_lenient`], so that files can be parsed
+    /// without copying them into a string first.
+    fn from_slice_lenient(json: &[u8]) -> Result<(Self, Vec<ElementDiagnostic>), Error> {
+
+        let raw: LenientResponse = serde_json::from_slice(json)?;
+
+        let mut elements: Vec<Element> = Vec::with_capacity(raw.elements.len());
+        let mut diagnostics: Vec<ElementDiagnostic> = vec![];
+
+        for (index, element) in raw.elements.into_iter().enumerate() {
+            match serde_json::from_str::<Element>(element.get()) {
+                Ok(e) => elements.push(e),
+                Err(error) => diagnostics.push(ElementDiagnostic {
+                    index,
+                    reason: diagnose(element, error)
+                })
+            }
+        }
+
+        let response = OverpassResponse {
+            elements,
+            generator: raw.generator,
+            osm3s: raw.osm3s,
+            version: raw.version,
+            remark: raw.remark
+        };
+
+        Ok((response, diagnostics))
+    }
+
     /// Return the `elements` field from the response. This field is the most important as it
     /// contains the actual graph information.
     pub fn elements(&self) -> &Vec<Element> {
@@ -83,6 +422,40 @@ impl OverpassResponse {
     pub fn version(&self) -> &Value {
         &self.version
     }
+    /// Return the `remark` field from the response. Overpass only sets this field when something
+    /// went wrong while running the query, such as `"runtime error: Query timed out in \"query\"
+    /// at line 3 after 26 seconds."`.
+    pub fn remark(&self) -> Option<&str> {
+        self.remark.as_deref()
+    }
+    /// Returns false if Overpass reported a runtime error in the `remark` of the response. In that
+    /// case the elements are only the part of the result that was output before the error.
+    pub fn is_complete(&self) -> bool {
+        !self.remark().is_some_and(is_runtime_error)
+    }
+
+    /// Merge several responses into one, such as the responses for the tiles of a large area.
+    /// Elements that appear in more than one response (for example a way that crosses the edge
+    /// between two tiles, along with its nodes) are only kept once, the first time they appear.
+    /// The metadata is taken from the first response, and the first `remark` found is kept.
+    pub fn merge(responses: impl IntoIterator<Item = OverpassResponse>) -> Self {
+
+        let mut merged: Option<OverpassResponse> = None;
+        let mut seen: HashSet<(MemberType, u64)> = HashSet::new();
+
+        for response in responses {
+            let OverpassResponse { elements, generator, osm3s, version, remark } = response;
+            let merged = merged.get_or_insert_with(|| OverpassResponse {
+                elements: vec![], generator, osm3s, version, remark: None
+            });
+
+            merged.remark = merged.remark.take().or(remark);
+            merged.elements.extend(elements.into_iter()
+                .filter(|element| seen.insert((element.element_type(), element.id()))));
+        }
+
+        merged.unwrap_or_default()
+    }
 
     /// Given a specified `filepath`, save the OverpassResponse to that location.
     pub async fn save(&self, filepath: &str) -> Result<(), Error> {
@@ -98,14 +471,14 @@ impl OverpassResponse {
 
     /// Behaves the same as [`OverpassResponse::save`], but will wait for the function to finish before continuing.
     pub fn save_blocking(&self, filepath: &str) -> Result<(), Error> {
-        Builder::new_current_thread()
-            .enable_all()
-            .build()?
-            .block_on(self.save(filepath))
+        block_on(self.save(filepath))
     }
 
     /// Given a specified `filepath`, load the OverpassResponse from that location. The file is
     /// assumed to be a JSON and follow the structure of OverpassResponse.
+    ///
+    /// The whole file is held in memory while it is parsed. To read files that are too large for
+    /// that, use [`crate::api::ElementReader`] instead.
     pub async fn load(filepath: &str) -> Result<Self, Error> {
 
         let mut file = File::open(filepath).await?;
@@ -115,18 +488,94 @@ impl OverpassResponse {
         // Read the file's contents into the buffer
         file.read_to_end(&mut contents).await?;
 
-        let contents_as_string: String = String::from_utf8_lossy(&contents).to_string();
-
-        let json: OverpassResponse = serde_json::from_str(&contents_as_string)?;
+        let json: OverpassResponse = serde_json::from_slice(&contents)?;
 
         Ok(json)
     }
 
     /// Behaves the same as [`OverpassResponse::load`], but will wait for the function to finish before continuing.
     pub fn load_blocking(filepath: &str) -> Result<Self, Error> {
-        Builder::new_current_thread()
-            .enable_all()
-            .build()?
-            .block_on(Self::load(filepath))
+        block_on(Self::load(filepath))
+    }
+
+    /// Read a response out of an OSM XML document, such as the answer to an `[out:xml]` query or
+    /// a `.osm` file exported from JOSM. The elements are the same as they would be in json, tags
+    /// and metadata included. The `version` and `generator` come from the `<osm>` element, and the
+    /// `osm_base` timestamp that Overpass writes in a `<meta>` element is put in `osm3s`.
+    ///
+    /// See [`crate::api::OsmXmlReader`] for what is left out, and for reading documents that are
+    /// too large to hold in memory.
+    ///
+    /// ```rust
+    /// use osmgraph::api::OverpassResponse;
+    /// use osmgraph::graph::{OSMGraph, create_graph};
+    ///
+    /// let response = OverpassResponse::from_xml(br#"<osm version="0.6" generator="Overpass API">
+    ///   <meta osm_base="2024-11-18T00:00:00Z"/>
+    ///   <node id="1" lat="40.0" lon="-76.0"/>
+    ///   <node id="2" lat="40.001" lon="-76.0"/>
+    ///   <way id="10"><nd ref="1"/><nd ref="2"/><tag k="highway" v="residential"/></way>
+    /// </osm>"#).expect("Was not able to parse xml!");
+    ///
+    /// assert_eq!(response.osm3s()["timestamp_osm_base"], "2024-11-18T00:00:00Z");
+    ///
+    /// let graph: OSMGraph = create_graph(response.elements()).expect("Was not able to create the graph!");
+    /// assert_eq!(graph.edge_count(), 1);
+    /// ```
+    pub fn from_xml(xml: &[u8]) -> Result<Self, Error> {
+
+        let (elements, header) = osm_xml::parse(xml)?;
+
+        let mut osm3s = serde_json::Map::new();
+        if let Some(osm_base) = header.osm_base {
+            osm3s.insert("timestamp_osm_base".to_string(), Value::String(osm_base));
+        }
+
+        Ok(OverpassResponse {
+            elements,
+            generator: header.generator.map_or(Value::Null, Value::String),
+            osm3s: Value::Object(osm3s),
+            //Json responses give the version as a number
+            version: header.version.map_or(Value::Null, |version| match version.parse::<f64>() {
+                Ok(number) => serde_json::json!(number),
+                Err(_) => Value::String(version)
+            }),
+            remark: header.remark
+        })
+    }
+
+    /// Given a specified `filepath`, load the OverpassResponse from an OSM XML file, such as a
+    /// `.osm` file. See [`OverpassResponse::from_xml`].
+    pub async fn load_xml(filepath: &str) -> Result<Self, Error> {
+
+        let mut file = File::open(filepath).await?;
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).await?;
+
+        Self::from_xml(&contents)
+    }
+
+    /// Behaves the same as [`OverpassResponse::load_xml`], but will wait for the function to finish before continuing.
+    pub fn load_xml_blocking(filepath: &str) -> Result<Self, Error> {
+        block_on(Self::load_xml(filepath))
+    }
+
+    /// Behaves the same as [`OverpassResponse::load`], but parses the file with
+    /// [`OverpassResponse::from_str_lenient`] so that unknown or malformed elements are skipped
+    /// and reported rather than failing the whole load.
+    pub async fn load_lenient(filepath: &str) -> Result<(Self, Vec<ElementDiagnostic>), Error> {
+
+        let mut file = File::open(filepath).await?;
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).await?;
+
+        Self::from_slice_lenient(&contents)
+    }
+
+    /// Behaves the same as [`OverpassResponse::load_lenient`], but will wait for the function to finish before continuing.
+    pub fn load_lenient_blocking(filepath: &str) -> Result<(Self, Vec<ElementDiagnostic>), Error> {
+        block_on(Self::load_lenient(filepath))
     }
 }
@@ -0,0 +1,763 @@
+//! Reading OSM PBF files, the binary format of the extracts that Geofabrik and others publish
+//! (`.osm.pbf`). A PBF file is a series of blocks of about 8000 elements each, compressed one by
+//! one, so it can be read a block at a time without ever holding the whole file in memory.
+//!
+//! [`PbfReader`] gives the elements of a file one at a time, the same as they would be in an
+//! Overpass response, and [`crate::graph::create_graph_from_pbf`] builds a graph straight from a
+//! file. Only blocks that are stored raw or compressed with zlib can be read, which is what every
+//! common tool writes.
+//!
+//! This module needs the `pbf` feature.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+use flate2::read::ZlibDecoder;
+use prost::Message;
+
+use crate::Error;
+use crate::api::{Element, Metadata, MemberType, RelationMember, Tags};
+
+/// The largest blob header and blob that the format allows.
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+const MAX_BLOB_SIZE: usize = 32 * 1024 * 1024;
+
+/// The features a file can require that we know how to read.
+const SUPPORTED_FEATURES: [&str; 2] = ["OsmSchema-V0.6", "DenseNodes"];
+
+//The messages of the format, as defined in `fileformat.proto` and `osmformat.proto`
+
+#[derive(Clone, PartialEq, Message)]
+struct BlobHeader {
+    #[prost(string, tag = "1")]
+    r#type: String,
+    #[prost(int32, tag = "3")]
+    datasize: i32
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Blob {
+    #[prost(bytes = "vec", optional, tag = "1")]
+    raw: Option<Vec<u8>>,
+    #[prost(int32, optional, tag = "2")]
+    raw_size: Option<i32>,
+    #[prost(bytes = "vec", optional, tag = "3")]
+    zlib_data: Option<Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "4")]
+    lzma_data: Option<Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "6")]
+    lz4_data: Option<Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "7")]
+    zstd_data: Option<Vec<u8>>
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct HeaderBlock {
+    #[prost(string, repeated, tag = "4")]
+    required_features: Vec<String>,
+    #[prost(string, optional, tag = "16")]
+    writingprogram: Option<String>,
+    #[prost(int64, optional, tag = "32")]
+    osmosis_replication_timestamp: Option<i64>
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct PrimitiveBlock {
+    #[prost(message, optional, tag = "1")]
+    stringtable: Option<StringTable>,
+    #[prost(message, repeated, tag = "2")]
+    primitivegroup: Vec<PrimitiveGroup>,
+    #[prost(int32, optional, tag = "17")]
+    granularity: Option<i32>,
+    #[prost(int32, optional, tag = "18")]
+    date_granularity: Option<i32>,
+    #[prost(int64, optional, tag = "19")]
+    lat_offset: Option<i64>,
+    #[prost(int64, optional, tag = "20")]
+    lon_offset: Option<i64>
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct StringTable {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    s: Vec<Vec<u8>>
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct PrimitiveGroup {
+    #[prost(message, repeated, tag = "1")]
+    nodes: Vec<Node>,
+    #[prost(message, optional, tag = "2")]
+    dense: Option<DenseNodes>,
+    #[prost(message, repeated, tag = "3")]
+    ways: Vec<Way>,
+    #[prost(message, repeated, tag = "4")]
+    relations: Vec<Relation>
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Info {
+    #[prost(int32, optional, tag = "1")]
+    version: Option<i32>,
+    #[prost(int64, optional, tag = "2")]
+    timestamp: Option<i64>,
+    #[prost(int64, optional, tag = "3")]
+    changeset: Option<i64>,
+    #[prost(int32, optional, tag = "4")]
+    uid: Option<i32>,
+    #[prost(uint32, optional, tag = "5")]
+    user_sid: Option<u32>,
+    #[prost(bool, optional, tag = "6")]
+    visible: Option<bool>
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Node {
+    #[prost(sint64, tag = "1")]
+    id: i64,
+    #[prost(uint32, repeated, tag = "2")]
+    keys: Vec<u32>,
+    #[prost(uint32, repeated, tag = "3")]
+    vals: Vec<u32>,
+    #[prost(message, optional, tag = "4")]
+    info: Option<Info>,
+    #[prost(sint64, tag = "8")]
+    lat: i64,
+    #[prost(sint64, tag = "9")]
+    lon: i64
+}
+
+/// Nodes stored column by column, with ids, coordinates and most of the metadata delta coded.
+#[derive(Clone, PartialEq, Message)]
+struct DenseNodes {
+    #[prost(sint64, repeated, tag = "1")]
+    id: Vec<i64>,
+    #[prost(message, optional, tag = "5")]
+    denseinfo: Option<DenseInfo>,
+    #[prost(sint64, repeated, tag = "8")]
+    lat: Vec<i64>,
+    #[prost(sint64, repeated, tag = "9")]
+    lon: Vec<i64>,
+    #[prost(int32, repeated, tag = "10")]
+    keys_vals: Vec<i32>
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct DenseInfo {
+    #[prost(int32, repeated, tag = "1")]
+    version: Vec<i32>,
+    #[prost(sint64, repeated, tag = "2")]
+    timestamp: Vec<i64>,
+    #[prost(sint64, repeated, tag = "3")]
+    changeset: Vec<i64>,
+    #[prost(sint32, repeated, tag = "4")]
+    uid: Vec<i32>,
+    #[prost(sint32, repeated, tag = "5")]
+    user_sid: Vec<i32>,
+    #[prost(bool, repeated, tag = "6")]
+    visible: Vec<bool>
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Way {
+    #[prost(int64, tag = "1")]
+    id: i64,
+    #[prost(uint32, repeated, tag = "2")]
+    keys: Vec<u32>,
+    #[prost(uint32, repeated, tag = "3")]
+    vals: Vec<u32>,
+    #[prost(message, optional, tag = "4")]
+    info: Option<Info>,
+    #[prost(sint64, repeated, tag = "8")]
+    refs: Vec<i64>
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Relation {
+    #[prost(int64, tag = "1")]
+    id: i64,
+    #[prost(uint32, repeated, tag = "2")]
+    keys: Vec<u32>,
+    #[prost(uint32, repeated, tag = "3")]
+    vals: Vec<u32>,
+    #[prost(message, optional, tag = "4")]
+    info: Option<Info>,
+    #[prost(int32, repeated, tag = "8")]
+    roles_sid: Vec<i32>,
+    #[prost(sint64, repeated, tag = "9")]
+    memids: Vec<i64>,
+    #[prost(int32, repeated, tag = "10")]
+    types: Vec<i32>
+}
+
+/// Create an [`Error::Parse`] for a problem in a block. There are no lines in a binary file, so
+/// the position is left at zero and the block is named in the message instead.
+fn parse_error(block: usize, message: impl std::fmt::Display) -> Error {
+    Error::Parse { message: format!("block {block}: {message}"), line: 0, column: 0 }
+}
+
+/// Undo the delta coding of a column of dense nodes.
+fn undelta<T: Copy + std::ops::Add<Output = T> + Default>(deltas: &[T]) -> impl Iterator<Item = T> + '_ {
+    deltas.iter().scan(T::default(), |value, delta| {
+        *value = *value + *delta;
+        Some(*value)
+    })
+}
+
+/// Format seconds since 1970 as an ISO 8601 string, the same way Overpass gives timestamps.
+fn format_timestamp(seconds: i64) -> String {
+
+    let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+
+    //Turn days since 1970 into a date in the proleptic Gregorian calendar
+    let z: i64 = days + 719468;
+    let era: i64 = z.div_euclid(146097);
+    let day_of_era: i64 = z.rem_euclid(146097);
+    let year_of_era: i64 = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year: i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month: i64 = (5 * day_of_year + 2) / 153;
+    let day: i64 = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month: i64 = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year: i64 = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z", time / 3600, time % 3600 / 60, time % 60)
+}
+
+/// Which kinds of element a [`PbfReader`] gives back.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Kinds {
+    pub(crate) nodes: bool,
+    pub(crate) ways: bool,
+    pub(crate) relations: bool
+}
+
+/// Turns the elements of a decoded block into [`Element`]s.
+struct Decoder<'a> {
+    block: usize,
+    strings: Vec<String>,
+    granularity: i64,
+    date_granularity: i64,
+    lat_offset: i64,
+    lon_offset: i64,
+    elements: &'a mut VecDeque<Element>
+}
+
+impl Decoder<'_> {
+
+    fn string(&self, index: i64) -> Result<&str, Error> {
+        usize::try_from(index).ok()
+            .and_then(|index| self.strings.get(index))
+            .map(|string| string.as_str())
+            .ok_or_else(|| parse_error(self.block, format!("string {index} is not in the string table")))
+    }
+
+    fn id(&self, id: i64) -> Result<u64, Error> {
+        u64::try_from(id).map_err(|_| parse_error(self.block, format!("invalid id {id}")))
+    }
+
+    fn coordinate(&self, offset: i64, value: i64) -> f64 {
+        (offset + self.granularity * value) as f64 * 1e-9
+    }
+
+    fn tags(&self, keys: &[u32], vals: &[u32]) -> Result<Option<Tags>, Error> {
+        if keys.len() != vals.len() {
+            return Err(parse_error(self.block, format!("{} tag keys but {} values", keys.len(), vals.len())))
+        }
+        if keys.is_empty() {
+            return Ok(None)
+        }
+        let tags: Tags = keys.iter()
+            .zip(vals)
+            .map(|(key, val)| Ok((self.string(*key as i64)?.to_string(), self.string(*val as i64)?.to_string())))
+            .collect::<Result<_, Error>>()?;
+        Ok(Some(tags))
+    }
+
+    fn meta(&self, version: i32, timestamp: i64, changeset: i64, uid: i32, user_sid: i64) -> Metadata {
+        Metadata::new(
+            u64::try_from(version).ok(),
+            (timestamp > 0).then(|| format_timestamp(timestamp * self.date_granularity / 1000)),
+            u64::try_from(changeset).ok().filter(|changeset| *changeset > 0),
+            self.string(user_sid).ok().filter(|user| !user.is_empty()).map(|user| user.to_string()),
+            u64::try_from(uid).ok().filter(|_| user_sid > 0)
+        )
+    }
+
+    fn info(&self, info: &Option<Info>) -> Metadata {
+        match info {
+            Some(info) => self.meta(
+                info.version.unwrap_or(-1),
+                info.timestamp.unwrap_or(0),
+                info.changeset.unwrap_or(0),
+                info.uid.unwrap_or(-1),
+                info.user_sid.unwrap_or(0) as i64
+            ),
+            None => Metadata::default()
+        }
+    }
+
+    fn nodes(&mut self, nodes: &[Node]) -> Result<(), Error> {
+        for node in nodes {
+            if node.info.as_ref().is_some_and(|info| info.visible == Some(false)) {
+                continue
+            }
+            let element = Element::Node {
+                id: self.id(node.id)?,
+                lat: self.coordinate(self.lat_offset, node.lat),
+                lon: self.coordinate(self.lon_offset, node.lon),
+                tags: self.tags(&node.keys, &node.vals)?,
+                meta: self.info(&node.info)
+            };
+            self.elements.push_back(element);
+        }
+        Ok(())
+    }
+
+    fn dense(&mut self, dense: &DenseNodes) -> Result<(), Error> {
+
+        let count: usize = dense.id.len();
+        if dense.lat.len() != count || dense.lon.len() != count {
+            return Err(parse_error(self.block, "dense nodes have a different number of ids and coordinates"))
+        }
+
+        //The metadata columns are either empty or have one entry per node
+        let info: DenseInfo = dense.denseinfo.clone().unwrap_or_default();
+        let column = |values: Vec<i64>| match values.len() == count {
+            true => values,
+            false => vec![0; count]
+        };
+        let versions: Vec<i64> = column(info.version.iter().map(|version| *version as i64).collect());
+        let timestamps: Vec<i64> = column(undelta(&info.timestamp).collect());
+        let changesets: Vec<i64> = column(undelta(&info.changeset).collect());
+        let uids: Vec<i64> = column(undelta(&info.uid).map(i64::from).collect());
+        let user_sids: Vec<i64> = column(undelta(&info.user_sid).map(i64::from).collect());
+        let has_version: bool = info.version.len() == count;
+
+        //Tags of all nodes, with a 0 after the tags of each node
+        let mut keys_vals = dense.keys_vals.iter();
+
+        let ids = undelta(&dense.id);
+        let lats = undelta(&dense.lat);
+        let lons = undelta(&dense.lon);
+        for (i, ((id, lat), lon)) in ids.zip(lats).zip(lons).enumerate() {
+
+            let mut tags: Tags = Tags::new();
+            while let Some(key) = keys_vals.next().filter(|key| **key != 0) {
+                let val: &i32 = keys_vals.next()
+                    .ok_or_else(|| parse_error(self.block, "dense node tags end part way through a tag"))?;
+                tags.insert(self.string(*key as i64)?.to_string(), self.string(*val as i64)?.to_string());
+            }
+
+            if info.visible.get(i) == Some(&false) {
+                continue
+            }
+
+            let element = Element::Node {
+                id: self.id(id)?,
+                lat: self.coordinate(self.lat_offset, lat),
+                lon: self.coordinate(self.lon_offset, lon),
+                tags: (!tags.is_empty()).then_some(tags),
+                meta: match has_version {
+                    true => self.meta(versions[i] as i32, timestamps[i], changesets[i], uids[i] as i32, user_sids[i]),
+                    false => Metadata::default()
+                }
+            };
+            self.elements.push_back(element);
+        }
+        Ok(())
+    }
+
+    fn ways(&mut self, ways: &[Way]) -> Result<(), Error> {
+        for way in ways {
+            if way.info.as_ref().is_some_and(|info| info.visible == Some(false)) {
+                continue
+            }
+            let element = Element::Way {
+                id: self.id(way.id)?,
+                nodes: undelta(&way.refs).map(|id| self.id(id)).collect::<Result<_, _>>()?,
+                tags: self.tags(&way.keys, &way.vals)?,
+                bounds: None,
+                center: None,
+                geometry: None,
+                meta: self.info(&way.info)
+            };
+            self.elements.push_back(element);
+        }
+        Ok(())
+    }
+
+    fn relations(&mut self, relations: &[Relation]) -> Result<(), Error> {
+        for relation in relations {
+            if relation.info.as_ref().is_some_and(|info| info.visible == Some(false)) {
+                continue
+            }
+            let members: Vec<RelationMember> = undelta(&relation.memids)
+                .zip(&relation.types)
+                .zip(&relation.roles_sid)
+                .map(|((id, member_type), role)| {
+                    let member_type: MemberType = match member_type {
+                        0 => MemberType::Node,
+                        1 => MemberType::Way,
+                        2 => MemberType::Relation,
+                        _ => return Err(parse_error(self.block, format!("invalid member type {member_type}")))
+                    };
+                    Ok(RelationMember::new(member_type, self.id(id)?, self.string(*role as i64)?.to_string()))
+                })
+                .collect::<Result<_, Error>>()?;
+
+            let element = Element::Relation {
+                id: self.id(relation.id)?,
+                members,
+                tags: self.tags(&relation.keys, &relation.vals)?,
+                bounds: None,
+                center: None,
+                meta: self.info(&relation.info)
+            };
+            self.elements.push_back(element);
+        }
+        Ok(())
+    }
+}
+
+/// `PbfReader` reads the nodes, ways and relations of an OSM PBF file one block at a time from
+/// any [`Read`]. Only the elements of the block being read are held in memory, so a whole country
+/// can be read on an ordinary machine. The elements are the same as they would be in an Overpass
+/// response, tags and metadata included.
+///
+/// The reader is an iterator of `Result<Element, Error>`. A block that cannot be read returns an
+/// error and ends the iterator, as does a file that requires a feature this reader does not
+/// support (such as history files). Elements that are not visible are left out.
+///
+/// Files are usually sorted, with every node coming before the ways that use it, which is why
+/// [`crate::graph::create_graph_from_pbf`] reads the file twice instead of keeping every node
+/// around. A graph can also be built from the reader with
+/// [`crate::graph::create_graph_from_stream`], but then every node of the file is held until the
+/// end.
+///
+/// ```rust,no_run
+/// use osmgraph::api::{Element, PbfReader};
+///
+/// let reader = PbfReader::open("./pennsylvania-latest.osm.pbf").expect("Was not able to open the file!");
+///
+/// let mut ways: usize = 0;
+/// for element in reader {
+///     if let Element::Way { .. } = element.expect("Was not able to read an element!") {
+///         ways += 1;
+///     }
+/// }
+/// ```
+pub struct PbfReader<R> {
+    reader: R,
+    kinds: Kinds,
+    elements: VecDeque<Element>,
+    blocks: usize,
+    finished: bool,
+
+    //From the header block
+    writing_program: Option<String>,
+    replication_timestamp: Option<String>
+}
+
+impl<R: Read> PbfReader<R> {
+
+    /// Create a reader of the file in `reader`. Blocks are read in one go, so the reader does not
+    /// need to be buffered.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            kinds: Kinds { nodes: true, ways: true, relations: true },
+            elements: VecDeque::new(),
+            blocks: 0,
+            finished: false,
+            writing_program: None,
+            replication_timestamp: None
+        }
+    }
+
+    /// Only give back some kinds of element. Every block is still decompressed and decoded, but
+    /// the others are not turned into elements.
+    pub(crate) fn with_kinds(self, kinds: Kinds) -> Self {
+        Self { kinds, ..self }
+    }
+
+    /// Read the header and contents of the next blob, or `None` at the end of the file.
+    fn read_blob(&mut self) -> Result<Option<(String, Vec<u8>)>, Error> {
+
+        let block: usize = self.blocks;
+
+        //The length of the blob header, unless the file ends here
+        let mut length: [u8; 4] = [0; 4];
+        let mut read: usize = 0;
+        while read < 4 {
+            match self.reader.read(&mut length[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => return Err(parse_error(block, "the file ends part way through a block")),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e.into())
+            }
+        }
+        let length: usize = u32::from_be_bytes(length) as usize;
+        if length > MAX_HEADER_SIZE {
+            return Err(parse_error(block, format!("blob header of {length} bytes is too large")))
+        }
+
+        let header: BlobHeader = BlobHeader::decode(self.read_exact(block, length)?.as_slice())
+            .map_err(|e| parse_error(block, e))?;
+        let size: usize = usize::try_from(header.datasize).unwrap_or(usize::MAX);
+        if size > MAX_BLOB_SIZE {
+            return Err(parse_error(block, format!("blob of {} bytes is too large", header.datasize)))
+        }
+        let blob: Blob = Blob::decode(self.read_exact(block, size)?.as_slice())
+            .map_err(|e| parse_error(block, e))?;
+
+        let data: Vec<u8> = match blob {
+            Blob { raw: Some(raw), .. } => raw,
+            Blob { zlib_data: Some(zlib), raw_size, .. } => {
+                let mut data: Vec<u8> = Vec::with_capacity(raw_size.unwrap_or(0).clamp(0, MAX_BLOB_SIZE as i32) as usize);
+                ZlibDecoder::new(zlib.as_slice())
+                    .take(MAX_BLOB_SIZE as u64 + 1)
+                    .read_to_end(&mut data)
+                    .map_err(|e| parse_error(block, e))?;
+                if data.len() > MAX_BLOB_SIZE {
+                    return Err(parse_error(block, "uncompressed blob is too large"))
+                }
+                data
+            },
+            Blob { lzma_data: Some(_), .. } => return Err(parse_error(block, "lzma compression is not supported")),
+            Blob { lz4_data: Some(_), .. } => return Err(parse_error(block, "lz4 compression is not supported")),
+            Blob { zstd_data: Some(_), .. } => return Err(parse_error(block, "zstd compression is not supported")),
+            _ => return Err(parse_error(block, "blob has no data"))
+        };
+
+        self.blocks += 1;
+        Ok(Some((header.r#type, data)))
+    }
+
+    fn read_exact(&mut self, block: usize, size: usize) -> Result<Vec<u8>, Error> {
+        let mut buffer: Vec<u8> = vec![0; size];
+        self.reader.read_exact(&mut buffer).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => parse_error(block, "the file ends part way through a block"),
+            _ => e.into()
+        })?;
+        Ok(buffer)
+    }
+
+    /// Read blobs until one has elements in it, or the file ends.
+    fn read_block(&mut self) -> Result<(), Error> {
+        while self.elements.is_empty() {
+
+            let block: usize = self.blocks;
+            let Some((blob_type, data)) = self.read_blob()? else {
+                self.finished = true;
+                return Ok(())
+            };
+
+            match blob_type.as_str() {
+                "OSMHeader" => {
+                    let header: HeaderBlock = HeaderBlock::decode(data.as_slice())
+                        .map_err(|e| parse_error(block, e))?;
+                    if let Some(feature) = header.required_features.iter()
+                        .find(|feature| !SUPPORTED_FEATURES.contains(&feature.as_str())) {
+                        return Err(parse_error(block, format!("the file requires `{feature}`, which is not supported")))
+                    }
+                    self.writing_program = header.writingprogram;
+                    self.replication_timestamp = header.osmosis_replication_timestamp.map(format_timestamp);
+                },
+                "OSMData" => {
+                    let primitives: PrimitiveBlock = PrimitiveBlock::decode(data.as_slice())
+                        .map_err(|e| parse_error(block, e))?;
+                    self.decode(block, primitives)?;
+                },
+                //Unknown blobs are meant to be skipped
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(&mut self, block: usize, primitives: PrimitiveBlock) -> Result<(), Error> {
+
+        let mut decoder = Decoder {
+            block,
+            strings: primitives.stringtable.unwrap_or_default().s.into_iter()
+                .map(|string| String::from_utf8_lossy(&string).into_owned())
+                .collect(),
+            granularity: primitives.granularity.unwrap_or(100) as i64,
+            date_granularity: primitives.date_granularity.unwrap_or(1000) as i64,
+            lat_offset: primitives.lat_offset.unwrap_or(0),
+            lon_offset: primitives.lon_offset.unwrap_or(0),
+            elements: &mut self.elements
+        };
+
+        for group in &primitives.primitivegroup {
+            if self.kinds.nodes {
+                decoder.nodes(&group.nodes)?;
+                if let Some(dense) = &group.dense {
+                    decoder.dense(dense)?;
+                }
+            }
+            if self.kinds.ways {
+                decoder.ways(&group.ways)?;
+            }
+            if self.kinds.relations {
+                decoder.relations(&group.relations)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PbfReader<BufReader<File>> {
+
+    /// Open a file to read the elements of.
+    pub fn open(filepath: &str) -> Result<Self, Error> {
+        Ok(Self::new(BufReader::new(File::open(filepath)?)))
+    }
+}
+
+impl<R> PbfReader<R> {
+
+    /// Get the program that wrote the file, once the header block has been read.
+    pub fn writing_program(&self) -> Option<&str> {
+        self.writing_program.as_deref()
+    }
+    /// Get the time up to which the file has the changes to OSM, as an ISO 8601 string, once the
+    /// header block has been read. Extracts that are kept up to date give this.
+    pub fn replication_timestamp(&self) -> Option<&str> {
+        self.replication_timestamp.as_deref()
+    }
+}
+
+impl<R: Read> Iterator for PbfReader<R> {
+    type Item = Result<Element, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.elements.is_empty() && !self.finished {
+            if let Err(e) = self.read_block() {
+                self.finished = true;
+                self.elements.clear();
+                return Some(Err(e))
+            }
+        }
+        self.elements.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Write a blob to a file the way the format lays it out.
+    fn write_blob(file: &mut Vec<u8>, blob_type: &str, data: Vec<u8>, compress: bool) {
+        use std::io::Write;
+
+        let blob = match compress {
+            true => {
+                let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(&data).unwrap();
+                Blob { raw_size: Some(data.len() as i32), zlib_data: Some(encoder.finish().unwrap()), ..Default::default() }
+            },
+            false => Blob { raw: Some(data), ..Default::default() }
+        }.encode_to_vec();
+
+        let header = BlobHeader { r#type: blob_type.to_string(), datasize: blob.len() as i32 }.encode_to_vec();
+        file.extend((header.len() as u32).to_be_bytes());
+        file.extend(header);
+        file.extend(blob);
+    }
+
+    /// A file with a header block, a block of dense nodes and a compressed block of ways and
+    /// relations. The fixtures in `assets/` that `tests/pbf.rs` reads were written by this.
+    fn test_file(required_features: &[&str]) -> Vec<u8> {
+
+        let strings: Vec<&str> = vec!["", "highway", "residential", "crossing", "mapper", "name", "Main", "from"];
+        let stringtable = StringTable { s: strings.iter().map(|s| s.as_bytes().to_vec()).collect() };
+
+        let mut file: Vec<u8> = vec![];
+        write_blob(&mut file, "OSMHeader", HeaderBlock {
+            required_features: required_features.iter().map(|s| s.to_string()).collect(),
+            writingprogram: Some("osmium/1.16.0".to_string()),
+            osmosis_replication_timestamp: Some(1731888000)
+        }.encode_to_vec(), false);
+
+        //Nodes 1, 2 and 3, where 1 is a crossing and 3 is not visible
+        let dense = DenseNodes {
+            id: vec![1, 1, 1],
+            lat: vec![400_000_000, 10_000, 10_000],
+            lon: vec![-760_000_000, 0, 0],
+            keys_vals: vec![1, 3, 0, 0, 0],
+            denseinfo: Some(DenseInfo {
+                version: vec![2, 1, 1],
+                timestamp: vec![1700000000, 0, 0],
+                changeset: vec![100, 0, 0],
+                uid: vec![7, 0, 0],
+                user_sid: vec![4, 0, 0],
+                visible: vec![true, true, false]
+            })
+        };
+        write_blob(&mut file, "OSMData", PrimitiveBlock {
+            stringtable: Some(stringtable.clone()),
+            primitivegroup: vec![PrimitiveGroup { dense: Some(dense), ..Default::default() }],
+            ..Default::default()
+        }.encode_to_vec(), false);
+
+        //Unknown blobs are skipped
+        write_blob(&mut file, "Unknown", vec![1, 2, 3], false);
+
+        let way = Way { id: 10, keys: vec![1, 5], vals: vec![2, 6], refs: vec![1, 1], ..Default::default() };
+        let relation = Relation {
+            id: 100, roles_sid: vec![7, 0], memids: vec![10, -9], types: vec![1, 0], ..Default::default()
+        };
+        write_blob(&mut file, "OSMData", PrimitiveBlock {
+            stringtable: Some(stringtable),
+            primitivegroup: vec![PrimitiveGroup { ways: vec![way], relations: vec![relation], ..Default::default() }],
+            ..Default::default()
+        }.encode_to_vec(), true);
+
+        file
+    }
+
+    #[test]
+    fn test_kinds() {
+
+        let reader = PbfReader::new(Cursor::new(test_file(&[])))
+            .with_kinds(Kinds { nodes: false, ways: true, relations: false });
+        let ids: Vec<u64> = reader.map(|element| element.unwrap().id()).collect();
+        assert_eq!(ids, vec![10]);
+    }
+
+    #[test]
+    fn test_tags() {
+
+        let mut elements: VecDeque<Element> = VecDeque::new();
+        let decoder = Decoder {
+            block: 1,
+            strings: vec![String::new(), "highway".to_string(), "residential".to_string()],
+            granularity: 100,
+            date_granularity: 1000,
+            lat_offset: 0,
+            lon_offset: 0,
+            elements: &mut elements
+        };
+
+        let tags: Tags = decoder.tags(&[1], &[2]).unwrap().unwrap();
+        assert_eq!(tags.get("highway"), Some("residential"));
+        assert!(decoder.tags(&[], &[]).unwrap().is_none());
+
+        //Every key needs a value
+        assert!(matches!(decoder.tags(&[1, 1], &[2]), Err(Error::Parse { message, .. }) if message.starts_with("block 1")));
+        assert!(matches!(decoder.tags(&[], &[2]), Err(Error::Parse { .. })));
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1731888000 + 15 * 3600 + 4 * 60 + 5), "2024-11-18T15:04:05Z");
+    }
+}
@@ -0,0 +1,283 @@
+use serde_json::Value;
+
+use crate::Error;
+use crate::api::overpass_response::Bounds;
+
+/// `Polygon` is a validated ring of `(lat, lon)` points that can be used to query an area, as in
+/// [`crate::api::QueryEngine::query_poly`].
+///
+/// Creating a polygon normalizes the ring: repeated points are removed and the ring is closed if
+/// it is open. A ring is rejected with [`Error::InvalidGeometry`] if it has points outside of the
+/// valid latitude and longitude ranges, if it has fewer than three distinct points or no area, or
+/// if any of its edges cross or touch each other.
+///
+/// ```rust
+/// use osmgraph::api::Polygon;
+///
+/// //An open ring with a repeated point
+/// let polygon = Polygon::new(vec![
+///     (40.0, -76.0),
+///     (41.0, -76.0),
+///     (41.0, -76.0),
+///     (41.0, -75.0),
+/// ]).expect("Polygon should be valid!");
+///
+/// assert_eq!(polygon.points(), &vec![(40.0, -76.0), (41.0, -76.0), (41.0, -75.0), (40.0, -76.0)]);
+///
+/// //A bow tie crosses itself
+/// assert!(Polygon::new(vec![(40.0, -76.0), (41.0, -75.0), (41.0, -76.0), (40.0, -75.0)]).is_err());
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct Polygon {
+    points: Vec<(f64, f64)>
+}
+
+impl Polygon {
+
+    /// Validate and normalize a ring of `(lat, lon)` points. The ring may be open or closed.
+    pub fn new(points: Vec<(f64, f64)>) -> Result<Self, Error> {
+
+        if let Some((lat, lon)) = points.iter().find(|(lat, lon)| !valid_point(*lat, *lon)) {
+            return Err(Error::InvalidGeometry(format!("point ({lat}, {lon}) is not a valid coordinate")))
+        }
+
+        //Drop repeated points, including the closing point
+        let mut ring: Vec<(f64, f64)> = points;
+        ring.dedup();
+        while ring.len() > 1 && ring.first() == ring.last() {
+            ring.pop();
+        }
+
+        if ring.len() < 3 {
+            return Err(Error::InvalidGeometry(format!(
+                "polygon needs at least 3 distinct points, got {}", ring.len()
+            )))
+        }
+        if signed_area(&ring) == 0.0 {
+            return Err(Error::InvalidGeometry("polygon has no area".to_string()))
+        }
+        if let Some((i, j)) = find_self_intersection(&ring) {
+            return Err(Error::InvalidGeometry(format!("polygon edges {i} and {j} intersect")))
+        }
+
+        ring.push(ring[0]);
+        Ok(Polygon { points: ring })
+    }
+
+    /// Read the polygons out of a GeoJSON string. The string may hold a `Polygon` or
+    /// `MultiPolygon` geometry, or a `Feature` or `FeatureCollection` of them. GeoJSON gives
+    /// points as `[lon, lat]`, which are swapped to `(lat, lon)`.
+    ///
+    /// Overpass can only search inside a single ring, so only the outer ring of each polygon is
+    /// used. Holes are ignored, which means a query may return elements that lie inside a hole.
+    ///
+    /// ```rust
+    /// use osmgraph::api::Polygon;
+    ///
+    /// let polygons: Vec<Polygon> = Polygon::from_geojson(r#"{
+    ///     "type": "Polygon",
+    ///     "coordinates": [[[-76.0, 40.0], [-76.0, 41.0], [-75.0, 41.0], [-76.0, 40.0]]]
+    /// }"#).expect("GeoJSON should be valid!");
+    ///
+    /// assert_eq!(polygons[0].points()[1], (41.0, -76.0));
+    /// ```
+    pub fn from_geojson(geojson: &str) -> Result<Vec<Self>, Error> {
+
+        let value: Value = serde_json::from_str(geojson)?;
+
+        let mut polygons: Vec<Self> = vec![];
+        collect_geojson(&value, &mut polygons)?;
+
+        match polygons.is_empty() {
+            true => Err(Error::InvalidGeometry("GeoJSON does not contain any polygons".to_string())),
+            false => Ok(polygons)
+        }
+    }
+
+    /// Getter for the closed ring of `(lat, lon)` points. The first and last point are the same.
+    pub fn points(&self) -> &Vec<(f64, f64)> {
+        &self.points
+    }
+
+    /// Get the smallest bounding box that holds the polygon.
+    pub fn bounds(&self) -> Bounds {
+        let (mut minlat, mut minlon) = self.points[0];
+        let (mut maxlat, mut maxlon) = self.points[0];
+        for (lat, lon) in &self.points {
+            minlat = minlat.min(*lat);
+            minlon = minlon.min(*lon);
+            maxlat = maxlat.max(*lat);
+            maxlon = maxlon.max(*lon);
+        }
+        Bounds::new(minlat, minlon, maxlat, maxlon)
+    }
+
+    /// Returns true if the point is inside the polygon (ray casting). Points on the edge may go
+    /// either way.
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        let mut inside: bool = false;
+        for edge in self.points.windows(2) {
+            let ((lat_a, lon_a), (lat_b, lon_b)) = (edge[0], edge[1]);
+            if (lon_a > lon) != (lon_b > lon)
+                && lat < lat_a + (lon - lon_a) / (lon_b - lon_a) * (lat_b - lat_a) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Returns true if the polygon and the bounding box overlap.
+    pub fn intersects(&self, bounds: &Bounds) -> bool {
+
+        let in_bounds = |(lat, lon): (f64, f64)| {
+            lat >= bounds.minlat() && lat <= bounds.maxlat() && lon >= bounds.minlon() && lon <= bounds.maxlon()
+        };
+        let corners: [(f64, f64); 4] = [
+            (bounds.minlat(), bounds.minlon()),
+            (bounds.maxlat(), bounds.minlon()),
+            (bounds.maxlat(), bounds.maxlon()),
+            (bounds.minlat(), bounds.maxlon())
+        ];
+
+        self.points.iter().any(|point| in_bounds(*point))
+            || corners.iter().any(|(lat, lon)| self.contains(*lat, *lon))
+            || self.points.windows(2).any(|edge| (0..4).any(|i| {
+                segments_intersect(edge[0], edge[1], corners[i], corners[(i + 1) % 4])
+            }))
+    }
+}
+
+impl TryFrom<Vec<(f64, f64)>> for Polygon {
+    type Error = Error;
+
+    fn try_from(points: Vec<(f64, f64)>) -> Result<Self, Error> {
+        Polygon::new(points)
+    }
+}
+
+fn valid_point(lat: f64, lon: f64) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)
+}
+
+/// Twice the signed area of an open ring (shoelace formula).
+fn signed_area(ring: &[(f64, f64)]) -> f64 {
+    (0..ring.len())
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum()
+}
+
+/// Which side of the line through `a` and `b` the point `c` is on. Zero means on the line.
+fn orientation(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+/// Whether `c`, which is on the line through `a` and `b`, is between them.
+fn on_segment(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> bool {
+    c.0 >= a.0.min(b.0) && c.0 <= a.0.max(b.0) && c.1 >= a.1.min(b.1) && c.1 <= a.1.max(b.1)
+}
+
+/// Whether the segments `a`-`b` and `c`-`d` cross or touch.
+fn segments_intersect(a: (f64, f64), b: (f64, f64), c: (f64, f64), d: (f64, f64)) -> bool {
+
+    let (o1, o2) = (orientation(a, b, c), orientation(a, b, d));
+    let (o3, o4) = (orientation(c, d, a), orientation(c, d, b));
+
+    if o1 * o2 < 0.0 && o3 * o4 < 0.0 {
+        return true
+    }
+
+    (o1 == 0.0 && on_segment(a, b, c))
+        || (o2 == 0.0 && on_segment(a, b, d))
+        || (o3 == 0.0 && on_segment(c, d, a))
+        || (o4 == 0.0 && on_segment(c, d, b))
+}
+
+/// Find two edges of an open ring that intersect, other than neighbouring edges meeting at their
+/// shared point. Neighbouring edges that fold back onto each other count as intersecting.
+fn find_self_intersection(ring: &[(f64, f64)]) -> Option<(usize, usize)> {
+
+    let n: usize = ring.len();
+    let edge = |i: usize| (ring[i], ring[(i + 1) % n]);
+
+    for i in 0..n {
+        let (a, b) = edge(i);
+
+        //The next edge shares `b`, so it only intersects if it turns straight back
+        let (_, c) = edge((i + 1) % n);
+        if orientation(a, b, c) == 0.0 && (a.0 - b.0) * (c.0 - b.0) + (a.1 - b.1) * (c.1 - b.1) > 0.0 {
+            return Some((i, (i + 1) % n))
+        }
+
+        for j in (i + 2)..n {
+            if i == 0 && j == n - 1 {
+                continue
+            }
+            let (c, d) = edge(j);
+            if segments_intersect(a, b, c, d) {
+                return Some((i, j))
+            }
+        }
+    }
+
+    None
+}
+
+/// Read the `[lon, lat]` points of a GeoJSON ring.
+fn geojson_ring(value: &Value) -> Result<Vec<(f64, f64)>, Error> {
+
+    let points = value.as_array()
+        .ok_or_else(|| Error::InvalidGeometry("GeoJSON ring is not an array".to_string()))?;
+
+    points.iter()
+        .map(|point| match point.as_array().map(|p| p.as_slice()) {
+            Some([lon, lat, ..]) => match (lat.as_f64(), lon.as_f64()) {
+                (Some(lat), Some(lon)) => Ok((lat, lon)),
+                _ => Err(Error::InvalidGeometry(format!("GeoJSON point {point} is not numeric")))
+            },
+            _ => Err(Error::InvalidGeometry(format!("GeoJSON point {point} is not a [lon, lat] pair")))
+        })
+        .collect()
+}
+
+/// Read the outer ring of the rings of a GeoJSON polygon.
+fn geojson_polygon(rings: &Value) -> Result<Polygon, Error> {
+    let outer: &Value = rings.as_array()
+        .and_then(|rings| rings.first())
+        .ok_or_else(|| Error::InvalidGeometry("GeoJSON polygon has no rings".to_string()))?;
+
+    Polygon::new(geojson_ring(outer)?)
+}
+
+fn collect_geojson(value: &Value, polygons: &mut Vec<Polygon>) -> Result<(), Error> {
+
+    let coordinates = || value.get("coordinates")
+        .ok_or_else(|| Error::InvalidGeometry("GeoJSON geometry has no coordinates".to_string()));
+
+    match value.get("type").and_then(|t| t.as_str()) {
+        Some("Polygon") => polygons.push(geojson_polygon(coordinates()?)?),
+        Some("MultiPolygon") => {
+            let members = coordinates()?.as_array()
+                .ok_or_else(|| Error::InvalidGeometry("GeoJSON multipolygon is not an array".to_string()))?;
+            for rings in members {
+                polygons.push(geojson_polygon(rings)?);
+            }
+        },
+        Some("Feature") => {
+            if let Some(geometry) = value.get("geometry").filter(|g| !g.is_null()) {
+                collect_geojson(geometry, polygons)?;
+            }
+        },
+        Some("FeatureCollection") => {
+            for feature in value.get("features").and_then(|f| f.as_array()).into_iter().flatten() {
+                collect_geojson(feature, polygons)?;
+            }
+        },
+        Some(other) => return Err(Error::InvalidGeometry(format!("GeoJSON type `{other}` is not a polygon"))),
+        None => return Err(Error::InvalidGeometry("GeoJSON object has no `type`".to_string()))
+    }
+
+    Ok(())
+}
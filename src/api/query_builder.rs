@@ -0,0 +1,635 @@
+use std::fmt;
+
+use crate::Error;
+
+/// Escape a value so that it can be placed between double quotes in an Overpass QL query. This is
+/// needed for any value that comes from the outside, such as the name of a place: `Val d'Or
+/// "Nord"` becomes `Val d'Or \"Nord\"`.
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+/// Check that a name can be used for a set, such as `searchArea`: a letter or an underscore
+/// followed by letters, digits and underscores.
+fn check_set(set: &str) -> Result<(), Error> {
+
+    let mut chars = set.chars();
+    let valid: bool = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    match valid {
+        true => Ok(()),
+        false => Err(Error::InvalidFilter(format!("`{set}` is not a valid set name")))
+    }
+}
+
+/// Check that a point is a valid `(lat, lon)` coordinate.
+fn check_point(lat: f64, lon: f64) -> Result<(), Error> {
+    match (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) {
+        true => Ok(()),
+        false => Err(Error::InvalidGeometry(format!("point ({lat}, {lon}) is not a valid coordinate")))
+    }
+}
+
+/// Check that a bounding box is made of valid coordinates, with its south edge below its north
+/// edge and its west edge left of its east edge.
+pub(crate) fn check_bbox(south: f64, west: f64, north: f64, east: f64) -> Result<(), Error> {
+
+    check_point(south, west)?;
+    check_point(north, east)?;
+
+    match south < north && west < east {
+        true => Ok(()),
+        false => Err(Error::InvalidGeometry(format!(
+            "bounding box ({south}, {west}, {north}, {east}) must have south < north and west < east"
+        )))
+    }
+}
+
+/// The two dates of a diff query, see [`Settings::with_diff`]. Dates are ISO 8601 times such as
+/// `"2020-01-01T00:00:00Z"`, and a missing second date means now.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Diff {
+    /// `[diff:"from","to"]`: the elements that changed, as they were at the first date and as
+    /// they are at the second.
+    Plain { from: String, to: Option<String> },
+    /// `[adiff:"from","to"]`: an augmented diff, which also says whether each element was created,
+    /// modified or deleted, and includes the ways whose nodes moved.
+    Augmented { from: String, to: Option<String> }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, from, to) = match self {
+            Diff::Plain { from, to } => ("diff", from, to),
+            Diff::Augmented { from, to } => ("adiff", from, to)
+        };
+        write!(f, "[{name}:\"{}\"", escape(from))?;
+        if let Some(to) = to {
+            write!(f, ",\"{}\"", escape(to))?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// The settings at the top of a query, such as `[out:json][timeout:180]`. The output format is
+/// json, since that is what [`crate::api::OverpassResponse`] parses, except for diff queries,
+/// which Overpass only answers in XML.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Settings {
+    timeout: Option<u32>,
+    maxsize: Option<u64>,
+    date: Option<String>,
+    diff: Option<Diff>
+}
+
+impl Settings {
+
+    /// Getter for the server side timeout in seconds.
+    pub fn timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+    /// Getter for the most memory (in bytes) the server may use for the query.
+    pub fn maxsize(&self) -> Option<u64> {
+        self.maxsize
+    }
+    /// Getter for the date at which the data is queried.
+    pub fn date(&self) -> Option<&str> {
+        self.date.as_deref()
+    }
+    /// Getter for the dates between which the changes are queried.
+    pub fn diff(&self) -> Option<&Diff> {
+        self.diff.as_ref()
+    }
+
+    /// Set the server side timeout in seconds. Meant to be used in a functional style
+    pub fn with_timeout(&self, timeout: u32) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+    /// Set the most memory (in bytes) the server may use for the query. Meant to be used in a
+    /// functional style
+    pub fn with_maxsize(&self, maxsize: u64) -> Self {
+        Self {
+            maxsize: Some(maxsize),
+            ..self.clone()
+        }
+    }
+    /// Query the data as it was at a point in the past, given as an ISO 8601 date such as
+    /// `"2020-01-01T00:00:00Z"`. Meant to be used in a functional style
+    pub fn with_date(&self, date: String) -> Self {
+        Self {
+            date: Some(date),
+            diff: None,
+            ..self.clone()
+        }
+    }
+    /// Query what changed between two dates rather than the data itself. This switches the output
+    /// to XML and replaces any date set with [`Self::with_date`], since Overpass does not allow
+    /// both. Meant to be used in a functional style
+    ///
+    /// ```rust
+    /// use osmgraph::api::{Diff, Settings};
+    ///
+    /// let settings = Settings::default()
+    ///     .with_diff(Diff::Augmented { from: "2020-01-01T00:00:00Z".to_string(), to: None });
+    ///
+    /// assert_eq!(settings.to_string(), r#"[out:xml][adiff:"2020-01-01T00:00:00Z"];"#);
+    /// ```
+    pub fn with_diff(&self, diff: Diff) -> Self {
+        Self {
+            date: None,
+            diff: Some(diff),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.diff {
+            Some(_) => write!(f, "[out:xml]")?,
+            None => write!(f, "[out:json]")?
+        }
+        if let Some(timeout) = self.timeout {
+            write!(f, "[timeout:{timeout}]")?;
+        }
+        if let Some(maxsize) = self.maxsize {
+            write!(f, "[maxsize:{maxsize}]")?;
+        }
+        if let Some(date) = &self.date {
+            write!(f, "[date:\"{}\"]", escape(date))?;
+        }
+        if let Some(diff) = &self.diff {
+            write!(f, "{diff}")?;
+        }
+        write!(f, ";")
+    }
+}
+
+/// A filter on the tags of an element.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum TagFilter {
+    /// `["key"="value"]`: the tag is present and has exactly this value.
+    Equals(String, String),
+    /// `["key"!="value"]`: the tag is missing or has another value.
+    NotEquals(String, String),
+    /// `["key"~"regex"]`: the tag is present and its value matches the regular expression.
+    Regex(String, String),
+    /// `["key"!~"regex"]`: the tag is missing or its value does not match the regular expression.
+    NotRegex(String, String),
+    /// `["key"]`: the tag is present.
+    Exists(String),
+    /// `[!"key"]`: the tag is missing.
+    NotExists(String)
+}
+
+impl TagFilter {
+
+    /// Create a [`TagFilter::Equals`] filter.
+    pub fn equals(key: &str, value: &str) -> Self {
+        TagFilter::Equals(key.to_string(), value.to_string())
+    }
+    /// Create a [`TagFilter::NotEquals`] filter.
+    pub fn not_equals(key: &str, value: &str) -> Self {
+        TagFilter::NotEquals(key.to_string(), value.to_string())
+    }
+    /// Create a [`TagFilter::Regex`] filter.
+    pub fn regex(key: &str, regex: &str) -> Self {
+        TagFilter::Regex(key.to_string(), regex.to_string())
+    }
+    /// Create a [`TagFilter::NotRegex`] filter.
+    pub fn not_regex(key: &str, regex: &str) -> Self {
+        TagFilter::NotRegex(key.to_string(), regex.to_string())
+    }
+    /// Create a [`TagFilter::Exists`] filter.
+    pub fn exists(key: &str) -> Self {
+        TagFilter::Exists(key.to_string())
+    }
+    /// Create a [`TagFilter::NotExists`] filter.
+    pub fn not_exists(key: &str) -> Self {
+        TagFilter::NotExists(key.to_string())
+    }
+}
+
+impl fmt::Display for TagFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagFilter::Equals(k, v) => write!(f, "[\"{}\"=\"{}\"]", escape(k), escape(v)),
+            TagFilter::NotEquals(k, v) => write!(f, "[\"{}\"!=\"{}\"]", escape(k), escape(v)),
+            TagFilter::Regex(k, v) => write!(f, "[\"{}\"~\"{}\"]", escape(k), escape(v)),
+            TagFilter::NotRegex(k, v) => write!(f, "[\"{}\"!~\"{}\"]", escape(k), escape(v)),
+            TagFilter::Exists(k) => write!(f, "[\"{}\"]", escape(k)),
+            TagFilter::NotExists(k) => write!(f, "[!\"{}\"]", escape(k))
+        }
+    }
+}
+
+/// A filter on the location of an element.
+#[derive(Clone, PartialEq, Debug)]
+pub enum SpatialFilter {
+    /// `(area.name)`: inside the areas held in the named set.
+    Area(String),
+    /// `(south,west,north,east)`: inside a bounding box.
+    BBox { south: f64, west: f64, north: f64, east: f64 },
+    /// `(around:radius,lat,lon)`: within `radius` meters of a point.
+    Around { radius: f64, lat: f64, lon: f64 },
+    /// `(poly:"lat lon lat lon ...")`: inside a polygon given as `(lat, lon)` points.
+    Poly(Vec<(f64, f64)>)
+}
+
+impl SpatialFilter {
+
+    fn check(&self) -> Result<(), Error> {
+        match self {
+            SpatialFilter::Area(set) => check_set(set),
+            SpatialFilter::BBox { south, west, north, east } => check_bbox(*south, *west, *north, *east),
+            SpatialFilter::Around { radius, lat, lon } => {
+                if !(*radius >= 0.0 && radius.is_finite()) {
+                    return Err(Error::InvalidGeometry(format!("radius {radius} must be a number of meters")))
+                }
+                check_point(*lat, *lon)
+            },
+            SpatialFilter::Poly(points) => points.iter().try_for_each(|(lat, lon)| check_point(*lat, *lon))
+        }
+    }
+}
+
+impl fmt::Display for SpatialFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpatialFilter::Area(set) => write!(f, "(area.{set})"),
+            SpatialFilter::BBox { south, west, north, east } => write!(f, "({south},{west},{north},{east})"),
+            SpatialFilter::Around { radius, lat, lon } => write!(f, "(around:{radius},{lat},{lon})"),
+            SpatialFilter::Poly(points) => {
+                let points: Vec<String> = points.iter()
+                    .map(|(lat, lon)| format!("{lat} {lon}"))
+                    .collect();
+                write!(f, "(poly:\"{}\")", points.join(" "))
+            }
+        }
+    }
+}
+
+/// The type of element that a [`Selector`] looks for.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ElementKind {
+    Node,
+    Way,
+    Relation,
+    /// Nodes, ways and relations.
+    Nwr,
+    Area
+}
+
+impl fmt::Display for ElementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElementKind::Node => write!(f, "node"),
+            ElementKind::Way => write!(f, "way"),
+            ElementKind::Relation => write!(f, "relation"),
+            ElementKind::Nwr => write!(f, "nwr"),
+            ElementKind::Area => write!(f, "area")
+        }
+    }
+}
+
+/// A `Selector` finds elements of one kind that pass all of its tag and spatial filters, such as
+/// `way["highway"~"primary|secondary"](area.searchArea)`. The result can be kept in a named set
+/// (`->.name`) for later statements to use.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Selector {
+    kind: ElementKind,
+    tags: Vec<TagFilter>,
+    spatial: Vec<SpatialFilter>,
+    output_set: Option<String>
+}
+
+impl Selector {
+
+    /// Create a selector for elements of the given kind, without any filters.
+    pub fn new(kind: ElementKind) -> Self {
+        Selector { kind, tags: vec![], spatial: vec![], output_set: None }
+    }
+    /// Create a selector for nodes.
+    pub fn node() -> Self {
+        Self::new(ElementKind::Node)
+    }
+    /// Create a selector for ways.
+    pub fn way() -> Self {
+        Self::new(ElementKind::Way)
+    }
+    /// Create a selector for relations.
+    pub fn relation() -> Self {
+        Self::new(ElementKind::Relation)
+    }
+    /// Create a selector for nodes, ways and relations.
+    pub fn nwr() -> Self {
+        Self::new(ElementKind::Nwr)
+    }
+    /// Create a selector for areas.
+    pub fn area() -> Self {
+        Self::new(ElementKind::Area)
+    }
+
+    /// Getter for the kind of element selected.
+    pub fn kind(&self) -> ElementKind {
+        self.kind
+    }
+    /// Getter for the tag filters.
+    pub fn tags(&self) -> &Vec<TagFilter> {
+        &self.tags
+    }
+    /// Getter for the spatial filters.
+    pub fn spatial(&self) -> &Vec<SpatialFilter> {
+        &self.spatial
+    }
+    /// Getter for the named set the result is kept in.
+    pub fn output_set(&self) -> Option<&str> {
+        self.output_set.as_deref()
+    }
+
+    /// Add a tag filter. Meant to be used in a functional style
+    pub fn with_tag(&self, filter: TagFilter) -> Self {
+        let mut new = self.clone();
+        new.tags.push(filter);
+        new
+    }
+    /// Add several tag filters. Meant to be used in a functional style
+    pub fn with_tags(&self, filters: Vec<TagFilter>) -> Self {
+        let mut new = self.clone();
+        new.tags.extend(filters);
+        new
+    }
+    /// Add a spatial filter. Meant to be used in a functional style
+    pub fn with_spatial(&self, filter: SpatialFilter) -> Self {
+        let mut new = self.clone();
+        new.spatial.push(filter);
+        new
+    }
+    /// Only select elements inside the areas held in the named set. Meant to be used in a
+    /// functional style
+    pub fn with_area(&self, set: &str) -> Self {
+        self.with_spatial(SpatialFilter::Area(set.to_string()))
+    }
+    /// Only select elements inside a bounding box. Meant to be used in a functional style
+    pub fn with_bbox(&self, south: f64, west: f64, north: f64, east: f64) -> Self {
+        self.with_spatial(SpatialFilter::BBox { south, west, north, east })
+    }
+    /// Only select elements within `radius` meters of a point. Meant to be used in a functional style
+    pub fn with_around(&self, radius: f64, lat: f64, lon: f64) -> Self {
+        self.with_spatial(SpatialFilter::Around { radius, lat, lon })
+    }
+    /// Only select elements inside a polygon of `(lat, lon)` points. Meant to be used in a
+    /// functional style
+    pub fn with_poly(&self, polygon: Vec<(f64, f64)>) -> Self {
+        self.with_spatial(SpatialFilter::Poly(polygon))
+    }
+    /// Keep the result in a named set instead of the default set. Meant to be used in a
+    /// functional style
+    pub fn with_output_set(&self, set: &str) -> Self {
+        Self {
+            output_set: Some(set.to_string()),
+            ..self.clone()
+        }
+    }
+}
+
+impl Selector {
+
+    fn check(&self) -> Result<(), Error> {
+        self.spatial.iter().try_for_each(|filter| filter.check())?;
+        match &self.output_set {
+            Some(set) => check_set(set),
+            None => Ok(())
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        for filter in &self.tags {
+            write!(f, "{filter}")?;
+        }
+        for filter in &self.spatial {
+            write!(f, "{filter}")?;
+        }
+        if let Some(set) = &self.output_set {
+            write!(f, "->.{set}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The amount of detail and the order of the elements that an `out` statement prints.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum OutMode {
+    /// Only the IDs of elements.
+    Ids,
+    /// IDs, coordinates of nodes and node lists of ways, but no tags.
+    Skel,
+    /// Everything needed to use the data (the default).
+    Body,
+    /// IDs and tags, but no coordinates or node lists.
+    Tags,
+    /// Everything, plus the version, timestamp, changeset and user of each element.
+    Meta,
+    /// Add the coordinates of every node to ways (`geometry`).
+    Geom,
+    /// Add the center of each way and relation (`center`).
+    Center,
+    /// Add the bounding box of each way and relation (`bounds`).
+    Bb,
+    /// Sort the elements by location rather than by ID, which is faster.
+    Qt
+}
+
+impl fmt::Display for OutMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutMode::Ids => write!(f, "ids"),
+            OutMode::Skel => write!(f, "skel"),
+            OutMode::Body => write!(f, "body"),
+            OutMode::Tags => write!(f, "tags"),
+            OutMode::Meta => write!(f, "meta"),
+            OutMode::Geom => write!(f, "geom"),
+            OutMode::Center => write!(f, "center"),
+            OutMode::Bb => write!(f, "bb"),
+            OutMode::Qt => write!(f, "qt")
+        }
+    }
+}
+
+/// A single statement of a query.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Statement {
+    /// Select elements, such as `way["highway"](area.searchArea);`.
+    Select(Selector),
+    /// The union of the results of several statements, such as `(way(1); node(2););`.
+    Union(Vec<Statement>),
+    /// The default set, `._;`. Mostly useful inside a union.
+    Current,
+    /// A named set, `.name;`. Mostly useful inside a union.
+    Set(String),
+    /// Everything that the elements in the default set refer to, `>;`. For ways, this is their
+    /// nodes.
+    RecurseDown,
+    /// Everything that refers to the elements in the default set, `<;`. For nodes, this is the
+    /// ways they are on.
+    RecurseUp,
+    /// Print the elements in the default set, such as `out body qt;`.
+    Out(Vec<OutMode>)
+}
+
+impl Statement {
+
+    fn check(&self) -> Result<(), Error> {
+        match self {
+            Statement::Select(selector) => selector.check(),
+            Statement::Union(statements) => statements.iter().try_for_each(|statement| statement.check()),
+            Statement::Set(set) => check_set(set),
+            _ => Ok(())
+        }
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Select(selector) => write!(f, "{selector};"),
+            Statement::Union(statements) => {
+                let statements: Vec<String> = statements.iter()
+                    .map(|statement| statement.to_string())
+                    .collect();
+                write!(f, "({});", statements.join(" "))
+            },
+            Statement::Current => write!(f, "._;"),
+            Statement::Set(set) => write!(f, ".{set};"),
+            Statement::RecurseDown => write!(f, ">;"),
+            Statement::RecurseUp => write!(f, "<;"),
+            Statement::Out(modes) => {
+                let modes: Vec<String> = modes.iter()
+                    .map(|mode| mode.to_string())
+                    .collect();
+                match modes.is_empty() {
+                    true => write!(f, "out;"),
+                    false => write!(f, "out {};", modes.join(" "))
+                }
+            }
+        }
+    }
+}
+
+/// `QueryBuilder` composes an Overpass QL query out of [`Settings`] and [`Statement`]s, so that
+/// queries do not have to be written by hand with `format!`. All values are escaped, and the
+/// result renders to a string that [`crate::api::QueryEngine::query`] can send.
+///
+/// [`Self::build`] checks the query before rendering it: a set name that is not an identifier
+/// returns [`Error::InvalidFilter`], and a coordinate or radius that is out of range (or not a
+/// number) returns [`Error::InvalidGeometry`].
+///
+/// Example:
+/// ```rust
+/// use osmgraph::api::{QueryBuilder, Selector, Statement, TagFilter, OutMode};
+///
+/// let query: String = QueryBuilder::new()
+///     .with_timeout(60)
+///     .with_statement(Statement::Select(Selector::area()
+///         .with_tag(TagFilter::equals("name", "Val d'Or \"Nord\""))
+///         .with_output_set("searchArea")))
+///     .with_statement(Statement::Select(Selector::way()
+///         .with_tag(TagFilter::regex("highway", "primary|secondary"))
+///         .with_tag(TagFilter::not_equals("access", "private"))
+///         .with_area("searchArea")))
+///     .with_statement(Statement::Union(vec![Statement::Current, Statement::RecurseDown]))
+///     .with_statement(Statement::Out(vec![OutMode::Body, OutMode::Qt]))
+///     .build()
+///     .expect("The query should be valid!");
+///
+/// assert_eq!(query, concat!(
+///     "[out:json][timeout:60];\n",
+///     "area[\"name\"=\"Val d'Or \\\"Nord\\\"\"]->.searchArea;\n",
+///     "way[\"highway\"~\"primary|secondary\"][\"access\"!=\"private\"](area.searchArea);\n",
+///     "(._; >;);\n",
+///     "out body qt;",
+/// ));
+/// ```
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct QueryBuilder {
+    settings: Settings,
+    statements: Vec<Statement>
+}
+
+impl QueryBuilder {
+
+    /// Create an empty query with the default settings (`[out:json];`).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Getter for the settings of the query.
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+    /// Getter for the statements of the query.
+    pub fn statements(&self) -> &Vec<Statement> {
+        &self.statements
+    }
+
+    /// Replace all of the settings of the query. Meant to be used in a functional style
+    pub fn with_settings(&self, settings: Settings) -> Self {
+        Self {
+            settings,
+            ..self.clone()
+        }
+    }
+    /// Set the server side timeout in seconds. Meant to be used in a functional style
+    pub fn with_timeout(&self, timeout: u32) -> Self {
+        self.with_settings(self.settings.with_timeout(timeout))
+    }
+    /// Set the most memory (in bytes) the server may use for the query. Meant to be used in a
+    /// functional style
+    pub fn with_maxsize(&self, maxsize: u64) -> Self {
+        self.with_settings(self.settings.with_maxsize(maxsize))
+    }
+    /// Query the data as it was at a point in the past. Meant to be used in a functional style
+    pub fn with_date(&self, date: String) -> Self {
+        self.with_settings(self.settings.with_date(date))
+    }
+
+    /// Add a statement to the end of the query. Meant to be used in a functional style
+    pub fn with_statement(&self, statement: Statement) -> Self {
+        let mut new = self.clone();
+        new.statements.push(statement);
+        new
+    }
+
+    /// Check the query and render it to a string.
+    pub fn build(&self) -> Result<String, Error> {
+        self.statements.iter().try_for_each(|statement| statement.check())?;
+        Ok(self.to_string())
+    }
+}
+
+impl fmt::Display for QueryBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.settings)?;
+        for statement in &self.statements {
+            write!(f, "\n{statement}")?;
+        }
+        Ok(())
+    }
+}
@@ -1,15 +1,59 @@
-use std::io::{Error, ErrorKind};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use tokio::runtime::Runtime;
+use tokio::task::JoinSet;
+
+use serde::Deserialize;
+
+use crate::Error;
+use crate::runtime::block_on;
+use crate::graph::{ChangeSet, OSMGraph, create_graph, create_graph_with_filter};
+use crate::api::overpass_response::{OverpassResponse, Bounds, Element, is_runtime_error};
+use crate::api::retry::{RetryPolicy, MAX_SLOT_CHECKS, slot_wait};
+use crate::api::endpoint::{Endpoints, EndpointHealth};
+use crate::api::polygon::Polygon;
+use crate::api::cache::QueryCache;
+use crate::api::tiles::{TileOptions, TileStatus, grid};
+use crate::api::query_builder::{QueryBuilder, check_bbox, Diff, Settings, Selector, Statement, TagFilter, OutMode};
+use crate::api::transport::{HttpRequest, HttpResponse, Transport};
+use crate::api::way_filter::WayFilter;
+use crate::api::network_type::NetworkType;
+#[cfg(feature = "reqwest")]
+use crate::api::transport::ReqwestTransport;
 
 /// QueryEngine is a structure that helps create queries to the Overpass API.
 /// It allows us to make lower level API calls (with the Overpass QL) as well as some higher level
 /// API calls such as just fetching a place of interest or a polygon of interest.
-#[derive(Clone, Debug, Default)]
+///
+/// Every request follows the engine's [`RetryPolicy`]. Clones of an engine share the time of the
+/// last request, so the minimum interval between requests holds across all of them.
+///
+/// An engine can be given several endpoints (for example a self-hosted instance followed by
+/// public mirrors). If an endpoint cannot be reached or is overloaded, the request moves on to the
+/// next one. Clones of an engine also share the health records of the endpoints.
+///
+/// Given a [`QueryCache`], the engine answers repeated queries from disk instead of sending them
+/// again.
+///
+/// Requests are sent by a [`Transport`], which is a [`ReqwestTransport`] unless the engine is
+/// created with [`Self::from_transport`].
+///
+/// The `*_blocking` methods (here and elsewhere in the crate) all run on one runtime, which is
+/// started the first time it is needed. Inside a multi-threaded async runtime (such as a web
+/// server's, including its `spawn_blocking` threads) they move off the runtime's thread while they
+/// wait. Inside a current-thread runtime they return [`Error::Runtime`]: use the async methods
+/// there.
+#[derive(Clone, Debug)]
 pub struct QueryEngine {
-    client: reqwest::Client,
-    base_url: String,
+    transport: Arc<dyn Transport>,
+    endpoints: Endpoints,
     way_filters: Vec<String>,
+    way_filter: Option<WayFilter>,
+    settings: Settings,
+    policy: RetryPolicy,
+    next_request: Arc<Mutex<Option<Instant>>>,
+    cache: Option<QueryCache>,
 }
 
 impl QueryEngine {
@@ -21,16 +65,42 @@ impl QueryEngine {
     /// QueryEngine also has a default set of filters for ways. The filter is currently set to only
     /// fetch roads that can be driven on with a car. However, you might be interested in
     /// footpaths, railroads, etc. If you would like to change the filter, take a look at
+    /// [`Self::with_network_type`] for presets, or at
     /// <https://wiki.openstreetmap.org/wiki/Key:highway> for more information on the options
     /// available.
     ///
     /// Note that these filters are only applied when using higher level api calls such as
     /// [`Self::query_place`]. The lowest level api call, [`Self::query`] directly sends your query to the api
     /// without any modification.
+    ///
+    /// Requests are sent with a [`ReqwestTransport`]. This is only available with the `reqwest`
+    /// feature, which is on by default.
+    #[cfg(feature = "reqwest")]
     pub fn new() -> Self {
+        Self::from_transport(ReqwestTransport::new())
+    }
+
+    /// Creates a new instance of the query engine that sends its requests with the given
+    /// [`Transport`]. Everything else is the same as [`Self::new`].
+    ///
+    /// Example with a reqwest client of your own, which needs the `reqwest` feature (see
+    /// [`Transport`] for a transport without reqwest):
+    /// ```rust
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::{QueryEngine, ReqwestTransport};
+    ///
+    /// let client = reqwest::Client::builder()
+    ///     .user_agent("my-app/1.0")
+    ///     .build()
+    ///     .expect("Was not able to build the client!");
+    ///
+    /// let engine = QueryEngine::from_transport(ReqwestTransport::from_client(client));
+    /// # }
+    /// ```
+    pub fn from_transport(transport: impl Transport + 'static) -> Self {
         Self {
-            client: reqwest::Client::new(),
-            base_url: "https://overpass-api.de/api/interpreter".to_string(),
+            transport: Arc::new(transport),
+            endpoints: Endpoints::new(vec!["https://overpass-api.de/api/interpreter".to_string()]),
             way_filters: vec![
                 String::from("motorway"),
                 String::from("trunk"),
@@ -40,46 +110,263 @@ impl QueryEngine {
                 String::from("unclassified"),
                 String::from("residential"),
                 String::from("service")
-            ].into_iter().collect()
+            ].into_iter().collect(),
+            way_filter: None,
+            settings: Settings::default(),
+            policy: RetryPolicy::new(),
+            next_request: Arc::new(Mutex::new(None)),
+            cache: None,
         }
     }
 
-    /// Getter for the base URL that the QueryEngine uses.
+    /// Getter for the base URL that the QueryEngine uses. If there are several endpoints, this is
+    /// the first of them.
     pub fn url(&self) -> &str {
-        &self.base_url
+        self.endpoints.urls().first().map_or("", |url| url.as_str())
     }
 
     /// Set a new url to query. Meant to be used in a functional style
     ///
     /// ```rust
+    /// # #[cfg(feature = "reqwest")] {
     /// use osmgraph::api::QueryEngine;
     ///
     /// let engine = QueryEngine::new()
     ///     .with_url("www.url_example.com".to_string());
+    /// # }
     /// ```
     pub fn with_url(&self, new_url: String) -> Self {
+        self.with_urls(vec![new_url])
+    }
+
+    /// Getter for all of the endpoints that the QueryEngine uses, in order of preference.
+    pub fn urls(&self) -> &Vec<String> {
+        self.endpoints.urls()
+    }
+
+    /// Set several endpoints to query, in order of preference. Requests go to the first endpoint
+    /// and fail over to the next one when an endpoint cannot be reached or is overloaded. An engine
+    /// without any endpoints returns [`Error::Config`] for every query. Meant to be used in a
+    /// functional style
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::QueryEngine;
+    ///
+    /// let engine = QueryEngine::new()
+    ///     .with_urls(vec![
+    ///         "http://localhost:12345/api/interpreter".to_string(),
+    ///         "https://overpass-api.de/api/interpreter".to_string(),
+    ///         "https://overpass.kumi.systems/api/interpreter".to_string(),
+    ///     ]);
+    /// # }
+    /// ```
+    pub fn with_urls(&self, new_urls: Vec<String>) -> Self {
         Self {
-            base_url: new_url,
+            endpoints: Endpoints::new(new_urls).with_round_robin(self.endpoints.round_robin()),
             ..self.clone()
         }
     }
 
-    /// Getter for the default way filters used in queries.
+    /// Getter for whether requests are spread over the endpoints in turn.
+    pub fn round_robin(&self) -> bool {
+        self.endpoints.round_robin()
+    }
+
+    /// Set whether requests are spread over the endpoints in turn, rather than always starting at
+    /// the first one. Failover still applies to each request. Meant to be used in a functional style
+    pub fn with_round_robin(&self, round_robin: bool) -> Self {
+        Self {
+            endpoints: self.endpoints.with_round_robin(round_robin),
+            ..self.clone()
+        }
+    }
+
+    /// Get the health record of each endpoint, in the same order as [`Self::urls`].
+    pub fn endpoint_health(&self) -> Vec<EndpointHealth> {
+        self.endpoints.health()
+    }
+
+    /// Getter for the default way filters used in queries. These are the values that the
+    /// `highway` tag may have, and are not used while a [`WayFilter`] is set.
     pub fn filters(&self) -> &Vec<String> {
         &self.way_filters
     }
 
-    /// Set new way filters in queries. Meant to be used in a functional style
+    /// Set new way filters in queries. This replaces any [`WayFilter`] set with
+    /// [`Self::with_way_filter`]. Meant to be used in a functional style
     ///
     /// ```rust
+    /// # #[cfg(feature = "reqwest")] {
     /// use osmgraph::api::QueryEngine;
     ///
     /// let engine = QueryEngine::new()
     ///     .with_filters(vec![String::from("motorway")]);
+    /// # }
     /// ```
     pub fn with_filters(&self, new_filters: Vec<String>) -> Self {
         Self {
             way_filters: new_filters,
+            way_filter: None,
+            ..self.clone()
+        }
+    }
+
+    /// Getter for the [`WayFilter`] used in queries and graphs, if one was set.
+    pub fn way_filter(&self) -> Option<&WayFilter> {
+        self.way_filter.as_ref()
+    }
+
+    /// Select ways with a [`WayFilter`] instead of the `highway` values of [`Self::filters`]. The
+    /// filter is used both in the queries that the engine builds and when the `graph_from_*`
+    /// methods build a graph (with [`create_graph_with_filter`]), so ways do not need a `highway`
+    /// tag. Meant to be used in a functional style
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::{QueryEngine, TagFilter, WayFilter};
+    ///
+    /// //Rail lines that are still in use
+    /// let engine = QueryEngine::new()
+    ///     .with_way_filter(WayFilter::all(vec![
+    ///         WayFilter::tag(TagFilter::regex("railway", "^(rail|light_rail|subway|tram)$")),
+    ///         WayFilter::tag(TagFilter::not_exists("abandoned")),
+    ///     ]));
+    ///
+    /// let query: String = engine.place_query("Selinsgrove", Some(8)).build().unwrap();
+    /// assert!(query.contains(r#"way["railway"~"^(rail|light_rail|subway|tram)$"][!"abandoned"](area.searchArea);"#));
+    /// # }
+    /// ```
+    pub fn with_way_filter(&self, filter: WayFilter) -> Self {
+        Self {
+            way_filter: Some(filter),
+            ..self.clone()
+        }
+    }
+
+    /// Select the ways that a kind of traveller can use, with the [`WayFilter`] of a
+    /// [`NetworkType`] preset. This sets both the query and the rules used to build graphs, the
+    /// same as [`Self::with_way_filter`]. Meant to be used in a functional style
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::{NetworkType, QueryEngine};
+    ///
+    /// let engine = QueryEngine::new()
+    ///     .with_network_type(NetworkType::Walk);
+    ///
+    /// let query: String = engine.place_query("Selinsgrove", Some(8)).build().unwrap();
+    /// assert!(query.contains(r#"["foot"!="no"]"#));
+    /// # }
+    /// ```
+    pub fn with_network_type(&self, network_type: NetworkType) -> Self {
+        self.with_way_filter(network_type.way_filter())
+    }
+
+    /// Getter for the settings put at the top of the queries that the engine builds, such as
+    /// [`Self::query_place`]. Queries given to [`Self::query`] are sent as they are.
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Set how many seconds Overpass may spend on a query (`[timeout:]`). Overpass uses 180 by
+    /// default, and large areas may need more. Meant to be used in a functional style
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::QueryEngine;
+    ///
+    /// let engine = QueryEngine::new()
+    ///     .with_timeout(600)
+    ///     .with_maxsize(2 * 1024 * 1024 * 1024)
+    ///     .with_date("2020-01-01T00:00:00Z".to_string());
+    ///
+    /// let query: String = engine.place_query("Selinsgrove", Some(8)).build().unwrap();
+    /// assert!(query.starts_with(r#"[out:json][timeout:600][maxsize:2147483648][date:"2020-01-01T00:00:00Z"];"#));
+    /// # }
+    /// ```
+    pub fn with_timeout(&self, timeout: u32) -> Self {
+        Self {
+            settings: self.settings.with_timeout(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// Set how many bytes of memory Overpass may use for a query (`[maxsize:]`). Meant to be used
+    /// in a functional style
+    pub fn with_maxsize(&self, maxsize: u64) -> Self {
+        Self {
+            settings: self.settings.with_maxsize(maxsize),
+            ..self.clone()
+        }
+    }
+
+    /// Query the data as it was at a point in the past (`[date:]`), given as an ISO 8601 time
+    /// such as `"2020-01-01T00:00:00Z"`. Meant to be used in a functional style
+    pub fn with_date(&self, date: String) -> Self {
+        Self {
+            settings: self.settings.with_date(date),
+            ..self.clone()
+        }
+    }
+
+    /// Getter for the retry policy used for every request.
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Set a new retry policy. Meant to be used in a functional style
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::{QueryEngine, RetryPolicy};
+    ///
+    /// let engine = QueryEngine::new()
+    ///     .with_policy(RetryPolicy::none());
+    /// # }
+    /// ```
+    pub fn with_policy(&self, new_policy: RetryPolicy) -> Self {
+        Self {
+            policy: new_policy,
+            ..self.clone()
+        }
+    }
+
+    /// Set the [`Transport`] that sends the requests. Meant to be used in a functional style
+    pub fn with_transport(&self, transport: impl Transport + 'static) -> Self {
+        Self {
+            transport: Arc::new(transport),
+            ..self.clone()
+        }
+    }
+
+    /// Getter for the cache that responses are stored in, if there is one.
+    pub fn cache(&self) -> Option<&QueryCache> {
+        self.cache.as_ref()
+    }
+
+    /// Store responses in a cache on disk and answer repeated queries from it. See
+    /// [`QueryCache`] for the details. Meant to be used in a functional style
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::{QueryEngine, QueryCache};
+    ///
+    /// let engine = QueryEngine::new()
+    ///     .with_cache(QueryCache::new("./osm_cache"));
+    /// # }
+    /// ```
+    pub fn with_cache(&self, cache: QueryCache) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self.clone()
+        }
+    }
+
+    /// Stop using a cache. Meant to be used in a functional style
+    pub fn without_cache(&self) -> Self {
+        Self {
+            cache: None,
             ..self.clone()
         }
     }
@@ -100,60 +387,55 @@ impl QueryEngine {
     ///
     /// Example: 
     ///
-    /// ```rust
+    /// ```rust,no_run
+    /// # #[cfg(feature = "reqwest")] {
     /// use osmgraph::api::{QueryEngine, OverpassResponse};
     ///
     /// let response: String = QueryEngine::new()
     ///     .query_place_blocking("Selinsgrove".to_string(), Some(7))
     ///     .expect("Could not query the server!");
+    /// # }
     /// ```
     pub async fn query_place(&self, area_name: String, admin_level: Option<usize>) -> Result<String, Error> {
+        self.query(self.place_query(&area_name, admin_level).build()?).await
+    }
 
-        let this_admin_level: String = match admin_level {
-            Some(num) => format!("[admin_level={num}]"),
-            None => "".to_string(),
-        };
-
-        let way_filter: String = match &self.way_filters.len() {
-            0 => "(area.searchArea)".to_string(),
-            _ => {
-                format!("[\"highway\"~\"{}\"](area.searchArea)",
-                    &self.way_filters.join("|"))
-            }
-        };
-
-        //Return a query with the specified city name
-        self.query(format!(r#"
-            [out:json];
-            area[name="{area_name}"]{this_admin_level}->.searchArea;
-
-            //Find all ways according to filter
-            (
-              way{way_filter};
-            );
+    /// Build the query that [`Self::query_place`] sends, so that it can be looked at or changed
+    /// before sending it with [`Self::query`].
+    pub fn place_query(&self, area_name: &str, admin_level: Option<usize>) -> QueryBuilder {
+        self.add_graph_output(self.place_selection(area_name, admin_level))
+    }
 
-            //Get nodes associated with ways defined before
-            (._; >;);
+    /// Start a query that selects the ways of a place by name.
+    fn place_selection(&self, area_name: &str, admin_level: Option<usize>) -> QueryBuilder {
 
-            out body; >;
+        let mut area: Selector = Selector::area()
+            .with_tag(TagFilter::equals("name", area_name));
+        if let Some(num) = admin_level {
+            area = area.with_tag(TagFilter::equals("admin_level", &num.to_string()));
+        }
 
-            out skel qt;"#
-        )).await
+        self.new_query()
+            .with_statement(Statement::Select(area.with_output_set("searchArea")))
+            .with_statement(self.select_ways(|ways| ways.with_area("searchArea")))
     }
 
     /// This function does the same thing as [`Self::query_place`] but waits for the request to complete
     pub fn query_place_blocking(&self, area_name: String, admin_level: Option<usize>) -> Result<String, Error> {
-        Runtime::new()?
-            .block_on(self.query_place(area_name, admin_level))
+        block_on(self.query_place(area_name, admin_level))
     }
 
-    /// Given a closed polygon, return all of the nodes and ways within that polygon.
+    /// Given a polygon of `(lat, lon)` points, return all of the nodes and ways within that
+    /// polygon.
     ///
-    /// **Note**: the first and last element of the vector must be the same!
+    /// The polygon is checked and normalized with [`Polygon::new`] first: an open ring is closed
+    /// and repeated points are removed. A ring that is degenerate or crosses itself returns
+    /// [`Error::InvalidGeometry`] without sending a request.
     ///
     /// Example: 
     ///
-    /// ```rust
+    /// ```rust,no_run
+    /// # #[cfg(feature = "reqwest")] {
     /// use osmgraph::api::{QueryEngine, OverpassResponse};
     /// 
     /// //A big box
@@ -174,70 +456,872 @@ impl QueryEngine {
     ///         (32.407, -64.896),
     ///     ])
     ///     .expect("Could not query the server!");
+    /// # }
     /// ```
     pub async fn query_poly(&self, polygon: Vec<(f64, f64)>) -> Result<String, Error> {
+        self.query(self.poly_query(&[Polygon::new(polygon)?]).build()?).await
+    }
+
+    /// Build the query that [`Self::query_poly`] and [`Self::query_geojson`] send. Several
+    /// polygons are searched as a union.
+    pub fn poly_query(&self, polygons: &[Polygon]) -> QueryBuilder {
+        self.add_graph_output(self.poly_selection(polygons))
+    }
+
+    /// Start a query that selects the ways within any of the polygons.
+    fn poly_selection(&self, polygons: &[Polygon]) -> QueryBuilder {
+
+        let selectors: Vec<Statement> = polygons.iter()
+            .flat_map(|polygon| self.way_selectors()
+                .into_iter()
+                .map(|ways| Statement::Select(ways.with_poly(polygon.points().clone()))))
+            .collect();
+
+        self.new_query().with_statement(union(selectors))
+    }
+
+    /// This function does the same thing as [`Self::query_poly`] but waits for the request to complete
+    pub fn query_poly_blocking(&self, polygon: Vec<(f64, f64)>) -> Result<String, Error> {
+        block_on(self.query_poly(polygon))
+    }
+
+    /// Given a GeoJSON `Polygon` or `MultiPolygon` (or a `Feature` or `FeatureCollection` of
+    /// them), return all of the nodes and ways within the polygons. This makes it possible to pass
+    /// an area drawn on a web map straight in. See [`Polygon::from_geojson`] for how the GeoJSON
+    /// is read.
+    ///
+    /// Example:
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::QueryEngine;
+    ///
+    /// let response: String = QueryEngine::new()
+    ///     .query_geojson_blocking(r#"{
+    ///         "type": "Polygon",
+    ///         "coordinates": [[[-76.88, 40.78], [-76.84, 40.78], [-76.84, 40.82], [-76.88, 40.82]]]
+    ///     }"#)
+    ///     .expect("Could not query the server!");
+    /// # }
+    /// ```
+    pub async fn query_geojson(&self, geojson: &str) -> Result<String, Error> {
+        self.query(self.poly_query(&Polygon::from_geojson(geojson)?).build()?).await
+    }
+
+    /// This function does the same thing as [`Self::query_geojson`] but waits for the request to complete
+    pub fn query_geojson_blocking(&self, geojson: &str) -> Result<String, Error> {
+        block_on(self.query_geojson(geojson))
+    }
+
+    /// Given a bounding box, return all of the nodes and ways inside it. The same way filters are
+    /// applied as in [`Self::query_place`].
+    ///
+    /// A box whose south edge is not below its north edge, or whose west edge is not left of its
+    /// east edge, returns [`Error::InvalidGeometry`].
+    ///
+    /// Example:
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::QueryEngine;
+    ///
+    /// //Selinsgrove, PA
+    /// let response: String = QueryEngine::new()
+    ///     .query_bbox_blocking(40.78, -76.88, 40.82, -76.84)
+    ///     .expect("Could not query the server!");
+    /// # }
+    /// ```
+    pub async fn query_bbox(&self, south: f64, west: f64, north: f64, east: f64) -> Result<String, Error> {
+        self.query(self.bbox_query(south, west, north, east)?.build()?).await
+    }
+
+    /// Build the query that [`Self::query_bbox`] sends. The bounding box is checked the same way.
+    pub fn bbox_query(&self, south: f64, west: f64, north: f64, east: f64) -> Result<QueryBuilder, Error> {
+        Ok(self.add_graph_output(self.bbox_selection(south, west, north, east)?))
+    }
+
+    /// Start a query that selects the ways inside a bounding box, after checking the box.
+    fn bbox_selection(&self, south: f64, west: f64, north: f64, east: f64) -> Result<QueryBuilder, Error> {
+
+        check_bbox(south, west, north, east)?;
+
+        Ok(self.new_query()
+            .with_statement(self.select_ways(|ways| ways.with_bbox(south, west, north, east))))
+    }
+
+    /// This function does the same thing as [`Self::query_bbox`] but waits for the request to complete
+    pub fn query_bbox_blocking(&self, south: f64, west: f64, north: f64, east: f64) -> Result<String, Error> {
+        block_on(self.query_bbox(south, west, north, east))
+    }
+
+    /// Given a point and a radius in meters, return all of the nodes and ways within that distance
+    /// of the point. The same way filters are applied as in [`Self::query_place`].
+    ///
+    /// A radius that is not a positive number returns [`Error::InvalidGeometry`].
+    ///
+    /// Example:
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::QueryEngine;
+    ///
+    /// //Everything within 2km of a depot
+    /// let response: String = QueryEngine::new()
+    ///     .query_around_blocking(40.80, -76.86, 2000.0)
+    ///     .expect("Could not query the server!");
+    /// # }
+    /// ```
+    pub async fn query_around(&self, lat: f64, lon: f64, radius_m: f64) -> Result<String, Error> {
+        self.query(self.around_query(lat, lon, radius_m)?.build()?).await
+    }
+
+    /// Build the query that [`Self::query_around`] sends. The radius is checked the same way.
+    pub fn around_query(&self, lat: f64, lon: f64, radius_m: f64) -> Result<QueryBuilder, Error> {
+
+        if !(radius_m > 0.0 && radius_m.is_finite()) {
+            return Err(Error::InvalidGeometry(format!("radius {radius_m} must be a positive number of meters")))
+        }
+
+        let query: QueryBuilder = self.new_query()
+            .with_statement(self.select_ways(|ways| ways.with_around(radius_m, lat, lon)));
+
+        Ok(self.add_graph_output(query))
+    }
+
+    /// This function does the same thing as [`Self::query_around`] but waits for the request to complete
+    pub fn query_around_blocking(&self, lat: f64, lon: f64, radius_m: f64) -> Result<String, Error> {
+        block_on(self.query_around(lat, lon, radius_m))
+    }
+
+    /// Does the same thing as [`Self::query_place`], but parses the response.
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::{QueryEngine, OverpassResponse};
+    ///
+    /// let response: OverpassResponse = QueryEngine::new()
+    ///     .fetch_place_blocking("Selinsgrove".to_string(), Some(8))
+    ///     .expect("Could not query the server!");
+    /// println!("{} elements", response.elements().len());
+    /// # }
+    /// ```
+    pub async fn fetch_place(&self, area_name: String, admin_level: Option<usize>) -> Result<OverpassResponse, Error> {
+        self.fetch(self.place_query(&area_name, admin_level).build()?).await
+    }
+
+    /// This function does the same thing as [`Self::fetch_place`] but waits for the request to complete
+    pub fn fetch_place_blocking(&self, area_name: String, admin_level: Option<usize>) -> Result<OverpassResponse, Error> {
+        block_on(self.fetch_place(area_name, admin_level))
+    }
+
+    /// Does the same thing as [`Self::query_poly`], but parses the response.
+    pub async fn fetch_poly(&self, polygon: Vec<(f64, f64)>) -> Result<OverpassResponse, Error> {
+        self.fetch(self.poly_query(&[Polygon::new(polygon)?]).build()?).await
+    }
+
+    /// This function does the same thing as [`Self::fetch_poly`] but waits for the request to complete
+    pub fn fetch_poly_blocking(&self, polygon: Vec<(f64, f64)>) -> Result<OverpassResponse, Error> {
+        block_on(self.fetch_poly(polygon))
+    }
+
+    /// Does the same thing as [`Self::query_geojson`], but parses the response.
+    pub async fn fetch_geojson(&self, geojson: &str) -> Result<OverpassResponse, Error> {
+        self.fetch(self.poly_query(&Polygon::from_geojson(geojson)?).build()?).await
+    }
+
+    /// This function does the same thing as [`Self::fetch_geojson`] but waits for the request to complete
+    pub fn fetch_geojson_blocking(&self, geojson: &str) -> Result<OverpassResponse, Error> {
+        block_on(self.fetch_geojson(geojson))
+    }
+
+    /// Does the same thing as [`Self::query_bbox`], but parses the response.
+    pub async fn fetch_bbox(&self, south: f64, west: f64, north: f64, east: f64) -> Result<OverpassResponse, Error> {
+        self.fetch(self.bbox_query(south, west, north, east)?.build()?).await
+    }
+
+    /// This function does the same thing as [`Self::fetch_bbox`] but waits for the request to complete
+    pub fn fetch_bbox_blocking(&self, south: f64, west: f64, north: f64, east: f64) -> Result<OverpassResponse, Error> {
+        block_on(self.fetch_bbox(south, west, north, east))
+    }
+
+    /// Does the same thing as [`Self::query_around`], but parses the response.
+    pub async fn fetch_around(&self, lat: f64, lon: f64, radius_m: f64) -> Result<OverpassResponse, Error> {
+        self.fetch(self.around_query(lat, lon, radius_m)?.build()?).await
+    }
+
+    /// This function does the same thing as [`Self::fetch_around`] but waits for the request to complete
+    pub fn fetch_around_blocking(&self, lat: f64, lon: f64, radius_m: f64) -> Result<OverpassResponse, Error> {
+        block_on(self.fetch_around(lat, lon, radius_m))
+    }
+
+    /// Query an area by name, like [`Self::query_place`], and build a graph out of the result.
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::QueryEngine;
+    /// use osmgraph::graph::OSMGraph;
+    ///
+    /// let graph: OSMGraph = QueryEngine::new()
+    ///     .graph_from_place_blocking("Selinsgrove".to_string(), Some(8))
+    ///     .expect("Could not create the graph!");
+    /// println!("{} nodes and {} edges", graph.node_count(), graph.edge_count());
+    /// # }
+    /// ```
+    pub async fn graph_from_place(&self, area_name: String, admin_level: Option<usize>) -> Result<OSMGraph, Error> {
+        self.create_graph(self.fetch_place(area_name, admin_level).await?.elements())
+    }
+
+    /// This function does the same thing as [`Self::graph_from_place`] but waits for the request to complete
+    pub fn graph_from_place_blocking(&self, area_name: String, admin_level: Option<usize>) -> Result<OSMGraph, Error> {
+        block_on(self.graph_from_place(area_name, admin_level))
+    }
+
+    /// Query a polygon, like [`Self::query_poly`], and build a graph out of the result.
+    pub async fn graph_from_poly(&self, polygon: Vec<(f64, f64)>) -> Result<OSMGraph, Error> {
+        self.create_graph(self.fetch_poly(polygon).await?.elements())
+    }
+
+    /// This function does the same thing as [`Self::graph_from_poly`] but waits for the request to complete
+    pub fn graph_from_poly_blocking(&self, polygon: Vec<(f64, f64)>) -> Result<OSMGraph, Error> {
+        block_on(self.graph_from_poly(polygon))
+    }
+
+    /// Query the polygons of a GeoJSON string, like [`Self::query_geojson`], and build a graph out
+    /// of the result.
+    pub async fn graph_from_geojson(&self, geojson: &str) -> Result<OSMGraph, Error> {
+        self.create_graph(self.fetch_geojson(geojson).await?.elements())
+    }
+
+    /// This function does the same thing as [`Self::graph_from_geojson`] but waits for the request to complete
+    pub fn graph_from_geojson_blocking(&self, geojson: &str) -> Result<OSMGraph, Error> {
+        block_on(self.graph_from_geojson(geojson))
+    }
+
+    /// Query a bounding box, like [`Self::query_bbox`], and build a graph out of the result.
+    pub async fn graph_from_bbox(&self, south: f64, west: f64, north: f64, east: f64) -> Result<OSMGraph, Error> {
+        self.create_graph(self.fetch_bbox(south, west, north, east).await?.elements())
+    }
+
+    /// This function does the same thing as [`Self::graph_from_bbox`] but waits for the request to complete
+    pub fn graph_from_bbox_blocking(&self, south: f64, west: f64, north: f64, east: f64) -> Result<OSMGraph, Error> {
+        block_on(self.graph_from_bbox(south, west, north, east))
+    }
+
+    /// Query the area around a point, like [`Self::query_around`], and build a graph out of the
+    /// result.
+    pub async fn graph_from_around(&self, lat: f64, lon: f64, radius_m: f64) -> Result<OSMGraph, Error> {
+        self.create_graph(self.fetch_around(lat, lon, radius_m).await?.elements())
+    }
+
+    /// This function does the same thing as [`Self::graph_from_around`] but waits for the request to complete
+    pub fn graph_from_around_blocking(&self, lat: f64, lon: f64, radius_m: f64) -> Result<OSMGraph, Error> {
+        block_on(self.graph_from_around(lat, lon, radius_m))
+    }
+
+    /// Find out what changed in an area between two dates, given as ISO 8601 times such as
+    /// `"2020-01-01T00:00:00Z"`. A missing `to` means now. The same way filters are applied as in
+    /// [`Self::query_place`], and the changes come from an augmented diff (`[adiff:]`), so only
+    /// the elements that changed are sent.
+    ///
+    /// The result can be applied to a graph built at `from` (for instance with
+    /// [`Self::with_date`]) to get the graph at `to`:
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::QueryEngine;
+    /// use osmgraph::graph::{ChangeSet, OSMGraph};
+    ///
+    /// let engine = QueryEngine::new();
+    ///
+    /// let mut graph: OSMGraph = engine
+    ///     .with_date("2020-01-01T00:00:00Z".to_string())
+    ///     .graph_from_place_blocking("Selinsgrove".to_string(), Some(8))
+    ///     .expect("Could not create the graph!");
+    ///
+    /// let changes: ChangeSet = engine
+    ///     .changes_place_blocking("Selinsgrove".to_string(), Some(8), "2020-01-01T00:00:00Z".to_string(), None)
+    ///     .expect("Could not query the server!");
+    /// println!("{} ways were created", changes.created_ways().len());
+    ///
+    /// changes.apply(&mut graph).expect("Could not apply the changes!");
+    /// # }
+    /// ```
+    pub async fn changes_place(&self, area_name: String, admin_level: Option<usize>, from: String, to: Option<String>) -> Result<ChangeSet, Error> {
+        let query: QueryBuilder = self.place_selection(&area_name, admin_level);
+        self.fetch_changes(self.add_changes_output(query, from, to).build()?).await
+    }
+
+    /// This function does the same thing as [`Self::changes_place`] but waits for the request to complete
+    pub fn changes_place_blocking(&self, area_name: String, admin_level: Option<usize>, from: String, to: Option<String>) -> Result<ChangeSet, Error> {
+        block_on(self.changes_place(area_name, admin_level, from, to))
+    }
+
+    /// Find out what changed in a polygon between two dates, like [`Self::changes_place`]. The
+    /// polygon is checked with [`Polygon::new`].
+    pub async fn changes_poly(&self, polygon: Vec<(f64, f64)>, from: String, to: Option<String>) -> Result<ChangeSet, Error> {
+        let query: QueryBuilder = self.poly_selection(&[Polygon::new(polygon)?]);
+        self.fetch_changes(self.add_changes_output(query, from, to).build()?).await
+    }
+
+    /// This function does the same thing as [`Self::changes_poly`] but waits for the request to complete
+    pub fn changes_poly_blocking(&self, polygon: Vec<(f64, f64)>, from: String, to: Option<String>) -> Result<ChangeSet, Error> {
+        block_on(self.changes_poly(polygon, from, to))
+    }
+
+    /// Find out what changed in a bounding box between two dates, like [`Self::changes_place`].
+    /// The bounding box is checked the same way as in [`Self::query_bbox`].
+    pub async fn changes_bbox(&self, south: f64, west: f64, north: f64, east: f64, from: String, to: Option<String>) -> Result<ChangeSet, Error> {
+        let query: QueryBuilder = self.bbox_selection(south, west, north, east)?;
+        self.fetch_changes(self.add_changes_output(query, from, to).build()?).await
+    }
+
+    /// This function does the same thing as [`Self::changes_bbox`] but waits for the request to complete
+    pub fn changes_bbox_blocking(&self, south: f64, west: f64, north: f64, east: f64, from: String, to: Option<String>) -> Result<ChangeSet, Error> {
+        block_on(self.changes_bbox(south, west, north, east, from, to))
+    }
+
+    /// Fetch a large bounding box by splitting it into tiles, as set by `options`, and fetching
+    /// the tiles concurrently. The tiles are merged into one response with
+    /// [`OverpassResponse::merge`]. Each tile selects every way that passes through it along with
+    /// all of that way's nodes, so ways that cross the edge of a tile come back whole and are only
+    /// kept once.
+    ///
+    /// A tile that fails in a way that is worth retrying, or that Overpass could not finish, is
+    /// fetched again on its own, up to [`TileOptions::max_retries`] times. Tiles are only retried
+    /// this way: the `max_retries` of the engine's [`RetryPolicy`] is not used for them, so the two
+    /// do not multiply. If a tile fails for good, the whole fetch returns its error. An area that
+    /// would need more than 10 000 tiles returns [`Error::InvalidGeometry`].
+    ///
+    /// Example:
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::{QueryEngine, OverpassResponse, TileOptions};
+    ///
+    /// //Most of Manhattan in tiles of about 2km
+    /// let options = TileOptions::new()
+    ///     .with_tile_size(0.02)
+    ///     .with_progress(|progress| println!("{}/{}", progress.completed(), progress.total()));
+    ///
+    /// let response: OverpassResponse = QueryEngine::new()
+    ///     .fetch_bbox_tiled_blocking(40.70, -74.02, 40.80, -73.93, &options)
+    ///     .expect("Could not query the server!");
+    /// # }
+    /// ```
+    pub async fn fetch_bbox_tiled(&self, south: f64, west: f64, north: f64, east: f64, options: &TileOptions) -> Result<OverpassResponse, Error> {
 
-        assert!(polygon[0] == polygon[polygon.len()-1], "Beginning and end of polygon must be the same point!");
+        check_bbox(south, west, north, east)?;
 
-        let polyline_string: String = polygon
-            .iter()
-            .map(|(lat, lon)| format!("{lat} {lon}"))
-            .collect::<Vec<String>>()
-            .join(" ");
+        let tiles: Vec<(Bounds, String)> = grid(south, west, north, east, options.tile_size())?
+            .into_iter()
+            .map(|tile| {
+                let ways: Statement = self.select_ways(|ways| {
+                    ways.with_bbox(tile.minlat(), tile.minlon(), tile.maxlat(), tile.maxlon())
+                });
+                let query: QueryBuilder = self.new_query().with_statement(ways);
+                self.add_graph_output(query).build().map(|query| (tile, query))
+            })
+            .collect::<Result<_, Error>>()?;
 
-        let way_filter: String = match &self.way_filters.len() {
-            0 => "".to_string(),
-            _ => {
-                format!("[\"highway\"~\"{}\"]",
-                    &self.way_filters.join("|"))
+        self.fetch_tiles(tiles, options).await
+    }
+
+    /// This function does the same thing as [`Self::fetch_bbox_tiled`] but waits for the request to complete
+    pub fn fetch_bbox_tiled_blocking(&self, south: f64, west: f64, north: f64, east: f64, options: &TileOptions) -> Result<OverpassResponse, Error> {
+        block_on(self.fetch_bbox_tiled(south, west, north, east, options))
+    }
+
+    /// Fetch a large polygon by splitting it into tiles, the same way as
+    /// [`Self::fetch_bbox_tiled`]. The tiles cover the bounding box of the polygon, but tiles that
+    /// do not overlap the polygon are never fetched. The polygon is checked with [`Polygon::new`].
+    pub async fn fetch_poly_tiled(&self, polygon: Vec<(f64, f64)>, options: &TileOptions) -> Result<OverpassResponse, Error> {
+
+        let polygon: Polygon = Polygon::new(polygon)?;
+        let bounds: Bounds = polygon.bounds();
+
+        let tiles: Vec<(Bounds, String)> = grid(bounds.minlat(), bounds.minlon(), bounds.maxlat(), bounds.maxlon(), options.tile_size())?
+            .into_iter()
+            .filter(|tile| polygon.intersects(tile))
+            .map(|tile| {
+                let ways: Statement = self.select_ways(|ways| {
+                    ways.with_poly(polygon.points().clone())
+                        .with_bbox(tile.minlat(), tile.minlon(), tile.maxlat(), tile.maxlon())
+                });
+                let query: QueryBuilder = self.new_query().with_statement(ways);
+                self.add_graph_output(query).build().map(|query| (tile, query))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        self.fetch_tiles(tiles, options).await
+    }
+
+    /// This function does the same thing as [`Self::fetch_poly_tiled`] but waits for the request to complete
+    pub fn fetch_poly_tiled_blocking(&self, polygon: Vec<(f64, f64)>, options: &TileOptions) -> Result<OverpassResponse, Error> {
+        block_on(self.fetch_poly_tiled(polygon, options))
+    }
+
+    /// Fetch the query of every tile, at most `options.concurrency()` at a time, and merge the
+    /// results in the order of the tiles.
+    async fn fetch_tiles(&self, tiles: Vec<(Bounds, String)>, options: &TileOptions) -> Result<OverpassResponse, Error> {
+
+        let total: usize = tiles.len();
+        let mut results: Vec<Option<OverpassResponse>> = vec![None; total];
+        let mut completed: usize = 0;
+
+        //Tiles waiting to be fetched, with their attempt and how long to wait before starting
+        let mut queue: VecDeque<(usize, usize, Duration)> = (0..total)
+            .map(|index| (index, 0, Duration::ZERO))
+            .collect();
+        let mut running: JoinSet<(usize, usize, Result<OverpassResponse, Error>)> = JoinSet::new();
+
+        //Tiles are retried here, so each fetch is only tried once
+        let once: QueryEngine = self.with_policy(self.policy.with_max_retries(0));
+
+        loop {
+            while running.len() < options.concurrency() {
+                let Some((index, attempt, wait)) = queue.pop_front() else { break };
+                let engine: QueryEngine = once.clone();
+                let query: String = tiles[index].1.clone();
+                running.spawn(async move {
+                    tokio::time::sleep(wait).await;
+                    (index, attempt, engine.fetch(query).await)
+                });
             }
-        };
 
-        //Return a query with the specified city name
-        self.query(format!(r#"
-            [out:json];
+            let Some(joined) = running.join_next().await else { break };
+            let (index, attempt, result) = joined.map_err(|e| Error::Transport(Box::new(e)))?;
+            let bounds: &Bounds = &tiles[index].0;
 
-            //Get the ways from the polygon
-            way{way_filter}(poly:"{polyline_string}");
+            match result {
+                Ok(response) => {
+                    completed += 1;
+                    let elements: usize = response.elements().len();
+                    options.report(index, total, completed, bounds, attempt, TileStatus::Done { elements });
+                    results[index] = Some(response);
+                },
+                Err(e) if (e.is_retryable() || matches!(e, Error::Incomplete { .. })) && attempt < options.max_retries() => {
+                    options.report(index, total, completed, bounds, attempt, TileStatus::Retrying(e.to_string()));
+                    queue.push_back((index, attempt + 1, self.policy.delay(attempt)));
+                },
+                Err(e) => {
+                    options.report(index, total, completed, bounds, attempt, TileStatus::Failed(e.to_string()));
+                    running.abort_all();
+                    return Err(e)
+                }
+            }
+        }
 
-            //Get the nodes and anything else on the way
-            (._; >;);
+        Ok(OverpassResponse::merge(results.into_iter().flatten()))
+    }
 
-            out body; >;
+    /// Get the selectors for the ways that pass the engine's way filters. There is more than one
+    /// if the [`WayFilter`] has alternatives.
+    fn way_selectors(&self) -> Vec<Selector> {
+        match (&self.way_filter, self.way_filters.len()) {
+            (Some(filter), _) => filter.selectors(),
+            (None, 0) => vec![Selector::way()],
+            (None, _) => vec![Selector::way().with_tag(TagFilter::regex("highway", &self.way_filters.join("|")))]
+        }
+    }
 
-            out skel qt;"#
-        )).await
+    /// Select the ways that pass the engine's way filters, with spatial filters added to each
+    /// selector.
+    fn select_ways(&self, spatial: impl Fn(Selector) -> Selector) -> Statement {
+        union(self.way_selectors().into_iter().map(|ways| Statement::Select(spatial(ways))).collect())
     }
 
-    /// This function does the same thing as [`Self::query_poly`] but waits for the request to complete
-    pub fn query_poly_blocking(&self, polygon: Vec<(f64, f64)>) -> Result<String, Error> {
-        Runtime::new()?
-            .block_on(self.query_poly(polygon))
+    /// Build a graph with the engine's [`WayFilter`], if one was set.
+    fn create_graph(&self, elements: &[Element]) -> Result<OSMGraph, Error> {
+        match &self.way_filter {
+            Some(filter) => create_graph_with_filter(elements, filter),
+            None => create_graph(elements)
+        }
+    }
+
+    /// Start a query with the engine's settings.
+    fn new_query(&self) -> QueryBuilder {
+        QueryBuilder::new().with_settings(self.settings.clone())
+    }
+
+    /// Add the statements that print the selected ways along with all of their nodes.
+    fn add_graph_output(&self, query: QueryBuilder) -> QueryBuilder {
+        query
+            .with_statement(Statement::Union(vec![Statement::Current, Statement::RecurseDown]))
+            .with_statement(Statement::Out(vec![OutMode::Body]))
+            .with_statement(Statement::RecurseDown)
+            .with_statement(Statement::Out(vec![OutMode::Skel, OutMode::Qt]))
+    }
+
+    /// Turn a query into an augmented diff between two dates that prints the selected ways along
+    /// with their nodes. Ways are printed with their geometry so that the nodes they gain can be
+    /// placed even if the nodes themselves did not change.
+    fn add_changes_output(&self, query: QueryBuilder, from: String, to: Option<String>) -> QueryBuilder {
+        query
+            .with_settings(query.settings().with_diff(Diff::Augmented { from, to }))
+            .with_statement(Statement::Union(vec![Statement::Current, Statement::RecurseDown]))
+            .with_statement(Statement::Out(vec![OutMode::Meta, OutMode::Geom]))
     }
 
     /// Requests data from the Overpass API given a particular query. The query must conform to the
     /// Overpass Query Language.
+    ///
+    /// The response is checked before it is returned:
+    /// - A status code that is not a success (such as 429 or 504) returns [`Error::HttpStatus`].
+    /// - An HTML error page returns [`Error::Overpass`], or [`Error::Timeout`] if the server gave up
+    ///   on the query.
+    /// - A response whose `remark` reports a runtime error returns [`Error::Incomplete`], which
+    ///   still holds the partial response in case you want to use it anyway.
+    ///
+    /// A 400 or a parse error means the query itself is wrong, and is returned right away. Any
+    /// other failure moves on to the next endpoint. Once every endpoint has failed, the whole round
+    /// is retried according to the engine's [`RetryPolicy`] if any of the failures is worth
+    /// retrying.
     pub async fn query(&self, query: String) -> Result<String, Error> {
+        let bytes: Vec<u8> = self.query_bytes(&query).await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
 
-        let response = self.client
-            .post(&self.base_url)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .body(format!("data={}", query))
-            .send()
-            .await
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+    /// Behaves the same as [`Self::query`], but will wait for the function to finish before continuing.
+    pub fn query_blocking(&self, query: String) -> Result<String, Error> {
+        block_on(self.query(query))
+    }
 
-        // Parse the response as JSON
-        let json_string: String = response.text()
-            .await
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+    /// Requests data from the Overpass API given a particular query and parses the response. The
+    /// query must ask for json output (`[out:json]`). Errors are reported the same way as in
+    /// [`Self::query`], and a response that does not parse returns [`Error::Parse`].
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::{QueryEngine, OverpassResponse};
+    ///
+    /// let response: OverpassResponse = QueryEngine::new()
+    ///     .fetch_blocking("[out:json];node(1);out;".to_string())
+    ///     .expect("Could not query the server!");
+    /// # }
+    /// ```
+    pub async fn fetch(&self, query: String) -> Result<OverpassResponse, Error> {
+
+        if let Some(body) = self.cached(&query).await? {
+            return Ok(serde_json::from_slice(&body)?)
+        }
+
+        //The response is parsed while it is checked, so the body is only read once
+        let (url, (checked, response)) = self.request(&query, check_json).await?;
+        self.store(&url, &query, &checked).await;
 
-        Ok(json_string)
+        response
     }
 
-    /// Behaves the same as [`Self::query`], but will wait for the function to finish before continuing.
-    pub fn query_blocking(&self, query: String) -> Result<String, Error> {
-        Runtime::new()?
-            .block_on(self.query(query))
+    /// Behaves the same as [`Self::fetch`], but will wait for the function to finish before continuing.
+    pub fn fetch_blocking(&self, query: String) -> Result<OverpassResponse, Error> {
+        block_on(self.fetch(query))
+    }
+
+    /// Requests the changes between two dates from the Overpass API, given a `[diff:]` or
+    /// `[adiff:]` query with XML output. Errors are reported the same way as in [`Self::query`],
+    /// and a response that does not parse returns [`Error::Parse`]. See [`ChangeSet`] for how the
+    /// answer is read. The engine's [`WayFilter`], if one was set, decides which ways are kept.
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "reqwest")] {
+    /// use osmgraph::api::QueryEngine;
+    /// use osmgraph::graph::ChangeSet;
+    ///
+    /// let changes: ChangeSet = QueryEngine::new()
+    ///     .fetch_changes_blocking(r#"[out:xml][adiff:"2020-01-01T00:00:00Z"];way[highway](40.79,-76.87,40.80,-76.85);(._;>;);out meta geom;"#.to_string())
+    ///     .expect("Could not query the server!");
+    /// # }
+    /// ```
+    pub async fn fetch_changes(&self, query: String) -> Result<ChangeSet, Error> {
+        let bytes: Vec<u8> = self.query_bytes(&query).await?;
+        match &self.way_filter {
+            Some(filter) => ChangeSet::from_xml_with_filter(&bytes, filter),
+            None => ChangeSet::from_xml(&bytes)
+        }
+    }
+
+    /// Behaves the same as [`Self::fetch_changes`], but will wait for the function to finish before continuing.
+    pub fn fetch_changes_blocking(&self, query: String) -> Result<ChangeSet, Error> {
+        block_on(self.fetch_changes(query))
+    }
+
+    /// Get the raw bytes of the response to a query, from the cache if there is a fresh response
+    /// for any of the endpoints, and from the network otherwise.
+    async fn query_bytes(&self, query: &str) -> Result<Vec<u8>, Error> {
+
+        if let Some(body) = self.cached(query).await? {
+            return Ok(body)
+        }
+
+        let (url, checked) = self.request(query, check_response).await?;
+        self.store(&url, query, &checked).await;
+
+        Ok(checked.body)
+    }
+
+    /// Get a fresh response to a query from the cache, for any of the endpoints. Returns `None`
+    /// if there is no cache or no such response, and [`Error::CacheMiss`] if the cache is offline.
+    async fn cached(&self, query: &str) -> Result<Option<Vec<u8>>, Error> {
+
+        let Some(cache) = &self.cache else {
+            return Ok(None)
+        };
+
+        for url in self.endpoints.urls() {
+            if let Some(body) = cache.get(url, query).await? {
+                return Ok(Some(body))
+            }
+        }
+
+        match cache.offline() {
+            true => Err(Error::CacheMiss(query.to_string())),
+            false => Ok(None)
+        }
+    }
+
+    /// Store a checked response in the cache, if there is one.
+    async fn store(&self, url: &str, query: &str, checked: &Checked) {
+        if let Some(cache) = &self.cache {
+            //The response is good even if it could not be stored
+            let _ = cache.put(url, query, &checked.body, checked.osm_base.clone()).await;
+        }
+    }
+
+    /// Send a query, following the retry policy and failing over between endpoints, and return
+    /// the endpoint that answered along with the response, as checked by `check`.
+    async fn request<T>(&self, query: &str, check: CheckFn<T>) -> Result<(String, T), Error> {
+
+        let mut attempt: usize = 0;
+
+        loop {
+            let mut last_error: Option<Error> = None;
+            let mut retryable: bool = false;
+
+            for url in self.endpoints.order() {
+                self.wait_for_turn(&url).await;
+
+                match self.send(&url, query, check).await {
+                    Ok(checked) => {
+                        self.endpoints.record_success(&url);
+                        return Ok((url, checked))
+                    },
+                    //Every endpoint gives the same answer to a mistake in the query
+                    Err(e) if is_query_error(&e) => return Err(e),
+                    Err(e) => {
+                        self.endpoints.record_failure(&url, &e);
+                        retryable |= e.is_retryable();
+                        last_error = Some(e);
+                    }
+                }
+            }
+
+            let Some(error) = last_error else {
+                return Err(Error::Config("no Overpass endpoints are configured".to_string()))
+            };
+
+            if !retryable || attempt >= self.policy.max_retries() {
+                return Err(error)
+            }
+
+            tokio::time::sleep(self.policy.delay(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Wait until the engine is allowed to send its next request. This respects the minimum
+    /// interval of the retry policy and, if enabled, waits for the endpoint to have a free slot,
+    /// asking again after every wait.
+    async fn wait_for_turn(&self, url: &str) {
+
+        //Reserve the next start time before sleeping so that concurrent requests queue up
+        let wait: Duration = {
+            let mut next_request = self.next_request.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let start: Instant = next_request.map_or(now, |next| next.max(now));
+            *next_request = Some(start + self.policy.min_interval());
+            start - now
+        };
+        tokio::time::sleep(wait).await;
+
+        if self.policy.check_slots() {
+            //Another client may take the slot while waiting, so ask again until one is free
+            for _ in 0..MAX_SLOT_CHECKS {
+                match self.slot_wait(url).await {
+                    Some(wait) if !wait.is_zero() => tokio::time::sleep(wait).await,
+                    _ => break
+                }
+            }
+        }
+    }
+
+    /// Ask the endpoint how long until a query slot is free. Any problem reaching the status page
+    /// is ignored, since the query itself will report it.
+    async fn slot_wait(&self, url: &str) -> Option<Duration> {
+
+        let status_url: String = url
+            .strip_suffix("/interpreter")
+            .map(|base| format!("{base}/status"))?;
+
+        let response: HttpResponse = self.transport.send(HttpRequest::get(&status_url)).await.ok()?;
+
+        slot_wait(&String::from_utf8_lossy(response.body()))
+    }
+
+    /// Send a single request to one endpoint and check the response.
+    async fn send<T>(&self, url: &str, query: &str, check: CheckFn<T>) -> Result<T, Error> {
+
+        let response: HttpResponse = self.transport
+            .send(HttpRequest::post_form(url, &[("data", query)]))
+            .await?;
+
+        let status: u16 = response.status();
+        let content_type: Option<String> = response.header("Content-Type").map(|value| value.to_string());
+
+        check(status, content_type.as_deref(), response.into_body())
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Default for QueryEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A complete response, along with the `timestamp_osm_base` it reports, if any.
+struct Checked {
+    body: Vec<u8>,
+    osm_base: Option<String>
+}
+
+/// A function that checks the status, content type and body of a response, such as
+/// [`check_response`].
+type CheckFn<T> = fn(u16, Option<&str>, Vec<u8>) -> Result<T, Error>;
+
+/// The fields of a response that are looked at before it is returned. The elements are skipped
+/// over without being kept.
+#[derive(Deserialize, Default)]
+struct Summary {
+    remark: Option<String>,
+    osm3s: Option<Osm3s>
+}
+
+#[derive(Deserialize, Default)]
+struct Osm3s {
+    timestamp_osm_base: Option<String>
+}
+
+/// Read the remark and the `timestamp_osm_base` of a response in a single pass. Json responses
+/// carry them in the `remark` field and the `osm3s` object, and xml responses in a `<remark>`
+/// element and the `osm_base` attribute of their `<meta>` element.
+fn summarize(content_type: Option<&str>, body: &[u8]) -> Summary {
+
+    let is_json: bool = content_type.is_some_and(|t| t.contains("json"))
+        || body.trim_ascii_start().starts_with(b"{");
+
+    if is_json {
+        return serde_json::from_slice(body).unwrap_or_default()
+    }
+
+    let body = String::from_utf8_lossy(body);
+    let between = |start: &str, end: &str| -> Option<String> {
+        let from: usize = body.find(start)? + start.len();
+        let to: usize = from + body[from..].find(end)?;
+        Some(body[from..to].trim().to_string())
+    };
+
+    Summary {
+        remark: between("<remark>", "</remark>"),
+        osm3s: Some(Osm3s { timestamp_osm_base: between("osm_base=\"", "\"") })
+    }
+}
+
+/// Returns true if an error is about the query itself rather than the endpoint that answered it,
+/// such as a 400 or a parse error reported by Overpass.
+fn is_query_error(error: &Error) -> bool {
+    match error {
+        Error::HttpStatus { status, .. } => *status == 400,
+        Error::Overpass(message) => message.contains("parse error") || message.contains("static error"),
+        _ => false
+    }
+}
+
+/// Pull the error message out of the HTML page that Overpass sends when it rejects a query. The
+/// message follows a bold `Error` label inside a paragraph.
+fn find_html_error(body: &str) -> Option<String> {
+
+    let start: usize = body.find("Error</strong>:")? + "Error</strong>:".len();
+    let end: usize = start + body[start..].find("</p>").unwrap_or(body.len() - start);
+
+    Some(body[start..end].trim().to_string())
+}
+
+/// Put several statements in a union, unless there is only one.
+fn union(mut statements: Vec<Statement>) -> Statement {
+    match statements.len() {
+        1 => statements.remove(0),
+        _ => Statement::Union(statements)
+    }
+}
+
+/// Check the status, content type and remark of a response, turning anything that is not a
+/// complete result into an [`Error`].
+fn check_response(status: u16, content_type: Option<&str>, body: Vec<u8>) -> Result<Checked, Error> {
+    check_status(status, content_type, &body)?;
+    check_remark(content_type, body)
+}
+
+/// Check a response the same way as [`check_response`], but parse a json body along the way and
+/// read the remark and the `timestamp_osm_base` from the parsed response rather than from a
+/// second pass over the body. A body that does not parse is still checked, and the parse error is
+/// handed back with it.
+fn check_json(status: u16, content_type: Option<&str>, body: Vec<u8>) -> Result<(Checked, Result<OverpassResponse, Error>), Error> {
+
+    check_status(status, content_type, &body)?;
+
+    let response: OverpassResponse = match serde_json::from_slice(&body) {
+        Ok(response) => response,
+        Err(error) => return Ok((check_remark(content_type, body)?, Err(error.into())))
+    };
+
+    if let Some(remark) = response.remark().filter(|remark| is_runtime_error(remark)) {
+        return Err(Error::Incomplete { remark: remark.to_string(), body: String::from_utf8_lossy(&body).into_owned() })
+    }
+
+    let osm_base: Option<String> = response.osm3s()["timestamp_osm_base"].as_str().map(|base| base.to_string());
+
+    Ok((Checked { body, osm_base }, Ok(response)))
+}
+
+/// Check the status and content type of a response, turning an error status or an HTML error
+/// page into an [`Error`].
+fn check_status(status: u16, content_type: Option<&str>, body: &[u8]) -> Result<(), Error> {
+
+    if !(200..300).contains(&status) {
+        return Err(Error::HttpStatus { status, body: String::from_utf8_lossy(body).into_owned() })
+    }
+
+    if content_type.is_some_and(|t| t.contains("html")) {
+        let message: String = find_html_error(&String::from_utf8_lossy(body))
+            .unwrap_or_else(|| "server responded with an HTML page instead of data".to_string());
+
+        return Err(match message.contains("timed out") {
+            true => Error::Timeout(message),
+            false => Error::Overpass(message)
+        })
+    }
+
+    Ok(())
+}
+
+/// Check the remark of a response, turning a runtime error into [`Error::Incomplete`].
+fn check_remark(content_type: Option<&str>, body: Vec<u8>) -> Result<Checked, Error> {
+
+    let summary: Summary = summarize(content_type, &body);
+    match summary.remark {
+        Some(remark) if is_runtime_error(&remark) => {
+            Err(Error::Incomplete { remark, body: String::from_utf8_lossy(&body).into_owned() })
+        },
+        _ => Ok(Checked { body, osm_base: summary.osm3s.and_then(|osm3s| osm3s.timestamp_osm_base) })
     }
 }
@@ -0,0 +1,196 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How many times the server is asked for a free slot before a request is sent anyway.
+pub(crate) const MAX_SLOT_CHECKS: usize = 5;
+
+/// `RetryPolicy` describes how politely a [`crate::api::QueryEngine`] treats the Overpass server.
+///
+/// - Requests that fail in a way that might succeed later (see [`crate::Error::is_retryable`],
+///   such as a 429 or a 504) are sent again up to `max_retries` times. The wait between attempts
+///   doubles each time, starting at `base_delay` and capped at `max_delay`, and is randomized
+///   (jitter) so that many clients do not retry in lockstep.
+/// - If `check_slots` is set, the engine asks the server's `/api/status` endpoint whether a query
+///   slot is free before each request, and waits until one is. The server is asked again after
+///   each wait, since another client may have taken the slot, up to 5 times.
+/// - `min_interval` is the least amount of time between the start of two requests from the same
+///   engine (and all of its clones).
+///
+/// The default policy retries 3 times starting at 1 second, does not check slots and does not
+/// space out requests.
+///
+/// Example:
+/// ```rust
+/// # #[cfg(feature = "reqwest")] {
+/// use std::time::Duration;
+/// use osmgraph::api::{QueryEngine, RetryPolicy};
+///
+/// let engine = QueryEngine::new()
+///     .with_policy(RetryPolicy::new()
+///         .with_max_retries(5)
+///         .with_slot_check(true)
+///         .with_min_interval(Duration::from_secs(2)));
+/// # }
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct RetryPolicy {
+    max_retries: usize,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    check_slots: bool,
+    min_interval: Duration
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetryPolicy {
+
+    /// Create the default policy.
+    pub fn new() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            jitter: true,
+            check_slots: false,
+            min_interval: Duration::ZERO
+        }
+    }
+
+    /// Create a policy that sends every request exactly once, without any waiting.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::new()
+        }
+    }
+
+    /// Getter for the number of times a failed request is sent again.
+    pub fn max_retries(&self) -> usize {
+        self.max_retries
+    }
+    /// Getter for the wait before the first retry.
+    pub fn base_delay(&self) -> Duration {
+        self.base_delay
+    }
+    /// Getter for the longest wait between two retries.
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+    /// Getter for whether the wait between retries is randomized.
+    pub fn jitter(&self) -> bool {
+        self.jitter
+    }
+    /// Getter for whether the server is asked for a free slot before each request.
+    pub fn check_slots(&self) -> bool {
+        self.check_slots
+    }
+    /// Getter for the least amount of time between the start of two requests.
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+
+    /// Set the number of times a failed request is sent again. Meant to be used in a functional style
+    pub fn with_max_retries(&self, max_retries: usize) -> Self {
+        Self {
+            max_retries,
+            ..self.clone()
+        }
+    }
+
+    /// Set the wait before the first retry. Meant to be used in a functional style
+    pub fn with_base_delay(&self, base_delay: Duration) -> Self {
+        Self {
+            base_delay,
+            ..self.clone()
+        }
+    }
+
+    /// Set the longest wait between two retries. Meant to be used in a functional style
+    pub fn with_max_delay(&self, max_delay: Duration) -> Self {
+        Self {
+            max_delay,
+            ..self.clone()
+        }
+    }
+
+    /// Set whether the wait between retries is randomized. Meant to be used in a functional style
+    pub fn with_jitter(&self, jitter: bool) -> Self {
+        Self {
+            jitter,
+            ..self.clone()
+        }
+    }
+
+    /// Set whether the server is asked for a free slot before each request. Meant to be used in a
+    /// functional style
+    pub fn with_slot_check(&self, check_slots: bool) -> Self {
+        Self {
+            check_slots,
+            ..self.clone()
+        }
+    }
+
+    /// Set the least amount of time between the start of two requests. Meant to be used in a
+    /// functional style
+    pub fn with_min_interval(&self, min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            ..self.clone()
+        }
+    }
+
+    /// How long to wait before sending a request again, after `attempt` retries have already
+    /// been made. Without jitter this is `base_delay * 2^attempt`, capped at `max_delay`. With
+    /// jitter it is a random duration between half of that and all of it.
+    pub fn delay(&self, attempt: usize) -> Duration {
+
+        let factor: u32 = 2u32.saturating_pow(attempt.min(31) as u32);
+        let delay: Duration = self.base_delay
+            .saturating_mul(factor)
+            .min(self.max_delay);
+
+        match self.jitter {
+            true => delay.mul_f64(0.5 + 0.5 * random_fraction()),
+            false => delay
+        }
+    }
+}
+
+/// A random number in `[0, 1)`. The standard library seeds every `RandomState` randomly, which is
+/// plenty for spreading out retries without pulling in a dependency.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos());
+
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Read how long to wait for a free query slot from the text of Overpass' `/api/status`
+/// endpoint. The page either says `2 slots available now.` or lists the slots that are taken,
+/// each as `Slot available after: 2024-11-18T15:04:05Z, in 12 seconds.`
+pub(crate) fn slot_wait(status: &str) -> Option<Duration> {
+
+    if status.lines().any(|line| {
+        line.ends_with("slots available now.") && !line.starts_with("0 ")
+    }) {
+        return Some(Duration::ZERO)
+    }
+
+    status.lines()
+        .filter_map(|line| {
+            let seconds: &str = line
+                .strip_prefix("Slot available after:")?
+                .rsplit_once(", in ")?.1
+                .strip_suffix(" seconds.")?;
+            seconds.trim().parse::<u64>().ok()
+        })
+        .min()
+        .map(Duration::from_secs)
+}
@@ -0,0 +1,486 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Read;
+
+use serde_json::{Map, Value};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::Error;
+use crate::api::Element;
+use crate::api::overpass_response::is_runtime_error;
+
+/// How much is read from the underlying reader at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A json value that is being read, tracked closely enough to know where it ends.
+#[derive(Default)]
+struct Capture {
+    bytes: Vec<u8>,
+    depth: usize,
+    in_string: bool,
+    escape: bool
+}
+
+/// What happened to the value when a byte was given to a [`Capture`].
+enum Captured {
+    /// The value goes on.
+    More,
+    /// The byte ended the value and is part of it.
+    Done,
+    /// The value (a number, `true`, `false` or `null`) ended before the byte, which belongs to
+    /// whatever comes after the value.
+    DoneBefore
+}
+
+impl Capture {
+
+    fn push(&mut self, byte: u8) -> Captured {
+
+        if self.in_string {
+            self.bytes.push(byte);
+            if self.escape {
+                self.escape = false;
+            } else if byte == b'\\' {
+                self.escape = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+                if self.depth == 0 {
+                    return Captured::Done
+                }
+            }
+            return Captured::More
+        }
+
+        match byte {
+            b'"' => {
+                self.bytes.push(byte);
+                self.in_string = true;
+            },
+            b'{' | b'[' => {
+                self.bytes.push(byte);
+                self.depth += 1;
+            },
+            b'}' | b']' if self.depth == 0 => return Captured::DoneBefore,
+            b'}' | b']' => {
+                self.bytes.push(byte);
+                self.depth -= 1;
+                if self.depth == 0 {
+                    return Captured::Done
+                }
+            },
+            b',' if self.depth == 0 => return Captured::DoneBefore,
+            b' ' | b'\t' | b'\n' | b'\r' if self.depth == 0 => return Captured::DoneBefore,
+            _ => self.bytes.push(byte)
+        }
+
+        Captured::More
+    }
+}
+
+/// Where the scanner is in the document.
+enum State {
+    Start,
+    BeforeKey,
+    Key { escape: bool },
+    Colon,
+    BeforeValue,
+    Value(Capture),
+    AfterValue,
+    BeforeArray,
+    BeforeElement,
+    Element(Capture),
+    AfterElement,
+    Done
+}
+
+/// Reads the top level of an Overpass json response as it arrives, handing over each member of
+/// `elements` as soon as it is complete. Only the element being read is held in memory.
+struct Scanner {
+    state: State,
+    key: Vec<u8>,
+    fields: Map<String, Value>,
+    elements: VecDeque<Result<Element, Error>>,
+
+    //Position of the next byte, and of the start of the value being read
+    line: usize,
+    column: usize,
+    start: (usize, usize)
+}
+
+impl Scanner {
+
+    fn new() -> Self {
+        Scanner {
+            state: State::Start,
+            key: vec![],
+            fields: Map::new(),
+            elements: VecDeque::new(),
+            line: 1,
+            column: 1,
+            start: (1, 1)
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::Parse { message: message.to_string(), line: self.line, column: self.column }
+    }
+
+    /// The error for a response whose `remark` reports that the query was aborted, if it does.
+    fn incomplete(&self) -> Option<Error> {
+        let remark: &str = self.fields.get("remark")?.as_str()?;
+        is_runtime_error(remark).then(|| Error::Incomplete { remark: remark.to_string(), body: String::new() })
+    }
+
+    /// Turn an error from parsing a value into one with a position in the whole document. Errors
+    /// without a position (such as a field of the wrong type) point at the start of the value.
+    fn value_error(&self, e: serde_json::Error) -> Error {
+        match Error::from(e) {
+            Error::Parse { message, line: 0, .. } => Error::Parse {
+                message,
+                line: self.start.0,
+                column: self.start.1
+            },
+            Error::Parse { message, line, column } => Error::Parse {
+                message,
+                line: self.start.0 + line - 1,
+                column: if line == 1 { self.start.1 + column - 1 } else { column }
+            },
+            e => e
+        }
+    }
+
+    fn feed(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        for byte in bytes {
+            self.push(*byte)?;
+            match byte {
+                b'\n' => {
+                    self.line += 1;
+                    self.column = 1;
+                },
+                _ => self.column += 1
+            }
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, byte: u8) -> Result<(), Error> {
+
+        //Whitespace only matters inside keys and values
+        let reading: bool = matches!(self.state, State::Key { .. } | State::Value(_) | State::Element(_));
+        if !reading && matches!(byte, b' ' | b'\t' | b'\n' | b'\r') {
+            return Ok(())
+        }
+
+        match &mut self.state {
+            State::Start => match byte {
+                b'{' => self.state = State::BeforeKey,
+                _ => return Err(self.error("expected the response to be a json object"))
+            },
+            State::BeforeKey => match byte {
+                b'"' => {
+                    self.key.clear();
+                    self.state = State::Key { escape: false };
+                },
+                b'}' => self.state = State::Done,
+                _ => return Err(self.error("expected a key"))
+            },
+            State::Key { escape } => match (*escape, byte) {
+                (false, b'"') => self.state = State::Colon,
+                (false, b'\\') => {
+                    self.key.push(byte);
+                    *escape = true;
+                },
+                _ => {
+                    self.key.push(byte);
+                    *escape = false;
+                }
+            },
+            State::Colon => match byte {
+                b':' if self.key == b"elements" => self.state = State::BeforeArray,
+                b':' => self.state = State::BeforeValue,
+                _ => return Err(self.error("expected `:` after a key"))
+            },
+            State::BeforeValue => {
+                self.start = (self.line, self.column);
+                self.state = State::Value(Capture::default());
+                return self.push(byte)
+            },
+            State::Value(capture) => match capture.push(byte) {
+                Captured::More => {},
+                Captured::Done => self.finish_value()?,
+                Captured::DoneBefore => {
+                    self.finish_value()?;
+                    return self.push(byte)
+                }
+            },
+            State::AfterValue => match byte {
+                b',' => self.state = State::BeforeKey,
+                b'}' => self.state = State::Done,
+                _ => return Err(self.error("expected `,` or `}` after a value"))
+            },
+            State::BeforeArray => match byte {
+                b'[' => self.state = State::BeforeElement,
+                _ => return Err(self.error("expected `elements` to be an array"))
+            },
+            State::BeforeElement => match byte {
+                b'{' => {
+                    self.start = (self.line, self.column);
+                    let mut capture = Capture::default();
+                    capture.push(byte);
+                    self.state = State::Element(capture);
+                },
+                b']' => self.state = State::AfterValue,
+                _ => return Err(self.error("expected an element"))
+            },
+            State::Element(capture) => {
+                if let Captured::Done = capture.push(byte) {
+                    let State::Element(capture) = std::mem::replace(&mut self.state, State::AfterElement) else {
+                        unreachable!()
+                    };
+                    let element = serde_json::from_slice::<Element>(&capture.bytes)
+                        .map_err(|e| self.value_error(e));
+                    self.elements.push_back(element);
+                }
+            },
+            State::AfterElement => match byte {
+                b',' => self.state = State::BeforeElement,
+                b']' => self.state = State::AfterValue,
+                _ => return Err(self.error("expected `,` or `]` after an element"))
+            },
+            State::Done => return Err(self.error("unexpected data after the end of the response"))
+        }
+
+        Ok(())
+    }
+
+    /// Parse a top level value other than `elements`, and keep it.
+    fn finish_value(&mut self) -> Result<(), Error> {
+        let State::Value(capture) = std::mem::replace(&mut self.state, State::AfterValue) else {
+            unreachable!()
+        };
+        let key: String = serde_json::from_slice(&[b"\"", self.key.as_slice(), b"\""].concat())
+            .map_err(|e| self.value_error(e))?;
+        let value: Value = serde_json::from_slice(&capture.bytes)
+            .map_err(|e| self.value_error(e))?;
+        self.fields.insert(key, value);
+        Ok(())
+    }
+
+    /// Called once the reader has run out.
+    fn finish(&mut self) -> Result<(), Error> {
+        match self.state {
+            State::Done => Ok(()),
+            _ => Err(self.error("unexpected end of the response"))
+        }
+    }
+}
+
+/// `ElementReader` reads the elements of an Overpass json response (such as a file written by
+/// [`crate::api::OverpassResponse::save`]) one at a time from any [`Read`], such as a file or the
+/// body of an HTTP response. Unlike [`crate::api::OverpassResponse::load`], only the element
+/// being read is held in memory, so files that are much larger than the memory available can be
+/// read. [`crate::graph::create_graph_from_stream`] builds a graph straight from the reader.
+///
+/// The reader is an iterator of `Result<Element, Error>`. An element that does not parse is
+/// returned as an [`Error::Parse`] and reading goes on with the next one; a response that is not
+/// json at all, or ends part way, returns an error and then ends.
+///
+/// The other top level fields of the response (`version`, `generator`, `osm3s` and `remark`) are
+/// available from [`ElementReader::fields`] once they have been read. Overpass writes `remark`
+/// after the elements, so a remark that reports a runtime error (such as `Query timed out`) is
+/// returned as an [`Error::Incomplete`] after the last element. Its `body` is empty, since the
+/// elements have already been returned.
+///
+/// ```rust
+/// use osmgraph::api::{Element, ElementReader};
+///
+/// let reader = ElementReader::open("./assets/test.json").expect("Was not able to open the file!");
+///
+/// let mut ways: usize = 0;
+/// for element in reader {
+///     if let Element::Way { .. } = element.expect("Was not able to read an element!") {
+///         ways += 1;
+///     }
+/// }
+/// assert!(ways > 0);
+/// ```
+///
+/// An HTTP response can be read as it arrives with the blocking client of reqwest:
+/// ```rust,no_run
+/// # #[cfg(feature = "reqwest")] {
+/// use osmgraph::api::ElementReader;
+/// use osmgraph::graph::{OSMGraph, create_graph_from_stream};
+///
+/// let response = reqwest::blocking::Client::new()
+///     .post("https://overpass-api.de/api/interpreter")
+///     .form(&[("data", "[out:json];way[highway](40.79,-76.87,40.80,-76.85);(._;>;);out;")])
+///     .send()
+///     .expect("Could not query the server!");
+///
+/// let graph: OSMGraph = create_graph_from_stream(ElementReader::new(response))
+///     .expect("Could not create the graph!");
+/// # }
+/// ```
+pub struct ElementReader<R> {
+    reader: R,
+    scanner: Scanner,
+    buffer: Vec<u8>,
+    finished: bool
+}
+
+impl<R: Read> ElementReader<R> {
+
+    /// Create a reader of the response in `reader`. The reader is read in large chunks, so it
+    /// does not need to be buffered.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            scanner: Scanner::new(),
+            buffer: vec![0; CHUNK_SIZE],
+            finished: false
+        }
+    }
+}
+
+impl ElementReader<File> {
+
+    /// Open a file to read the elements of.
+    pub fn open(filepath: &str) -> Result<Self, Error> {
+        Ok(Self::new(File::open(filepath)?))
+    }
+}
+
+impl<R> ElementReader<R> {
+
+    /// Getter for the top level fields of the response other than `elements`, such as `version`
+    /// and `osm3s`, that have been read so far.
+    pub fn fields(&self) -> &Map<String, Value> {
+        &self.scanner.fields
+    }
+    /// Getter for the `remark` of the response, if it has been read.
+    pub fn remark(&self) -> Option<&str> {
+        self.scanner.fields.get("remark").and_then(|remark| remark.as_str())
+    }
+}
+
+impl<R: Read> Iterator for ElementReader<R> {
+    type Item = Result<Element, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(element) = self.scanner.elements.pop_front() {
+                return Some(element)
+            }
+            if self.finished {
+                return None
+            }
+
+            let result: Result<(), Error> = match self.reader.read(&mut self.buffer) {
+                Ok(0) => self.scanner.finish(),
+                Ok(read) => self.scanner.feed(&self.buffer[..read]),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e.into())
+            };
+            if let Err(e) = result {
+                self.finished = true;
+                self.scanner.elements.push_back(Err(e));
+            } else if matches!(self.scanner.state, State::Done) {
+                self.finished = true;
+                self.scanner.elements.extend(self.scanner.incomplete().map(Err));
+            }
+        }
+    }
+}
+
+/// `AsyncElementReader` does the same thing as [`ElementReader`], but reads from an
+/// [`AsyncRead`] such as a [`tokio::fs::File`]. Call [`AsyncElementReader::next_element`] until
+/// it returns `None`, and add the elements to a [`crate::graph::GraphBuilder`] to build a graph
+/// from them.
+///
+/// ```rust
+/// use osmgraph::api::AsyncElementReader;
+/// use osmgraph::graph::{GraphBuilder, OSMGraph};
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let mut reader = AsyncElementReader::open("./assets/test.json")
+///     .await
+///     .expect("Was not able to open the file!");
+///
+/// let mut builder = GraphBuilder::new();
+/// while let Some(element) = reader.next_element().await {
+///     builder.add(element.expect("Was not able to read an element!"))
+///         .expect("Was not able to add an element!");
+/// }
+///
+/// let graph: OSMGraph = builder.build().expect("Was not able to create the graph!");
+/// assert!(graph.edge_count() > 0);
+/// # });
+/// ```
+pub struct AsyncElementReader<R> {
+    reader: R,
+    scanner: Scanner,
+    buffer: Vec<u8>,
+    finished: bool
+}
+
+impl<R: AsyncRead + Unpin> AsyncElementReader<R> {
+
+    /// Create a reader of the response in `reader`. The reader is read in large chunks, so it
+    /// does not need to be buffered.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            scanner: Scanner::new(),
+            buffer: vec![0; CHUNK_SIZE],
+            finished: false
+        }
+    }
+
+    /// Read the next element, or return `None` once there are no more. Errors are returned the
+    /// same way as by [`ElementReader`].
+    pub async fn next_element(&mut self) -> Option<Result<Element, Error>> {
+        loop {
+            if let Some(element) = self.scanner.elements.pop_front() {
+                return Some(element)
+            }
+            if self.finished {
+                return None
+            }
+
+            let result: Result<(), Error> = match self.reader.read(&mut self.buffer).await {
+                Ok(0) => self.scanner.finish(),
+                Ok(read) => self.scanner.feed(&self.buffer[..read]),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e.into())
+            };
+            if let Err(e) = result {
+                self.finished = true;
+                self.scanner.elements.push_back(Err(e));
+            } else if matches!(self.scanner.state, State::Done) {
+                self.finished = true;
+                self.scanner.elements.extend(self.scanner.incomplete().map(Err));
+            }
+        }
+    }
+}
+
+impl AsyncElementReader<tokio::fs::File> {
+
+    /// Open a file to read the elements of.
+    pub async fn open(filepath: &str) -> Result<Self, Error> {
+        Ok(Self::new(tokio::fs::File::open(filepath).await?))
+    }
+}
+
+impl<R> AsyncElementReader<R> {
+
+    /// Getter for the top level fields of the response other than `elements`, such as `version`
+    /// and `osm3s`, that have been read so far.
+    pub fn fields(&self) -> &Map<String, Value> {
+        &self.scanner.fields
+    }
+    /// Getter for the `remark` of the response, if it has been read.
+    pub fn remark(&self) -> Option<&str> {
+        self.scanner.fields.get("remark").and_then(|remark| remark.as_str())
+    }
+}
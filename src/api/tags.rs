@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::{Serialize, Deserialize};
+
+/// Number of meters in a foot.
+const FOOT: f64 = 0.3048;
+/// Number of meters in an inch.
+const INCH: f64 = 0.0254;
+
+/// `Tags` are the key/value pairs that OSM attaches to nodes, ways and relations (such as
+/// `highway=residential` or `maxspeed=30 mph`). The same type is used on [`crate::api::Element`],
+/// [`crate::graph::OSMNode`], [`crate::graph::way::OSMWay`] and [`crate::graph::OSMEdge`], and it
+/// serializes to the same json object that Overpass sends.
+///
+/// On top of plain lookups, `Tags` has helpers for the value formats that OSM uses over and over:
+/// `;` separated lists, yes/no booleans, and numbers with units.
+///
+/// Example:
+/// ```rust
+/// use osmgraph::api::Tags;
+///
+/// let tags: Tags = [
+///     ("highway", "residential"),
+///     ("oneway", "yes"),
+///     ("maxspeed", "30 mph"),
+///     ("maxheight", "12'6\""),
+///     ("sidewalk", "left;right"),
+/// ].into_iter().collect();
+///
+/// assert!(tags.matches("highway", "residential"));
+/// assert_eq!(tags.get_bool("oneway"), Some(true));
+/// assert_eq!(tags.get_speed("maxspeed").map(f64::round), Some(48.0));
+/// assert!((tags.get_length("maxheight").unwrap() - 3.81).abs() < 1e-9);
+/// assert_eq!(tags.values("sidewalk"), vec!["left", "right"]);
+/// ```
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+#[serde(transparent)]
+pub struct Tags(BTreeMap<String, String>);
+
+impl fmt::Display for Tags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs: Vec<String> = self.iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        write!(f, "[{}]", pairs.join(", "))
+    }
+}
+
+impl Tags {
+
+    /// Create an empty set of tags.
+    pub fn new() -> Self {
+        Tags(BTreeMap::new())
+    }
+
+    /// Get the number of tags.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Returns true if there are no tags.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Iterate over all key/value pairs, sorted by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Add a tag, returning the old value if the key was already present.
+    pub fn insert(&mut self, key: String, value: String) -> Option<String> {
+        self.0.insert(key, value)
+    }
+
+    /// Get the raw value of a tag.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|value| value.as_str())
+    }
+    /// Returns true if the tag is present, whatever its value.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Returns true if the tag is present and its value is exactly `value`.
+    pub fn matches(&self, key: &str, value: &str) -> bool {
+        self.get(key) == Some(value)
+    }
+    /// Returns true if the tag is present and its value is one of `values`.
+    pub fn matches_any(&self, key: &str, values: &[&str]) -> bool {
+        self.get(key).is_some_and(|v| values.contains(&v))
+    }
+
+    /// Get all of the values of a tag that holds a `;` separated list, such as
+    /// `sidewalk=left;right` or `ref=I 80;US 15`. Whitespace around each value is trimmed and
+    /// empty values are dropped. Returns an empty list if the tag is not present.
+    pub fn values(&self, key: &str) -> Vec<&str> {
+        match self.get(key) {
+            Some(value) => value.split(';')
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .collect(),
+            None => vec![]
+        }
+    }
+
+    /// Get a tag as a boolean. `yes`, `true` and `1` are true, while `no`, `false` and `0` are
+    /// false. Any other value (such as `oneway=-1` or `access=private`) returns `None`.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)?.trim() {
+            "yes" | "true" | "1" => Some(true),
+            "no" | "false" | "0" => Some(false),
+            _ => None
+        }
+    }
+
+    /// Get a tag as a plain number, such as `lanes=2` or `layer=-1`. If the tag holds a `;`
+    /// separated list, the first value is used.
+    pub fn get_number(&self, key: &str) -> Option<f64> {
+        self.values(key).first()?.parse().ok()
+    }
+
+    /// Get a speed tag (such as `maxspeed`) in kilometers per hour. Values without a unit are
+    /// taken to be km/h as OSM specifies. `mph` and `knots` are converted. Values that are not
+    /// numbers (such as `maxspeed=none` or `maxspeed=walk`) return `None`.
+    pub fn get_speed(&self, key: &str) -> Option<f64> {
+        let (number, unit) = split_unit(self.values(key).first()?)?;
+
+        match unit {
+            "" | "km/h" | "kmh" | "kph" => Some(number),
+            "mph" => Some(number * 1.609344),
+            "knots" => Some(number * 1.852),
+            _ => None
+        }
+    }
+
+    /// Get a length tag (such as `width`, `maxheight` or `maxlength`) in meters. Values without a
+    /// unit are taken to be meters as OSM specifies. `cm`, `km`, `mi`, `ft` and `in` are converted,
+    /// as is the feet and inches notation (`12'6"`).
+    pub fn get_length(&self, key: &str) -> Option<f64> {
+        let value: &str = self.values(key).first()?;
+
+        if value.contains('\'') || value.contains('"') {
+            return parse_feet_inches(value)
+        }
+
+        let (number, unit) = split_unit(value)?;
+
+        match unit {
+            "" | "m" => Some(number),
+            "cm" => Some(number / 100.),
+            "km" => Some(number * 1000.),
+            "mi" => Some(number * 1609.344),
+            "nmi" => Some(number * 1852.),
+            "ft" => Some(number * FOOT),
+            "in" => Some(number * INCH),
+            _ => None
+        }
+    }
+}
+
+/// Split a value like `30 mph` or `3.5m` into its number and its unit (which may be empty).
+fn split_unit(value: &str) -> Option<(f64, &str)> {
+    let value = value.trim();
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(value.len());
+
+    let number: f64 = value[..end].parse().ok()?;
+    Some((number, value[end..].trim()))
+}
+
+/// Parse the feet and inches notation, such as `12'6"`, `12'` or `6"`, into meters.
+fn parse_feet_inches(value: &str) -> Option<f64> {
+    let value = value.trim();
+
+    let (feet, rest) = match value.split_once('\'') {
+        Some((feet, rest)) => (feet.trim().parse::<f64>().ok()?, rest.trim()),
+        None => (0., value)
+    };
+
+    let inches: f64 = match rest.strip_suffix('"') {
+        Some(inches) => inches.trim().parse().ok()?,
+        None if rest.is_empty() => 0.,
+        None => return None
+    };
+
+    Some(feet * FOOT + inches * INCH)
+}
+
+impl From<HashMap<String, String>> for Tags {
+    fn from(map: HashMap<String, String>) -> Self {
+        Tags(map.into_iter().collect())
+    }
+}
+
+impl From<BTreeMap<String, String>> for Tags {
+    fn from(map: BTreeMap<String, String>) -> Self {
+        Tags(map)
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Tags {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Tags(iter.into_iter().map(|(key, value)| (key.into(), value.into())).collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a Tags {
+    type Item = (&'a String, &'a String);
+    type IntoIter = std::collections::btree_map::Iter<'a, String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::Error;
+use crate::api::overpass_response::Bounds;
+
+/// The most tiles a tiled fetch may be split into.
+pub(crate) const MAX_TILES: usize = 10_000;
+
+/// What happened to a tile during a tiled fetch.
+#[derive(Clone, PartialEq, Debug)]
+pub enum TileStatus {
+    /// The tile was fetched and held this many elements.
+    Done { elements: usize },
+    /// The tile failed with this error and will be fetched again.
+    Retrying(String),
+    /// The tile failed with this error and will not be fetched again. The whole fetch fails.
+    Failed(String)
+}
+
+/// `TileProgress` is passed to the progress callback of [`TileOptions`] every time a tile is
+/// done, is about to be retried or has failed.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TileProgress {
+    index: usize,
+    total: usize,
+    completed: usize,
+    bounds: Bounds,
+    attempt: usize,
+    status: TileStatus
+}
+
+impl TileProgress {
+
+    /// Get the index of the tile, from 0 up to [`Self::total`].
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    /// Get the number of tiles in the fetch.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+    /// Get the number of tiles that are done so far, including this one if it is done.
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+    /// Get the bounding box of the tile.
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+    /// Get the attempt at fetching the tile, starting at 0.
+    pub fn attempt(&self) -> usize {
+        self.attempt
+    }
+    /// Get what happened to the tile.
+    pub fn status(&self) -> &TileStatus {
+        &self.status
+    }
+}
+
+type ProgressCallback = Arc<dyn Fn(&TileProgress) + Send + Sync>;
+
+/// `TileOptions` controls how [`crate::api::QueryEngine::fetch_bbox_tiled`] and
+/// [`crate::api::QueryEngine::fetch_poly_tiled`] split an area into tiles and fetch them.
+///
+/// By default tiles are 0.05 degrees (about 5km) on each side, two tiles are fetched at a time
+/// (most public Overpass instances allow two queries at once), and a tile that fails is fetched
+/// again up to two more times. An area that would need more than 10 000 tiles is refused.
+///
+/// ```rust
+/// use osmgraph::api::{TileOptions, TileStatus};
+///
+/// let options = TileOptions::new()
+///     .with_tile_size(0.1)
+///     .with_concurrency(4)
+///     .with_progress(|progress| {
+///         if let TileStatus::Done { .. } = progress.status() {
+///             println!("{}/{} tiles done", progress.completed(), progress.total());
+///         }
+///     });
+/// ```
+#[derive(Clone)]
+pub struct TileOptions {
+    tile_size: f64,
+    concurrency: usize,
+    max_retries: usize,
+    progress: Option<ProgressCallback>
+}
+
+impl TileOptions {
+
+    /// Create the default tile options.
+    pub fn new() -> Self {
+        Self {
+            tile_size: 0.05,
+            concurrency: 2,
+            max_retries: 2,
+            progress: None
+        }
+    }
+
+    /// Getter for the size of each side of a tile, in degrees.
+    pub fn tile_size(&self) -> f64 {
+        self.tile_size
+    }
+    /// Getter for the number of tiles fetched at the same time.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+    /// Getter for the number of times a failed tile is fetched again.
+    pub fn max_retries(&self) -> usize {
+        self.max_retries
+    }
+
+    /// Set the size of each side of a tile, in degrees. A size that is not positive makes the fetch
+    /// return [`Error::InvalidGeometry`]. Meant to be used in a functional style
+    pub fn with_tile_size(&self, tile_size: f64) -> Self {
+        Self {
+            tile_size,
+            ..self.clone()
+        }
+    }
+    /// Set the number of tiles fetched at the same time. This is at least 1. Meant to be used in a
+    /// functional style
+    pub fn with_concurrency(&self, concurrency: usize) -> Self {
+        Self {
+            concurrency: concurrency.max(1),
+            ..self.clone()
+        }
+    }
+    /// Set the number of times a failed tile is fetched again. This takes the place of the
+    /// `max_retries` of the engine's [`crate::api::RetryPolicy`], whose delays are still used
+    /// between attempts, and also covers tiles that Overpass could not finish
+    /// ([`crate::Error::Incomplete`]). Meant to be used in a functional style
+    pub fn with_max_retries(&self, max_retries: usize) -> Self {
+        Self {
+            max_retries,
+            ..self.clone()
+        }
+    }
+    /// Set a callback that is told about the progress of every tile. Meant to be used in a
+    /// functional style
+    pub fn with_progress(&self, progress: impl Fn(&TileProgress) + Send + Sync + 'static) -> Self {
+        Self {
+            progress: Some(Arc::new(progress)),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn report(&self, index: usize, total: usize, completed: usize, bounds: &Bounds, attempt: usize, status: TileStatus) {
+        if let Some(progress) = &self.progress {
+            progress(&TileProgress { index, total, completed, bounds: *bounds, attempt, status });
+        }
+    }
+}
+
+impl Default for TileOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for TileOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TileOptions")
+            .field("tile_size", &self.tile_size)
+            .field("concurrency", &self.concurrency)
+            .field("max_retries", &self.max_retries)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+/// Split a bounding box into a grid of tiles whose sides are at most `tile_size` degrees. The
+/// tiles are ordered row by row from the south west corner. A tile size that is not a positive
+/// number, or a grid of more than [`MAX_TILES`] tiles, returns [`Error::InvalidGeometry`].
+pub(crate) fn grid(south: f64, west: f64, north: f64, east: f64, tile_size: f64) -> Result<Vec<Bounds>, Error> {
+
+    if !(tile_size > 0.0 && tile_size.is_finite()) {
+        return Err(Error::InvalidGeometry(format!("tile size {tile_size} is not a positive number of degrees")))
+    }
+    let steps = |span: f64| -> f64 { (span / tile_size).ceil().max(1.0) };
+
+    //Counted as floats first, since a tiny tile size would overflow the count
+    let count: f64 = steps(north - south) * steps(east - west);
+    if count > MAX_TILES as f64 {
+        return Err(Error::InvalidGeometry(format!(
+            "tiles of {tile_size} degrees would split the area into {count} tiles, more than {MAX_TILES}"
+        )))
+    }
+    let (rows, cols) = (steps(north - south) as usize, steps(east - west) as usize);
+
+    //The last row and column end exactly on the edge of the box, whatever the rounding
+    let edge = |start: f64, end: f64, count: usize, i: usize| -> f64 {
+        match i == count {
+            true => end,
+            false => start + (end - start) * i as f64 / count as f64
+        }
+    };
+
+    let mut tiles: Vec<Bounds> = Vec::with_capacity(rows * cols);
+    for row in 0..rows {
+        for col in 0..cols {
+            tiles.push(Bounds::new(
+                edge(south, north, rows, row),
+                edge(west, east, cols, col),
+                edge(south, north, rows, row + 1),
+                edge(west, east, cols, col + 1)
+            ));
+        }
+    }
+
+    Ok(tiles)
+}
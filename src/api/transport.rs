@@ -0,0 +1,263 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::Error;
+
+/// The HTTP method of an [`HttpRequest`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Method {
+    Get,
+    Post
+}
+
+/// `HttpRequest` is a request that a [`crate::api::QueryEngine`] asks its [`Transport`] to send.
+/// Queries are sent as a POST with the query in the form encoded `data` field, which is what
+/// Overpass expects. [`HttpRequest::to_get`] turns such a request into the equivalent GET.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HttpRequest {
+    method: Method,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>
+}
+
+impl HttpRequest {
+
+    /// Create a GET request without a body.
+    pub fn get(url: &str) -> Self {
+        Self {
+            method: Method::Get,
+            url: url.to_string(),
+            headers: vec![],
+            body: vec![]
+        }
+    }
+
+    /// Create a POST request with a form encoded body.
+    pub fn post_form(url: &str, fields: &[(&str, &str)]) -> Self {
+        Self {
+            method: Method::Post,
+            url: url.to_string(),
+            headers: vec![("Content-Type".to_string(), "application/x-www-form-urlencoded".to_string())],
+            body: encode_form(fields).into_bytes()
+        }
+    }
+
+    /// Getter for the method of the request.
+    pub fn method(&self) -> Method {
+        self.method
+    }
+    /// Getter for the url of the request.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    /// Getter for the headers of the request.
+    pub fn headers(&self) -> &Vec<(String, String)> {
+        &self.headers
+    }
+    /// Getter for the body of the request.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Add a header to the request. Meant to be used in a functional style
+    pub fn with_header(&self, name: &str, value: &str) -> Self {
+        let mut headers: Vec<(String, String)> = self.headers.clone();
+        headers.push((name.to_string(), value.to_string()));
+        Self {
+            headers,
+            ..self.clone()
+        }
+    }
+
+    /// Turn a POST with a form encoded body into a GET with the form in the query string, which
+    /// Overpass answers the same way. Other requests are returned as they are.
+    ///
+    /// ```rust
+    /// use osmgraph::api::{HttpRequest, Method};
+    ///
+    /// let request = HttpRequest::post_form("https://overpass-api.de/api/interpreter", &[("data", "node(1); out;")])
+    ///     .to_get();
+    ///
+    /// assert_eq!(request.method(), Method::Get);
+    /// assert_eq!(request.url(), "https://overpass-api.de/api/interpreter?data=node%281%29%3B+out%3B");
+    /// ```
+    pub fn to_get(&self) -> Self {
+        if self.method != Method::Post || self.body.is_empty() {
+            return self.clone()
+        }
+        Self {
+            method: Method::Get,
+            url: format!("{}?{}", self.url, String::from_utf8_lossy(&self.body)),
+            headers: self.headers.iter()
+                .filter(|(name, _)| !name.eq_ignore_ascii_case("content-type"))
+                .cloned()
+                .collect(),
+            body: vec![]
+        }
+    }
+}
+
+/// `HttpResponse` is what a [`Transport`] hands back for an [`HttpRequest`]. Any status is a
+/// valid response: the engine decides what counts as a failure.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HttpResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>
+}
+
+impl HttpResponse {
+
+    /// Create a response.
+    pub fn new(status: u16, headers: Vec<(String, String)>, body: Vec<u8>) -> Self {
+        Self { status, headers, body }
+    }
+
+    /// Getter for the status code of the response.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+    /// Getter for the headers of the response.
+    pub fn headers(&self) -> &Vec<(String, String)> {
+        &self.headers
+    }
+    /// Getter for the body of the response.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+    /// Get the value of a header, ignoring the case of its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub(crate) fn into_body(self) -> Vec<u8> {
+        self.body
+    }
+}
+
+/// The future returned by [`Transport::send`].
+pub type TransportFuture<'a> = Pin<Box<dyn Future<Output = Result<HttpResponse, Error>> + Send + 'a>>;
+
+/// `Transport` sends the HTTP requests of a [`crate::api::QueryEngine`]. The engine uses
+/// [`ReqwestTransport`] by default; give it another one with
+/// [`crate::api::QueryEngine::from_transport`] to go through a proxy, add headers such as a
+/// User-Agent or credentials, use GET instead of POST, or answer from memory in tests.
+///
+/// A transport only moves bytes. Retries, failover between endpoints and the checks on the
+/// response are done by the engine, so a transport should return any response it gets (even a 429
+/// or a 504) and only fail when there is no response at all, usually with [`Error::Transport`] or
+/// [`Error::Timeout`].
+///
+/// With `default-features = false`, reqwest is not built at all and
+/// [`crate::api::QueryEngine::from_transport`] is the way to create an engine.
+///
+/// Example of a transport that answers every query from memory, which needs no HTTP client at
+/// all:
+/// ```rust
+/// use osmgraph::api::{HttpRequest, HttpResponse, OverpassResponse, QueryEngine, Transport, TransportFuture};
+///
+/// #[derive(Debug)]
+/// struct MemoryTransport(&'static str);
+///
+/// impl Transport for MemoryTransport {
+///     fn send(&self, _request: HttpRequest) -> TransportFuture<'_> {
+///         let headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+///         Box::pin(async move { Ok(HttpResponse::new(200, headers, self.0.as_bytes().to_vec())) })
+///     }
+/// }
+///
+/// let engine = QueryEngine::from_transport(MemoryTransport(
+///     r#"{"version": 0.6, "generator": "memory", "osm3s": {},
+///         "elements": [{"type": "node", "id": 1, "lat": 40.0, "lon": -76.0}]}"#
+/// ));
+///
+/// let response: OverpassResponse = engine.fetch_blocking("[out:json];node(1);out;".to_string())
+///     .expect("The transport should answer!");
+/// assert_eq!(response.elements().len(), 1);
+/// ```
+///
+/// A transport can also wrap another one, for example to send every query as a GET with
+/// [`HttpRequest::to_get`].
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// Send a request and return the response, whatever its status.
+    fn send(&self, request: HttpRequest) -> TransportFuture<'_>;
+}
+
+/// Form encode some fields (`application/x-www-form-urlencoded`).
+fn encode_form(fields: &[(&str, &str)]) -> String {
+    let encode = |value: &str| -> String {
+        value.bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => (byte as char).to_string(),
+                b' ' => "+".to_string(),
+                byte => format!("%{byte:02X}")
+            })
+            .collect()
+    };
+
+    fields.iter()
+        .map(|(key, value)| format!("{}={}", encode(key), encode(value)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// `ReqwestTransport` is the default [`Transport`], built on a
+/// [reqwest](https://docs.rs/reqwest/latest/reqwest/) client. It is only available with the
+/// `reqwest` feature, which is on by default.
+///
+/// A client of your own (with a proxy, a User-Agent or a timeout) can be given with
+/// [`ReqwestTransport::from_client`].
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+
+    /// Create a transport with a default client.
+    pub fn new() -> Self {
+        Self::from_client(reqwest::Client::new())
+    }
+
+    /// Create a transport that sends requests with the given client.
+    pub fn from_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    /// Getter for the client that sends the requests.
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+
+            let method: reqwest::Method = match request.method {
+                Method::Get => reqwest::Method::GET,
+                Method::Post => reqwest::Method::POST
+            };
+            let mut builder = self.client.request(method, &request.url);
+            for (name, value) in &request.headers {
+                builder = builder.header(name, value);
+            }
+            let response = builder.body(request.body).send().await?;
+
+            let status: u16 = response.status().as_u16();
+            let headers: Vec<(String, String)> = response.headers().iter()
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                .collect();
+            let body: Vec<u8> = response.bytes().await?.to_vec();
+
+            Ok(HttpResponse::new(status, headers, body))
+        })
+    }
+}
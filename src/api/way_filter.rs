@@ -0,0 +1,188 @@
+use regex::Regex;
+
+use crate::Error;
+use crate::api::{Selector, TagFilter, Tags};
+
+/// `WayFilter` decides which ways make it into a query and into a graph. The same filter is turned
+/// into Overpass QL by [`WayFilter::selectors`] and checked against the tags of ways that are
+/// already downloaded by [`WayFilter::matcher`], so cached data can be filtered again without
+/// sending a new query. See [`crate::api::QueryEngine::with_way_filter`] and
+/// [`crate::graph::create_graph_with_filter`].
+///
+/// Tag filters behave as they do in Overpass: a way without the key passes `!=` and `!~`, and
+/// regular expressions match anywhere in the value unless they are anchored with `^` and `$`.
+///
+/// Example:
+/// ```rust
+/// use osmgraph::api::{TagFilter, WayFilter};
+///
+/// //Public roads, along with ferry routes
+/// let filter = WayFilter::all(vec![
+///     WayFilter::any(vec![
+///         WayFilter::highway(&["primary", "residential"]),
+///         WayFilter::tag(TagFilter::equals("route", "ferry")),
+///     ]),
+///     WayFilter::tag(TagFilter::not_equals("access", "private")),
+///     WayFilter::tag(TagFilter::not_regex("service", "parking_aisle")),
+/// ]);
+///
+/// let selectors: Vec<String> = filter.selectors().iter().map(|s| s.to_string()).collect();
+/// assert_eq!(selectors, vec![
+///     r#"way["highway"~"^(primary|residential)$"]["access"!="private"]["service"!~"parking_aisle"]"#,
+///     r#"way["route"="ferry"]["access"!="private"]["service"!~"parking_aisle"]"#,
+/// ]);
+///
+/// let matcher = filter.matcher().expect("Regular expressions should be valid!");
+/// assert!(matcher.matches(Some(&[("highway", "primary")].into_iter().collect())));
+/// assert!(!matcher.matches(Some(&[("highway", "primary"), ("access", "private")].into_iter().collect())));
+/// assert!(!matcher.matches(Some(&[("highway", "primary_link")].into_iter().collect())));
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum WayFilter {
+    /// The way passes a single tag filter.
+    Tag(TagFilter),
+    /// The way passes every one of the filters. `All` of nothing lets every way through.
+    All(Vec<WayFilter>),
+    /// The way passes at least one of the filters. `Any` of nothing lets no way through.
+    Any(Vec<WayFilter>)
+}
+
+impl WayFilter {
+
+    /// Create a [`WayFilter::Tag`] filter.
+    pub fn tag(filter: TagFilter) -> Self {
+        WayFilter::Tag(filter)
+    }
+    /// Create a [`WayFilter::All`] filter.
+    pub fn all(filters: Vec<WayFilter>) -> Self {
+        WayFilter::All(filters)
+    }
+    /// Create a [`WayFilter::Any`] filter.
+    pub fn any(filters: Vec<WayFilter>) -> Self {
+        WayFilter::Any(filters)
+    }
+    /// Create a filter for ways whose `highway` tag is exactly one of the values. Without any
+    /// values, every way with a `highway` tag passes.
+    pub fn highway(values: &[&str]) -> Self {
+        match values.len() {
+            0 => WayFilter::Tag(TagFilter::exists("highway")),
+            _ => WayFilter::Tag(TagFilter::regex("highway", &format!("^({})$", values.join("|"))))
+        }
+    }
+
+    /// Get the filter as alternatives, each of which is a list of tag filters that must all pass.
+    fn alternatives(&self) -> Vec<Vec<TagFilter>> {
+        match self {
+            WayFilter::Tag(filter) => vec![vec![filter.clone()]],
+            WayFilter::Any(filters) => filters.iter()
+                .flat_map(|filter| filter.alternatives())
+                .collect(),
+            WayFilter::All(filters) => filters.iter()
+                .fold(vec![vec![]], |alternatives, filter| {
+                    let next: Vec<Vec<TagFilter>> = filter.alternatives();
+                    alternatives.iter()
+                        .flat_map(|first| next.iter().map(move |second| [first.clone(), second.clone()].concat()))
+                        .collect()
+                })
+        }
+    }
+
+    /// Turn the filter into way selectors, one for each alternative of the filter, so that the
+    /// union of the selectors holds the ways that pass. Overpass can only AND the tag filters of a
+    /// selector, so an `Any` inside an `All` gives one selector per combination.
+    pub fn selectors(&self) -> Vec<Selector> {
+        self.alternatives()
+            .into_iter()
+            .map(|filters| Selector::way().with_tags(filters))
+            .collect()
+    }
+
+    /// Compile the regular expressions of the filter so that it can be checked against the tags
+    /// of many ways. A regular expression that does not compile returns [`Error::InvalidFilter`].
+    pub fn matcher(&self) -> Result<WayMatcher, Error> {
+        Ok(WayMatcher(Matcher::new(self)?))
+    }
+}
+
+/// A [`WayFilter`] that is ready to be checked against tags, made by [`WayFilter::matcher`].
+#[derive(Clone, Debug)]
+pub struct WayMatcher(Matcher);
+
+impl WayMatcher {
+
+    /// Check if a way with these tags passes the filter.
+    pub fn matches(&self, tags: Option<&Tags>) -> bool {
+        self.0.matches(tags)
+    }
+}
+
+/// The compiled form of a [`WayFilter`].
+#[derive(Clone, Debug)]
+enum Matcher {
+    Tag(TagMatcher),
+    All(Vec<Matcher>),
+    Any(Vec<Matcher>)
+}
+
+impl Matcher {
+
+    fn new(filter: &WayFilter) -> Result<Self, Error> {
+        Ok(match filter {
+            WayFilter::Tag(filter) => Matcher::Tag(TagMatcher::new(filter)?),
+            WayFilter::All(filters) => Matcher::All(filters.iter().map(Matcher::new).collect::<Result<_, _>>()?),
+            WayFilter::Any(filters) => Matcher::Any(filters.iter().map(Matcher::new).collect::<Result<_, _>>()?)
+        })
+    }
+
+    fn matches(&self, tags: Option<&Tags>) -> bool {
+        match self {
+            Matcher::Tag(matcher) => matcher.matches(tags),
+            Matcher::All(matchers) => matchers.iter().all(|matcher| matcher.matches(tags)),
+            Matcher::Any(matchers) => matchers.iter().any(|matcher| matcher.matches(tags))
+        }
+    }
+}
+
+/// A [`TagFilter`] with its regex compiled.
+#[derive(Clone, Debug)]
+pub(crate) enum TagMatcher {
+    Equals(String, String),
+    NotEquals(String, String),
+    Regex(String, Regex),
+    NotRegex(String, Regex),
+    Exists(String),
+    NotExists(String)
+}
+
+impl TagMatcher {
+
+    /// Compile a tag filter. A regular expression that does not compile returns
+    /// [`Error::InvalidFilter`].
+    pub(crate) fn new(filter: &TagFilter) -> Result<Self, Error> {
+        let regex = |regex: &str| Regex::new(regex)
+            .map_err(|e| Error::InvalidFilter(format!("invalid regular expression `{regex}`: {e}")));
+
+        Ok(match filter {
+            TagFilter::Equals(key, value) => TagMatcher::Equals(key.clone(), value.clone()),
+            TagFilter::NotEquals(key, value) => TagMatcher::NotEquals(key.clone(), value.clone()),
+            TagFilter::Regex(key, value) => TagMatcher::Regex(key.clone(), regex(value)?),
+            TagFilter::NotRegex(key, value) => TagMatcher::NotRegex(key.clone(), regex(value)?),
+            TagFilter::Exists(key) => TagMatcher::Exists(key.clone()),
+            TagFilter::NotExists(key) => TagMatcher::NotExists(key.clone())
+        })
+    }
+
+    /// Check if an element with these tags passes the filter. Elements without the key pass the
+    /// negated filters, as they do in Overpass.
+    pub(crate) fn matches(&self, tags: Option<&Tags>) -> bool {
+        let get = |key: &str| tags.and_then(|tags| tags.get(key));
+        match self {
+            TagMatcher::Equals(key, value) => get(key) == Some(value),
+            TagMatcher::NotEquals(key, value) => get(key) != Some(value),
+            TagMatcher::Regex(key, regex) => get(key).is_some_and(|value| regex.is_match(value)),
+            TagMatcher::NotRegex(key, regex) => !get(key).is_some_and(|value| regex.is_match(value)),
+            TagMatcher::Exists(key) => get(key).is_some(),
+            TagMatcher::NotExists(key) => get(key).is_none()
+        }
+    }
+}
@@ -0,0 +1,475 @@
+//! Running parsed queries against the emulator's data.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde_json::{json, Map, Value};
+
+use crate::Error;
+use crate::api::{
+    Bounds, Coordinate, Element, ElementKind, MemberType, OutMode, Polygon, QueryBuilder, Selector,
+    SpatialFilter, Statement, Tags
+};
+use crate::api::way_filter::TagMatcher;
+
+/// Overpass gives areas made from ways and relations these offsets on top of their IDs.
+const WAY_AREA_OFFSET: u64 = 2_400_000_000;
+const RELATION_AREA_OFFSET: u64 = 3_600_000_000;
+/// Areas added by hand get IDs above any that Overpass would make.
+const CUSTOM_AREA_OFFSET: u64 = 10_000_000_000_000;
+
+/// The mean radius of the earth in meters, for `around`.
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// The fields that `out meta` adds on top of `out body`.
+const META_FIELDS: [&str; 5] = ["version", "timestamp", "changeset", "user", "uid"];
+
+/// An area that can be searched with `(area.name)`.
+#[derive(Clone, Debug)]
+struct Area {
+    id: u64,
+    tags: Tags,
+    polygons: Vec<Polygon>
+}
+
+/// All of the elements that queries are answered from.
+#[derive(Clone, Default, Debug)]
+pub(crate) struct Dataset {
+    nodes: BTreeMap<u64, Element>,
+    ways: BTreeMap<u64, Element>,
+    relations: BTreeMap<u64, Element>,
+    areas: Vec<Area>,
+    custom_areas: u64
+}
+
+/// The contents of a set such as `_` or `.searchArea`. Areas are indexes into [`Dataset::areas`].
+#[derive(Clone, Default, Debug)]
+struct Set {
+    nodes: BTreeSet<u64>,
+    ways: BTreeSet<u64>,
+    relations: BTreeSet<u64>,
+    areas: BTreeSet<usize>
+}
+
+impl Set {
+    fn extend(&mut self, other: Set) {
+        self.nodes.extend(other.nodes);
+        self.ways.extend(other.ways);
+        self.relations.extend(other.relations);
+        self.areas.extend(other.areas);
+    }
+}
+
+fn tags(element: &Element) -> Option<&Tags> {
+    match element {
+        Element::Node { tags, .. } | Element::Way { tags, .. } | Element::Relation { tags, .. } => tags.as_ref()
+    }
+}
+
+/// A [`SpatialFilter`] ready to test points against.
+enum Region<'a> {
+    BBox(Bounds),
+    Around { radius: f64, lat: f64, lon: f64 },
+    Poly(Polygon),
+    Areas(Vec<&'a Polygon>)
+}
+
+impl Region<'_> {
+    fn contains(&self, (lat, lon): (f64, f64)) -> bool {
+        match self {
+            Region::BBox(b) => lat >= b.minlat() && lat <= b.maxlat() && lon >= b.minlon() && lon <= b.maxlon(),
+            Region::Around { radius, lat: center_lat, lon: center_lon } => {
+                distance((lat, lon), (*center_lat, *center_lon)) <= *radius
+            },
+            Region::Poly(polygon) => polygon.contains(lat, lon),
+            Region::Areas(polygons) => polygons.iter().any(|polygon| polygon.contains(lat, lon))
+        }
+    }
+}
+
+/// The great circle distance between two points in meters (haversine formula).
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat_a, lat_b) = (a.0.to_radians(), b.0.to_radians());
+    let (d_lat, d_lon) = ((b.0 - a.0).to_radians(), (b.1 - a.1).to_radians());
+    let h: f64 = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+/// The smallest bounding box around some points.
+fn bounds(points: &[(f64, f64)]) -> Option<Bounds> {
+    let (first, rest) = points.split_first()?;
+    let (mut minlat, mut minlon, mut maxlat, mut maxlon) = (first.0, first.1, first.0, first.1);
+    for (lat, lon) in rest {
+        minlat = minlat.min(*lat);
+        minlon = minlon.min(*lon);
+        maxlat = maxlat.max(*lat);
+        maxlon = maxlon.max(*lon);
+    }
+    Some(Bounds::new(minlat, minlon, maxlat, maxlon))
+}
+
+/// Join the node lists of ways end to end into closed rings. Ways that cannot be closed are
+/// dropped.
+fn assemble_rings(mut open: Vec<Vec<u64>>) -> Vec<Vec<u64>> {
+
+    let mut rings: Vec<Vec<u64>> = vec![];
+    open.retain(|nodes| nodes.len() >= 2);
+
+    while let Some(mut ring) = open.pop() {
+        loop {
+            if ring.len() > 3 && ring.first() == ring.last() {
+                rings.push(ring);
+                break
+            }
+            let Some(&end) = ring.last() else { break };
+            let Some(next) = open.iter().position(|w| w.first() == Some(&end) || w.last() == Some(&end)) else {
+                break
+            };
+            let mut next: Vec<u64> = open.swap_remove(next);
+            if next.first() != Some(&end) {
+                next.reverse();
+            }
+            ring.extend(next.into_iter().skip(1));
+        }
+    }
+
+    rings
+}
+
+impl Dataset {
+
+    /// Index the elements and derive areas from them. Areas are made, as Overpass does, from
+    /// closed ways that are not roads (unless tagged `area=yes`) and from `multipolygon` and
+    /// `boundary` relations whose outer ways form closed rings.
+    pub(crate) fn new(elements: Vec<Element>) -> Self {
+
+        let mut dataset = Dataset::default();
+        for element in elements {
+            let map = match element {
+                Element::Node { .. } => &mut dataset.nodes,
+                Element::Way { .. } => &mut dataset.ways,
+                Element::Relation { .. } => &mut dataset.relations
+            };
+            map.entry(element.id()).or_insert(element);
+        }
+
+        let mut areas: Vec<Area> = vec![];
+        for way in dataset.ways.values() {
+            let Element::Way { id, nodes, tags: Some(tags), .. } = way else { continue };
+            let closed: bool = nodes.len() > 3 && nodes.first() == nodes.last();
+            let area: bool = match tags.get("area") {
+                Some("no") => false,
+                Some("yes") => true,
+                _ => !tags.contains_key("highway")
+            };
+            if closed && area {
+                let polygons: Vec<Polygon> = dataset.ring_polygon(nodes).into_iter().collect();
+                if !polygons.is_empty() {
+                    areas.push(Area { id: id + WAY_AREA_OFFSET, tags: tags.clone(), polygons });
+                }
+            }
+        }
+        for relation in dataset.relations.values() {
+            let Element::Relation { id, members, tags: Some(tags), .. } = relation else { continue };
+            if !tags.matches_any("type", &["multipolygon", "boundary"]) {
+                continue
+            }
+            let outer: Vec<Vec<u64>> = members.iter()
+                .filter(|member| member.member_type() == MemberType::Way)
+                .filter(|member| matches!(member.role(), "outer" | ""))
+                .filter_map(|member| match dataset.ways.get(&member.reference()) {
+                    Some(Element::Way { nodes, .. }) => Some(nodes.clone()),
+                    _ => None
+                })
+                .collect();
+            let polygons: Vec<Polygon> = assemble_rings(outer).iter()
+                .filter_map(|ring| dataset.ring_polygon(ring))
+                .collect();
+            if !polygons.is_empty() {
+                areas.push(Area { id: id + RELATION_AREA_OFFSET, tags: tags.clone(), polygons });
+            }
+        }
+
+        dataset.areas = areas;
+        dataset
+    }
+
+    /// Add an area by hand.
+    pub(crate) fn add_area(&mut self, tags: Tags, polygon: Polygon) {
+        self.areas.push(Area { id: CUSTOM_AREA_OFFSET + self.custom_areas, tags, polygons: vec![polygon] });
+        self.custom_areas += 1;
+    }
+
+    fn ring_polygon(&self, ring: &[u64]) -> Option<Polygon> {
+        let points: Option<Vec<(f64, f64)>> = ring.iter().map(|node| self.coordinate(*node)).collect();
+        Polygon::new(points?).ok()
+    }
+
+    fn coordinate(&self, node: u64) -> Option<(f64, f64)> {
+        match self.nodes.get(&node) {
+            Some(Element::Node { lat, lon, .. }) => Some((*lat, *lon)),
+            _ => None
+        }
+    }
+
+    fn way_nodes(&self, way: u64) -> &[u64] {
+        match self.ways.get(&way) {
+            Some(Element::Way { nodes, .. }) => nodes,
+            _ => &[]
+        }
+    }
+
+    fn way_points(&self, way: u64) -> Vec<(f64, f64)> {
+        self.way_nodes(way).iter().filter_map(|node| self.coordinate(*node)).collect()
+    }
+
+    /// The points of an element. For relations these are the points of their node and way
+    /// members; member relations are not followed.
+    fn points(&self, element: &Element) -> Vec<(f64, f64)> {
+        match element {
+            Element::Node { lat, lon, .. } => vec![(*lat, *lon)],
+            Element::Way { id, .. } => self.way_points(*id),
+            Element::Relation { members, .. } => members.iter()
+                .flat_map(|member| match member.member_type() {
+                    MemberType::Node => self.coordinate(member.reference()).into_iter().collect(),
+                    MemberType::Way => self.way_points(member.reference()),
+                    MemberType::Relation => vec![]
+                })
+                .collect()
+        }
+    }
+
+    /// Run a query and return the elements that its `out` statements print, in order.
+    pub(crate) fn run(&self, query: &QueryBuilder) -> Result<Vec<Value>, Error> {
+        let mut sets: HashMap<String, Set> = HashMap::new();
+        let mut output: Vec<Value> = vec![];
+        for statement in query.statements() {
+            self.execute(statement, &mut sets, &mut output)?;
+        }
+        Ok(output)
+    }
+
+    /// Run a statement, returning the set it produced (if any). As in Overpass, every statement
+    /// writes its result to `_` unless it names another set.
+    fn execute(&self, statement: &Statement, sets: &mut HashMap<String, Set>, output: &mut Vec<Value>) -> Result<Option<Set>, Error> {
+
+        let current = |sets: &HashMap<String, Set>| sets.get("_").cloned().unwrap_or_default();
+
+        let (result, target): (Set, &str) = match statement {
+            Statement::Select(selector) => {
+                let result: Set = self.select(selector, sets)?;
+                (result, selector.output_set().unwrap_or("_"))
+            },
+            Statement::Union(statements) => {
+                let mut result = Set::default();
+                for statement in statements {
+                    if let Some(set) = self.execute(statement, sets, output)? {
+                        result.extend(set);
+                    }
+                }
+                (result, "_")
+            },
+            Statement::Current => (current(sets), "_"),
+            Statement::Set(name) => (sets.get(name).cloned().unwrap_or_default(), "_"),
+            Statement::RecurseDown => (self.recurse_down(&current(sets)), "_"),
+            Statement::RecurseUp => (self.recurse_up(&current(sets)), "_"),
+            Statement::Out(modes) => {
+                self.print(&current(sets), modes, output)?;
+                return Ok(None)
+            }
+        };
+
+        sets.insert(target.to_string(), result.clone());
+        Ok(Some(result))
+    }
+
+    fn select(&self, selector: &Selector, sets: &HashMap<String, Set>) -> Result<Set, Error> {
+
+        let matchers: Vec<TagMatcher> = selector.tags().iter()
+            .map(|filter| TagMatcher::new(filter).map_err(|e| match e {
+                Error::InvalidFilter(message) => Error::Overpass(message),
+                e => e
+            }))
+            .collect::<Result<_, _>>()?;
+        let matches = |tags: Option<&Tags>| matchers.iter().all(|matcher| matcher.matches(tags));
+
+        let mut result = Set::default();
+
+        if selector.kind() == ElementKind::Area {
+            if !selector.spatial().is_empty() {
+                return Err(Error::Overpass("the emulator does not support spatial filters on areas".to_string()))
+            }
+            result.areas = (0..self.areas.len())
+                .filter(|i| matches(Some(&self.areas[*i].tags)))
+                .collect();
+            return Ok(result)
+        }
+
+        let regions: Vec<Region> = selector.spatial().iter()
+            .map(|filter| self.region(filter, sets))
+            .collect::<Result<_, _>>()?;
+        //Ways and relations count as inside a region if any of their nodes is inside it
+        let inside = |points: &[(f64, f64)]| {
+            regions.iter().all(|region| points.iter().any(|point| region.contains(*point)))
+        };
+
+        let kind: ElementKind = selector.kind();
+        let candidates = |map: &BTreeMap<u64, Element>| -> BTreeSet<u64> {
+            map.values()
+                .filter(|element| matches(tags(element)) && inside(&self.points(element)))
+                .map(|element| element.id())
+                .collect()
+        };
+        if matches!(kind, ElementKind::Node | ElementKind::Nwr) {
+            result.nodes = candidates(&self.nodes);
+        }
+        if matches!(kind, ElementKind::Way | ElementKind::Nwr) {
+            result.ways = candidates(&self.ways);
+        }
+        if matches!(kind, ElementKind::Relation | ElementKind::Nwr) {
+            result.relations = candidates(&self.relations);
+        }
+
+        Ok(result)
+    }
+
+    fn region<'a>(&'a self, filter: &SpatialFilter, sets: &HashMap<String, Set>) -> Result<Region<'a>, Error> {
+        Ok(match filter {
+            SpatialFilter::BBox { south, west, north, east } => Region::BBox(Bounds::new(*south, *west, *north, *east)),
+            SpatialFilter::Around { radius, lat, lon } => Region::Around { radius: *radius, lat: *lat, lon: *lon },
+            SpatialFilter::Poly(points) => Region::Poly(Polygon::new(points.clone())
+                .map_err(|e| Error::Overpass(format!("invalid polygon: {e}")))?),
+            SpatialFilter::Area(name) => Region::Areas(sets.get(name)
+                .map(|set| set.areas.iter().flat_map(|i| &self.areas[*i].polygons).collect())
+                .unwrap_or_default())
+        })
+    }
+
+    /// `>`: the nodes of ways, and the members of relations along with the nodes of member ways.
+    fn recurse_down(&self, input: &Set) -> Set {
+        let mut result = Set::default();
+        for way in &input.ways {
+            result.nodes.extend(self.way_nodes(*way));
+        }
+        for relation in &input.relations {
+            let Some(Element::Relation { members, .. }) = self.relations.get(relation) else { continue };
+            for member in members {
+                let id: u64 = member.reference();
+                match member.member_type() {
+                    MemberType::Node => { result.nodes.insert(id); },
+                    MemberType::Way => {
+                        result.ways.insert(id);
+                        result.nodes.extend(self.way_nodes(id));
+                    },
+                    MemberType::Relation => { result.relations.insert(id); }
+                }
+            }
+        }
+        //Only elements that are in the data can be printed
+        result.nodes.retain(|id| self.nodes.contains_key(id));
+        result.ways.retain(|id| self.ways.contains_key(id));
+        result.relations.retain(|id| self.relations.contains_key(id));
+        result
+    }
+
+    /// `<`: the ways that use the nodes, and the relations that have any of the elements or those
+    /// ways as members.
+    fn recurse_up(&self, input: &Set) -> Set {
+        let mut result = Set::default();
+        for way in self.ways.values() {
+            if self.way_nodes(way.id()).iter().any(|node| input.nodes.contains(node)) {
+                result.ways.insert(way.id());
+            }
+        }
+        for relation in self.relations.values() {
+            let Element::Relation { members, .. } = relation else { continue };
+            let found: bool = members.iter().any(|member| {
+                let id: u64 = member.reference();
+                match member.member_type() {
+                    MemberType::Node => input.nodes.contains(&id),
+                    MemberType::Way => input.ways.contains(&id) || result.ways.contains(&id),
+                    MemberType::Relation => input.relations.contains(&id)
+                }
+            });
+            if found {
+                result.relations.insert(relation.id());
+            }
+        }
+        result
+    }
+
+    /// `out`: print a set sorted by type and then ID. `qt` is accepted but does not change the
+    /// order.
+    fn print(&self, set: &Set, modes: &[OutMode], output: &mut Vec<Value>) -> Result<(), Error> {
+
+        let detail: OutMode = modes.iter()
+            .copied()
+            .rfind(|mode| matches!(mode, OutMode::Ids | OutMode::Skel | OutMode::Body | OutMode::Tags | OutMode::Meta))
+            .unwrap_or(OutMode::Body);
+
+        let elements = set.nodes.iter().filter_map(|id| self.nodes.get(id))
+            .chain(set.ways.iter().filter_map(|id| self.ways.get(id)))
+            .chain(set.relations.iter().filter_map(|id| self.relations.get(id)));
+
+        for element in elements {
+            let mut value: Value = serde_json::to_value(element)?;
+            let Some(object) = value.as_object_mut() else { continue };
+
+            for field in ["bounds", "center", "geometry"] {
+                object.remove(field);
+            }
+            match detail {
+                OutMode::Ids => object.retain(|key, _| matches!(key.as_str(), "type" | "id")),
+                OutMode::Tags => object.retain(|key, _| matches!(key.as_str(), "type" | "id" | "tags")),
+                OutMode::Skel => object.retain(|key, _| key != "tags" && !META_FIELDS.contains(&key.as_str())),
+                OutMode::Body => object.retain(|key, _| !META_FIELDS.contains(&key.as_str())),
+                _ => {}
+            }
+            self.add_geometry(element, modes, object)?;
+
+            output.push(value);
+        }
+
+        for i in &set.areas {
+            let area: &Area = &self.areas[*i];
+            output.push(match detail {
+                OutMode::Ids | OutMode::Skel => json!({ "type": "area", "id": area.id }),
+                _ => json!({ "type": "area", "id": area.id, "tags": area.tags })
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Add the `geometry` of ways and the `bounds` and `center` of ways and relations, when the
+    /// `out` statement asks for them.
+    fn add_geometry(&self, element: &Element, modes: &[OutMode], object: &mut Map<String, Value>) -> Result<(), Error> {
+
+        if let Element::Node { .. } = element {
+            return Ok(())
+        }
+
+        //One entry per node of the way, with null for nodes that are not in the data, as Overpass does
+        if let (true, Element::Way { nodes, .. }) = (modes.contains(&OutMode::Geom), element) {
+            let geometry: Vec<Option<Coordinate>> = nodes.iter()
+                .map(|node| self.coordinate(*node).map(|(lat, lon)| Coordinate::new(lat, lon)))
+                .collect();
+            object.insert("geometry".to_string(), serde_json::to_value(geometry)?);
+        }
+
+        let points: Vec<(f64, f64)> = self.points(element);
+        let Some(bounds) = bounds(&points) else { return Ok(()) };
+
+        if modes.contains(&OutMode::Bb) {
+            object.insert("bounds".to_string(), serde_json::to_value(bounds)?);
+        }
+        if modes.contains(&OutMode::Center) {
+            let center = Coordinate::new(
+                (bounds.minlat() + bounds.maxlat()) / 2.0,
+                (bounds.minlon() + bounds.maxlon()) / 2.0
+            );
+            object.insert("center".to_string(), serde_json::to_value(center)?);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+//! This module holds a small stand-in for the Overpass API, for tests and for working offline. It
+//! is only built with the `emulator` feature:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! osmgraph = { version = "*", features = ["emulator"] }
+//! ```
+//!
+//! The [`Emulator`] loads a saved [`OverpassResponse`] or an OSM XML (`.osm`) file and answers
+//! queries from it, either directly with [`Emulator::run`] or over a local HTTP port with
+//! [`Emulator::serve`], so that a [`crate::api::QueryEngine`] can be pointed at it.
+//!
+//! Only the part of Overpass QL that the query engine generates is understood: `[out:json]`,
+//! `[timeout:]` and `[maxsize:]` settings; `node`, `way`, `rel`, `nwr` and `area` selectors with
+//! tag filters (`=`, `!=`, `~`, `!~`, and key existence); `(area.name)`, bounding box,
+//! `(around:...)` and `(poly:"...")` filters; unions, named sets, `>` and `<`; and `out` with
+//! `ids`, `skel`, `body`, `tags`, `meta`, `geom`, `center`, `bb` and `qt`. Anything else is
+//! answered with a parse error, the way Overpass answers a bad query.
+//!
+//! The answers are close to what Overpass would give, with some simplifications:
+//! - A way or relation is inside an area, polygon, bounding box or radius if any of its nodes is.
+//!   Overpass also counts ways whose segments cross the region without a node inside it.
+//! - Areas are made from the closed ways and the `multipolygon` and `boundary` relations in the
+//!   data, using only their outer rings. Areas that are not in the data, such as the boundary of
+//!   the town a saved response was fetched for, can be added with [`Emulator::with_area`].
+//! - `qt` does not change the order of the output, which is always sorted by ID.
+
+mod ql;
+mod eval;
+
+mod server;
+pub use server::EmulatorServer;
+
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use tokio::fs;
+
+use crate::Error;
+use crate::runtime::block_on;
+use crate::api::{Element, OverpassResponse, Polygon, Tags};
+use eval::Dataset;
+
+/// `Emulator` answers Overpass queries from data held in memory. See the [module
+/// documentation](crate::emulator) for the queries it understands.
+///
+/// Example:
+/// ```rust
+/// use osmgraph::api::{QueryEngine, OverpassResponse, Polygon};
+/// use osmgraph::emulator::Emulator;
+///
+/// let emulator = Emulator::load_blocking("./assets/test.json")
+///     .expect("Was not able to load the data!")
+///     .with_area(
+///         [("name", "Selinsgrove"), ("admin_level", "8")].into_iter().collect(),
+///         Polygon::new(vec![(40.78, -76.88), (40.81, -76.88), (40.81, -76.84), (40.78, -76.84)])
+///             .expect("Polygon should be valid!")
+///     );
+///
+/// let server = emulator.serve().expect("Was not able to start the emulator!");
+/// let engine = QueryEngine::new().with_url(server.url());
+///
+/// let response: OverpassResponse = engine
+///     .fetch_place_blocking("Selinsgrove".to_string(), Some(8))
+///     .expect("Was not able to query the emulator!");
+///
+/// assert!(!response.elements().is_empty());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Emulator {
+    data: Arc<Dataset>,
+    osm_base: Option<String>
+}
+
+impl Emulator {
+
+    /// Create an emulator that answers queries from these elements.
+    pub fn from_elements(elements: Vec<Element>) -> Self {
+        Self {
+            data: Arc::new(Dataset::new(elements)),
+            osm_base: None
+        }
+    }
+
+    /// Create an emulator that answers queries from the elements of a response. The
+    /// `timestamp_osm_base` of the response is given back in every answer.
+    pub fn from_response(response: &OverpassResponse) -> Self {
+        Self {
+            osm_base: response.osm3s()
+                .get("timestamp_osm_base")
+                .and_then(|timestamp| timestamp.as_str())
+                .map(|timestamp| timestamp.to_string()),
+            ..Self::from_elements(response.elements().clone())
+        }
+    }
+
+    /// Read the contents of a file: either a saved [`OverpassResponse`] or OSM XML. Contents that
+    /// start with `<` are always read as XML.
+    fn from_contents(contents: &[u8], xml: bool) -> Result<Self, Error> {
+        match xml || contents.trim_ascii_start().starts_with(b"<") {
+            true => Ok(Self::from_response(&OverpassResponse::from_xml(contents)?)),
+            false => Ok(Self::from_response(&serde_json::from_slice(contents)?))
+        }
+    }
+
+    /// Load an emulator from a saved [`OverpassResponse`] (such as one written by
+    /// [`OverpassResponse::save`]) or an OSM XML file. Files ending in `.osm` or `.xml` are read
+    /// as XML.
+    pub async fn load(filepath: &str) -> Result<Self, Error> {
+        let xml: bool = Path::new(filepath)
+            .extension()
+            .is_some_and(|extension| extension == "osm" || extension == "xml");
+        Self::from_contents(&fs::read(filepath).await?, xml)
+    }
+
+    /// Behaves the same as [`Emulator::load`], but will wait for the function to finish before continuing.
+    pub fn load_blocking(filepath: &str) -> Result<Self, Error> {
+        block_on(Self::load(filepath))
+    }
+
+    /// Add an area that can be searched by its tags, such as the boundary of the town that a
+    /// saved response was fetched for. Meant to be used in a functional style
+    pub fn with_area(&self, tags: Tags, polygon: Polygon) -> Self {
+        let mut data: Dataset = self.data.as_ref().clone();
+        data.add_area(tags, polygon);
+
+        Self {
+            data: Arc::new(data),
+            ..self.clone()
+        }
+    }
+
+    /// Answer a query, returning the json that Overpass would send. A query that cannot be parsed
+    /// or uses something the emulator does not support returns [`Error::Overpass`].
+    pub fn run(&self, query: &str) -> Result<String, Error> {
+
+        let query = ql::parse(query)?;
+        let elements: Vec<Value> = self.data.run(&query)?;
+
+        let response: Value = json!({
+            "version": 0.6,
+            "generator": "osmgraph emulator",
+            "osm3s": {
+                "timestamp_osm_base": self.osm_base.as_deref().unwrap_or_default(),
+                "copyright": "The data included in this document is from www.openstreetmap.org. The data is made available under ODbL."
+            },
+            "elements": elements
+        });
+
+        Ok(response.to_string())
+    }
+
+    /// Start answering queries on a free local port. The server runs until the returned
+    /// [`EmulatorServer`] is dropped.
+    pub fn serve(&self) -> Result<EmulatorServer, Error> {
+        self.serve_on("127.0.0.1:0")
+    }
+
+    /// Start answering queries on the given address.
+    pub fn serve_on(&self, addr: impl ToSocketAddrs) -> Result<EmulatorServer, Error> {
+        EmulatorServer::start(self.clone(), addr)
+    }
+}
@@ -0,0 +1,393 @@
+//! A parser for the part of Overpass QL that the emulator understands. Queries are parsed into
+//! the same [`QueryBuilder`] that [`crate::api::QueryEngine`] builds them with.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Error;
+use crate::api::{
+    QueryBuilder, Settings, Statement, Selector, ElementKind, TagFilter, SpatialFilter, OutMode
+};
+
+/// Parse the text of a query. Errors are reported the way Overpass reports them, as
+/// [`Error::Overpass`] with the line of the problem.
+pub(crate) fn parse(query: &str) -> Result<QueryBuilder, Error> {
+
+    let mut parser = Parser { query, position: 0 };
+
+    let mut settings = Settings::default();
+    let mut json: bool = false;
+    let mut has_settings: bool = false;
+    while parser.eat("[") {
+        has_settings = true;
+        let key: &str = parser.identifier()?;
+        parser.expect(":")?;
+        match key {
+            "out" => match parser.identifier()? {
+                "json" => json = true,
+                other => return Err(parser.unsupported(format!("[out:{other}], only [out:json]")))
+            },
+            "timeout" => settings = settings.with_timeout(parser.integer()?),
+            "maxsize" => settings = settings.with_maxsize(parser.integer()?),
+            other => return Err(parser.unsupported(format!("the setting [{other}:]")))
+        }
+        parser.expect("]")?;
+    }
+    if has_settings {
+        parser.expect(";")?;
+    }
+    if !json {
+        return Err(parser.unsupported("xml output, add [out:json]"))
+    }
+
+    let mut builder = QueryBuilder::new().with_settings(settings);
+    while !parser.at_end() {
+        builder = builder.with_statement(parser.statement()?);
+    }
+
+    Ok(builder)
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    position: usize
+}
+
+impl<'a> Parser<'a> {
+
+    fn error(&self, message: impl fmt::Display) -> Error {
+        let line: usize = self.query[..self.position].matches('\n').count() + 1;
+        Error::Overpass(format!("line {line}: parse error: {message}"))
+    }
+
+    fn unsupported(&self, what: impl fmt::Display) -> Error {
+        self.error(format!("{what} is not supported by the emulator"))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.query[self.position..]
+    }
+
+    /// Skip whitespace and comments.
+    fn skip(&mut self) {
+        loop {
+            let rest: &str = self.rest();
+            let trimmed: &str = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                self.position += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if let Some(comment) = trimmed.strip_prefix("/*") {
+                self.position += comment.find("*/").map(|end| end + 4).unwrap_or(trimmed.len());
+            } else {
+                break
+            }
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip();
+        self.rest().is_empty()
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip();
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip();
+        match self.rest().starts_with(token) {
+            true => {
+                self.position += token.len();
+                true
+            },
+            false => false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), Error> {
+        match self.eat(token) {
+            true => Ok(()),
+            false => Err(self.error(format!("expected `{token}`")))
+        }
+    }
+
+    /// Take the longest run of characters that match `accept`.
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> &'a str {
+        self.skip();
+        let rest: &'a str = self.rest();
+        let end: usize = rest.find(|c: char| !accept(c)).unwrap_or(rest.len());
+        self.position += end;
+        &rest[..end]
+    }
+
+    fn identifier(&mut self) -> Result<&'a str, Error> {
+        match self.take_while(|c| c.is_alphanumeric() || c == '_') {
+            "" => Err(self.error("expected a name")),
+            identifier => Ok(identifier)
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, Error> {
+        let text: &str = self.take_while(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+        text.parse().map_err(|_| self.error(format!("expected a number, got `{text}`")))
+    }
+
+    fn integer<T: FromStr>(&mut self) -> Result<T, Error> {
+        let text: &str = self.take_while(|c| c.is_ascii_digit());
+        text.parse().map_err(|_| self.error(format!("expected a whole number, got `{text}`")))
+    }
+
+    /// A quoted string, or a bare word such as `residential` or `8`.
+    fn string(&mut self) -> Result<String, Error> {
+
+        let quote: char = match self.peek() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => return match self.take_while(|c| c.is_alphanumeric() || "_.-:".contains(c)) {
+                "" => Err(self.error("expected a string")),
+                word => Ok(word.to_string())
+            }
+        };
+        self.position += 1;
+
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, escaped)) => value.push(escaped),
+                    None => break
+                },
+                c if c == quote => {
+                    self.position += i + 1;
+                    return Ok(value)
+                },
+                c => value.push(c)
+            }
+        }
+
+        Err(self.error("unterminated string"))
+    }
+
+    fn statement(&mut self) -> Result<Statement, Error> {
+
+        if self.eat("(") {
+            let mut statements: Vec<Statement> = vec![];
+            while !self.eat(")") {
+                if self.at_end() {
+                    return Err(self.error("expected `)`"))
+                }
+                statements.push(self.statement()?);
+            }
+            if self.eat("->") {
+                return Err(self.unsupported("naming the result of a union"))
+            }
+            self.expect(";")?;
+            return Ok(Statement::Union(statements))
+        }
+
+        if self.rest().starts_with(">>") || self.rest().starts_with("<<") {
+            return Err(self.unsupported("recursion with `>>` and `<<`"))
+        }
+        if self.eat(">") {
+            self.expect(";")?;
+            return Ok(Statement::RecurseDown)
+        }
+        if self.eat("<") {
+            self.expect(";")?;
+            return Ok(Statement::RecurseUp)
+        }
+        if self.eat(".") {
+            let set: &str = self.identifier()?;
+            if self.peek() != Some(';') {
+                return Err(self.unsupported("a statement on a named set"))
+            }
+            self.expect(";")?;
+            return Ok(match set {
+                "_" => Statement::Current,
+                set => Statement::Set(set.to_string())
+            })
+        }
+
+        let word: &str = self.identifier()?;
+        let kind: ElementKind = match word {
+            "out" => return self.out(),
+            "node" => ElementKind::Node,
+            "way" => ElementKind::Way,
+            "rel" | "relation" => ElementKind::Relation,
+            "nwr" => ElementKind::Nwr,
+            "area" => ElementKind::Area,
+            word => return Err(self.unsupported(format!("the statement `{word}`")))
+        };
+        self.selector(Selector::new(kind))
+    }
+
+    fn out(&mut self) -> Result<Statement, Error> {
+        let mut modes: Vec<OutMode> = vec![];
+        while !self.eat(";") {
+            let mode: OutMode = match self.identifier()? {
+                "ids" => OutMode::Ids,
+                "skel" => OutMode::Skel,
+                "body" => OutMode::Body,
+                "tags" => OutMode::Tags,
+                "meta" => OutMode::Meta,
+                "geom" => OutMode::Geom,
+                "center" => OutMode::Center,
+                "bb" => OutMode::Bb,
+                "qt" => OutMode::Qt,
+                mode => return Err(self.unsupported(format!("`out {mode}`")))
+            };
+            if self.peek() == Some('(') {
+                return Err(self.unsupported("clipping the output to a bounding box"))
+            }
+            modes.push(mode);
+        }
+        Ok(Statement::Out(modes))
+    }
+
+    fn selector(&mut self, mut selector: Selector) -> Result<Statement, Error> {
+        loop {
+            match self.peek() {
+                Some('[') => {
+                    self.position += 1;
+                    selector = selector.with_tag(self.tag_filter()?);
+                },
+                Some('(') => {
+                    self.position += 1;
+                    selector = selector.with_spatial(self.spatial_filter()?);
+                },
+                Some('.') => return Err(self.unsupported("filtering a named set")),
+                _ => break
+            }
+        }
+        if self.eat("->") {
+            self.expect(".")?;
+            selector = selector.with_output_set(self.identifier()?);
+        }
+        self.expect(";")?;
+        Ok(Statement::Select(selector))
+    }
+
+    fn tag_filter(&mut self) -> Result<TagFilter, Error> {
+
+        if self.eat("!") {
+            let key: String = self.string()?;
+            self.expect("]")?;
+            return Ok(TagFilter::not_exists(&key))
+        }
+        if self.peek() == Some('~') {
+            return Err(self.unsupported("matching keys with a regex"))
+        }
+
+        let key: String = self.string()?;
+        if self.eat("]") {
+            return Ok(TagFilter::exists(&key))
+        }
+
+        let filter: fn(&str, &str) -> TagFilter = if self.eat("!=") {
+            TagFilter::not_equals
+        } else if self.eat("!~") {
+            TagFilter::not_regex
+        } else if self.eat("=") {
+            TagFilter::equals
+        } else if self.eat("~") {
+            TagFilter::regex
+        } else {
+            return Err(self.error("expected `=`, `!=`, `~` or `!~`"))
+        };
+        let value: String = self.string()?;
+        if self.eat(",") {
+            return Err(self.unsupported("case insensitive matching"))
+        }
+        self.expect("]")?;
+
+        Ok(filter(&key, &value))
+    }
+
+    fn spatial_filter(&mut self) -> Result<SpatialFilter, Error> {
+
+        let filter: SpatialFilter = match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let south: f64 = self.number()?;
+                if !self.eat(",") {
+                    return Err(self.unsupported("selecting elements by id"))
+                }
+                let west: f64 = self.number()?;
+                self.expect(",")?;
+                let north: f64 = self.number()?;
+                self.expect(",")?;
+                let east: f64 = self.number()?;
+                SpatialFilter::BBox { south, west, north, east }
+            },
+            _ => match self.identifier()? {
+                "area" => match self.eat(".") {
+                    true => SpatialFilter::Area(self.identifier()?.to_string()),
+                    false => SpatialFilter::Area("_".to_string())
+                },
+                "around" => {
+                    if !self.eat(":") {
+                        return Err(self.unsupported("`around` on a named set"))
+                    }
+                    let radius: f64 = self.number()?;
+                    self.expect(",")?;
+                    let lat: f64 = self.number()?;
+                    self.expect(",")?;
+                    let lon: f64 = self.number()?;
+                    SpatialFilter::Around { radius, lat, lon }
+                },
+                "poly" => {
+                    self.expect(":")?;
+                    let numbers: Vec<f64> = self.string()?
+                        .split_whitespace()
+                        .map(|number| number.parse::<f64>())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| self.error("expected numbers in `poly`"))?;
+                    if !numbers.len().is_multiple_of(2) {
+                        return Err(self.error("`poly` needs pairs of latitude and longitude"))
+                    }
+                    SpatialFilter::Poly(numbers.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+                },
+                filter => return Err(self.unsupported(format!("the filter `({filter}...)`")))
+            }
+        };
+
+        self.expect(")")?;
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let query: &str = concat!(
+            "[out:json][timeout:25];\n",
+            "area[\"name\"=\"Val d'Or \\\"Nord\\\"\"][\"admin_level\"=\"8\"]->.searchArea;\n",
+            "way[\"highway\"~\"primary|secondary\"][!\"access\"](area.searchArea);\n",
+            "node(40.1,-76.5,40.2,-76.4)(around:50,40.15,-76.45)(poly:\"40 -76 41 -76 41 -75 40 -76\");\n",
+            "(._; .searchArea; >;);\n",
+            "out body qt;",
+        );
+        assert_eq!(parse(query).unwrap().build().unwrap(), query);
+    }
+
+    #[test]
+    fn test_comments_and_bare_words() {
+        let builder = parse("/* a */ [out:json]; // b\nway[highway=residential](area); out;").unwrap();
+        assert_eq!(builder.build().unwrap(), "[out:json];\nway[\"highway\"=\"residential\"](area._);\nout;");
+    }
+
+    #[test]
+    fn test_errors() {
+        for query in ["way;", "[out:xml];", "[out:json];\nway(1);", "[out:json];\nway[\"a\"", "[out:json]; way >> ;"] {
+            assert!(matches!(parse(query), Err(Error::Overpass(_))), "{query}");
+        }
+        let Err(Error::Overpass(message)) = parse("[out:json];\n\nfoo;") else { panic!() };
+        assert!(message.starts_with("line 3: parse error"));
+    }
+}
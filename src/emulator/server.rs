@@ -0,0 +1,170 @@
+//! The HTTP side of the emulator: a small blocking server that answers the requests
+//! [`crate::api::QueryEngine`] sends, one thread per connection.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use crate::Error;
+use crate::emulator::Emulator;
+
+/// `EmulatorServer` is an [`Emulator`] listening on a local port, started with
+/// [`Emulator::serve`]. It answers `/api/interpreter` (the `data` field of a POST form or GET
+/// query string) and `/api/status`, like an Overpass instance. The server stops when it is
+/// dropped.
+#[derive(Debug)]
+pub struct EmulatorServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl EmulatorServer {
+
+    pub(crate) fn start(emulator: Emulator, addr: impl ToSocketAddrs) -> Result<Self, Error> {
+
+        let listener = TcpListener::bind(addr)?;
+        let addr: SocketAddr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let stopped = stop.clone();
+        let thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break
+                }
+                if let Ok(stream) = stream {
+                    let emulator = emulator.clone();
+                    thread::spawn(move || {
+                        let _ = handle(stream, &emulator);
+                    });
+                }
+            }
+        });
+
+        Ok(Self { addr, stop, thread: Some(thread) })
+    }
+
+    /// Getter for the address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Get the URL of the interpreter, ready to give to [`crate::api::QueryEngine::with_url`].
+    pub fn url(&self) -> String {
+        format!("http://{}/api/interpreter", self.addr)
+    }
+}
+
+impl Drop for EmulatorServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        //Wake the listener up so that it sees the stop flag
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Read one request from the connection and answer it.
+fn handle(stream: TcpStream, emulator: &Emulator) -> std::io::Result<()> {
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method: String = parts.next().unwrap_or_default().to_string();
+    let target: String = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length: usize = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body: Vec<u8> = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let (path, query_string) = target.split_once('?').unwrap_or((&target, ""));
+    let form: String = match method.as_str() {
+        "POST" => String::from_utf8_lossy(&body).into_owned(),
+        _ => query_string.to_string()
+    };
+
+    let (status, content_type, body): (u16, &str, String) = if path.ends_with("/status") {
+        (200, "text/plain", "Connected as: 0\nRate limit: 0\n2 slots available now.\n".to_string())
+    } else if path.ends_with("/interpreter") {
+        match form_field(&form, "data").map(|query| emulator.run(&query)) {
+            Some(Ok(json)) => (200, "application/json", json),
+            Some(Err(Error::Overpass(message))) => (400, "text/html", error_page(&message)),
+            Some(Err(e)) => (500, "text/html", error_page(&e.to_string())),
+            None => (400, "text/html", error_page("no query given in the `data` field"))
+        }
+    } else {
+        (404, "text/plain", "Not Found".to_string())
+    };
+
+    let reason: &str = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        _ => "Internal Server Error"
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// An error page in the same shape as the ones Overpass sends.
+fn error_page(message: &str) -> String {
+    let message: String = message.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    format!(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<html>\n<head><title>OSM3S Response</title></head>\n<body>\n",
+        "<p><strong style=\"color:#FF0000\">Error</strong>: {}</p>\n",
+        "</body>\n</html>\n"
+    ), message)
+}
+
+/// Find and decode a field of a form encoded string.
+fn form_field(form: &str, name: &str) -> Option<String> {
+    form.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| decode(value))
+}
+
+fn decode(value: &str) -> String {
+    let bytes: &[u8] = value.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i: usize = 0;
+    while i < bytes.len() {
+        let hex = |i: usize| bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex(i)) {
+            (b'+', _) => decoded.push(b' '),
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 2;
+            },
+            (byte, _) => decoded.push(byte)
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
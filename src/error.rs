@@ -0,0 +1,170 @@
+use std::fmt;
+
+/// `Error` is the error type returned throughout this crate, by both the [`crate::api`] and the
+/// [`crate::graph`] modules.
+///
+/// The variants separate failures that are worth retrying (the server was busy, the connection
+/// dropped, the request took too long) from failures that will happen again no matter how many
+/// times the request is sent (the query or the data is bad). [`Error::is_retryable`] makes that
+/// distinction for you.
+///
+/// Example:
+/// ```rust
+/// use osmgraph::Error;
+///
+/// let error = Error::HttpStatus { status: 429, body: String::new() };
+/// assert!(error.is_retryable());
+///
+/// let error = Error::MissingNode { way: 10, node: 1 };
+/// assert!(!error.is_retryable());
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// The request could not be sent or the response could not be received, such as when the
+    /// connection is refused or the host name cannot be resolved.
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+
+    /// The server responded with a status code that is not a success, such as 429 (too many
+    /// requests) or 504 (gateway timeout). The body of the response is kept, since Overpass
+    /// explains the problem there.
+    HttpStatus {
+        status: u16,
+        body: String
+    },
+
+    /// Overpass accepted the query but reported a runtime error while running it, such as running
+    /// out of memory.
+    Overpass(String),
+
+    /// Overpass hit a runtime error (such as `Query timed out` or `out of memory`) part way through
+    /// the query and reported it in the `remark` of the response. The elements it had already
+    /// output are still in `body`, but they are not the full result of the query.
+    Incomplete {
+        remark: String,
+        body: String
+    },
+
+    /// The request took too long, either on our side or on the server.
+    Timeout(String),
+
+    /// A response or file could not be parsed. The line and column of the problem are given
+    /// where they are known (they are zero otherwise).
+    Parse {
+        message: String,
+        line: usize,
+        column: usize
+    },
+
+    /// A way refers to a node that is not part of the data, so the graph cannot be built.
+    MissingNode {
+        way: u64,
+        node: u64
+    },
+
+    /// The geometry given to a query (such as a polygon) is not valid.
+    InvalidGeometry(String),
+
+    /// A way or tag filter is not valid, such as a regular expression that does not compile.
+    InvalidFilter(String),
+
+    /// The engine is set up in a way that cannot work, such as having no endpoints to send
+    /// queries to.
+    Config(String),
+
+    /// The engine is in offline mode and there is no cached response for the query. The
+    /// query text is kept so that it can be fetched somewhere with network access.
+    CacheMiss(String),
+
+    /// A blocking function (such as [`crate::api::QueryEngine::query_blocking`]) was called from
+    /// inside a current-thread async runtime, where it would block the runtime's only thread. Use
+    /// the async version of the function there instead.
+    Runtime(String),
+
+    /// Reading or writing a file failed.
+    Io(std::io::Error)
+}
+
+impl Error {
+
+    /// Returns true if sending the same request again could succeed. This is the case for
+    /// transport failures, timeouts and the HTTP statuses that Overpass uses when it is
+    /// overloaded (429, 502, 503 and 504).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Transport(_) | Error::Timeout(_) => true,
+            Error::HttpStatus { status, .. } => matches!(status, 429 | 502 | 503 | 504),
+            _ => false
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Transport(e) => write!(f, "transport error: {e}"),
+            Error::HttpStatus { status, .. } => write!(f, "server responded with HTTP status {status}"),
+            Error::Overpass(message) => write!(f, "overpass error: {message}"),
+            Error::Incomplete { remark, .. } => write!(f, "incomplete response: {remark}"),
+            Error::Timeout(message) => write!(f, "timed out: {message}"),
+            Error::Parse { message, line, column } =>
+                write!(f, "parse error at line {line} column {column}: {message}"),
+            Error::MissingNode { way, node } =>
+                write!(f, "way {way} refers to node {node} which is not in the data"),
+            Error::InvalidGeometry(message) => write!(f, "invalid geometry: {message}"),
+            Error::InvalidFilter(message) => write!(f, "invalid filter: {message}"),
+            Error::Config(message) => write!(f, "invalid configuration: {message}"),
+            Error::CacheMiss(_) => write!(f, "no cached response for the query while offline"),
+            Error::Runtime(message) => write!(f, "blocking call inside an async runtime: {message}"),
+            Error::Io(e) => write!(f, "io error: {e}")
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(e) => Some(e.as_ref()),
+            Error::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        if e.is_io() {
+            return Error::Io(e.into())
+        }
+
+        // serde_json adds the position to the end of its message, which we keep separately
+        let message: String = e.to_string();
+        let message: &str = message
+            .rsplit_once(" at line ")
+            .map_or(message.as_str(), |(message, _)| message);
+
+        Error::Parse {
+            message: message.to_string(),
+            line: e.line(),
+            column: e.column()
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Error::Timeout(e.to_string())
+        } else if let Some(status) = e.status() {
+            Error::HttpStatus { status: status.as_u16(), body: String::new() }
+        } else {
+            Error::Transport(Box::new(e))
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use petgraph::graph::NodeIndex;
+
+use crate::api::{Element, Tags, WayFilter, osm_xml::{self, ActionType}};
+use crate::Error;
+
+use super::{
+    way::{OSMWay, get_osm_ways, get_osm_ways_with_filter},
+    node::{OSMNode, node_dist, get_osm_nodes, get_nodes_from_geometry},
+    edge::OSMEdge,
+    graph::OSMGraph
+};
+
+/// `ChangeSet` holds the nodes and ways that were created, modified or deleted between two dates,
+/// as read from the answer to an augmented diff (`[adiff:]`) query such as the ones sent by
+/// [`crate::api::QueryEngine::changes_place`]. It can be applied to a graph built at the first
+/// date to get the graph at the second date, without fetching the whole area again.
+///
+/// Only ways with a `highway` tag are kept, the same as in [`crate::graph::create_graph`], unless
+/// the change set is read with a [`WayFilter`] ([`Self::from_xml_with_filter`]). A way that starts
+/// to pass the filter counts as created and a way that stops passing it counts as deleted.
+/// Created and modified elements are given as they are at the second date, deleted elements as
+/// they were at the first date.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ChangeSet {
+    created_nodes: Vec<OSMNode>,
+    modified_nodes: Vec<OSMNode>,
+    deleted_nodes: Vec<OSMNode>,
+    created_ways: Vec<OSMWay>,
+    modified_ways: Vec<OSMWay>,
+    deleted_ways: Vec<OSMWay>,
+
+    //Nodes that only appear as the geometry of a created or modified way
+    geometry_nodes: Vec<OSMNode>,
+
+    osm_base: Option<String>
+}
+
+impl ChangeSet {
+
+    /// Read a change set out of the XML that Overpass sends for a `[diff:]` or `[adiff:]` query.
+    /// Ways that are printed with `out geom` carry the coordinates of their nodes, which are used
+    /// when a way refers to a node that is neither in the graph nor in the change set.
+    pub fn from_xml(xml: &[u8]) -> Result<Self, Error> {
+        Self::read(xml, get_osm_ways)
+    }
+
+    /// Does the same thing as [`Self::from_xml`], but keeps the ways that pass a [`WayFilter`]
+    /// instead of the ways with a `highway` tag, the same as
+    /// [`crate::graph::create_graph_with_filter`].
+    pub fn from_xml_with_filter(xml: &[u8], filter: &WayFilter) -> Result<Self, Error> {
+        let matcher = filter.matcher()?;
+        Self::read(xml, |elements| get_osm_ways_with_filter(elements, &matcher))
+    }
+
+    /// Read a change set, getting the ways that are kept out of each side of a change with
+    /// `get_ways`.
+    fn read(xml: &[u8], get_ways: impl Fn(&[Element]) -> Result<Vec<OSMWay>, Error>) -> Result<Self, Error> {
+
+        let diff = osm_xml::parse_diff(xml)?;
+        let mut changes = ChangeSet { osm_base: diff.osm_base, ..Default::default() };
+
+        let mut new_elements: Vec<Element> = vec![];
+
+        for action in diff.actions {
+
+            let old_nodes: Vec<OSMNode> = get_osm_nodes(&action.old)?;
+            let new_nodes: Vec<OSMNode> = get_osm_nodes(&action.new)?;
+            let old_ways: Vec<OSMWay> = get_ways(&action.old)?;
+            let new_ways: Vec<OSMWay> = get_ways(&action.new)?;
+
+            match action.action {
+                ActionType::Create => {
+                    changes.created_nodes.extend(new_nodes);
+                    changes.created_ways.extend(new_ways);
+                },
+                ActionType::Delete => {
+                    changes.deleted_nodes.extend(old_nodes);
+                    changes.deleted_ways.extend(old_ways);
+                },
+                ActionType::Modify => {
+                    changes.modified_nodes.extend(new_nodes);
+
+                    //A way that is only kept on one side of the change was created or deleted
+                    let new_ids: HashSet<u64> = new_ways.iter().map(|way| way.id()).collect();
+                    let old_ids: HashSet<u64> = old_ways.iter().map(|way| way.id()).collect();
+                    changes.deleted_ways.extend(old_ways.into_iter().filter(|way| !new_ids.contains(&way.id())));
+                    for way in new_ways {
+                        match old_ids.contains(&way.id()) || action.old.is_empty() {
+                            true => changes.modified_ways.push(way),
+                            false => changes.created_ways.push(way)
+                        }
+                    }
+                }
+            }
+
+            if action.action != ActionType::Delete {
+                new_elements.extend(action.new);
+            }
+        }
+
+        changes.geometry_nodes = get_nodes_from_geometry(&new_elements)?;
+
+        Ok(changes)
+    }
+
+    /// Get the nodes that were created.
+    pub fn created_nodes(&self) -> &Vec<OSMNode> {
+        &self.created_nodes
+    }
+    /// Get the nodes that were modified (moved or retagged), as they are after the change.
+    pub fn modified_nodes(&self) -> &Vec<OSMNode> {
+        &self.modified_nodes
+    }
+    /// Get the nodes that were deleted, as they were before the change.
+    pub fn deleted_nodes(&self) -> &Vec<OSMNode> {
+        &self.deleted_nodes
+    }
+    /// Get the ways that were created.
+    pub fn created_ways(&self) -> &Vec<OSMWay> {
+        &self.created_ways
+    }
+    /// Get the ways that were modified, as they are after the change.
+    pub fn modified_ways(&self) -> &Vec<OSMWay> {
+        &self.modified_ways
+    }
+    /// Get the ways that were deleted, as they were before the change.
+    pub fn deleted_ways(&self) -> &Vec<OSMWay> {
+        &self.deleted_ways
+    }
+    /// Get the `osm_base` timestamp of the diff, if Overpass sent one.
+    pub fn osm_base(&self) -> Option<&str> {
+        self.osm_base.as_deref()
+    }
+
+    /// Check if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.created_nodes.is_empty() && self.modified_nodes.is_empty() && self.deleted_nodes.is_empty()
+            && self.created_ways.is_empty() && self.modified_ways.is_empty() && self.deleted_ways.is_empty()
+    }
+
+    /// Apply the changes to a graph that was built at the first date of the diff:
+    /// - The edges of deleted and modified ways are removed, and modified ways are added again.
+    /// - Modified nodes are updated in place, and the edges that end at them get a new length.
+    /// - Created nodes and ways are added.
+    /// - Deleted nodes are removed, along with any edges that are still attached to them.
+    ///
+    /// Petgraph moves nodes and edges around when others are removed, so indexes into the graph
+    /// taken before the call should not be used afterwards.
+    ///
+    /// If a created or modified way refers to a node that is not in the graph, the change set or
+    /// the geometry of the way, [`Error::MissingNode`] is returned. The graph may have been
+    /// partially changed by then.
+    ///
+    /// ```rust
+    /// use osmgraph::graph::{ChangeSet, OSMGraph, create_graph};
+    /// use osmgraph::api::OverpassResponse;
+    ///
+    /// let before: OverpassResponse = serde_json::from_str(r#"{"version": 0.6, "generator": "Overpass API", "osm3s": {}, "elements": [
+    ///     {"type": "node", "id": 1, "lat": 40.0, "lon": -76.0},
+    ///     {"type": "node", "id": 2, "lat": 40.001, "lon": -76.0},
+    ///     {"type": "way", "id": 10, "nodes": [1, 2], "tags": {"highway": "residential"}}
+    /// ]}"#).unwrap();
+    /// let mut graph: OSMGraph = create_graph(before.elements()).unwrap();
+    ///
+    /// let changes = ChangeSet::from_xml(br#"<osm version="0.6">
+    ///     <action type="create">
+    ///         <node id="3" lat="40.002" lon="-76.0"/>
+    ///     </action>
+    ///     <action type="create">
+    ///         <way id="11"><nd ref="2"/><nd ref="3"/><tag k="highway" v="service"/></way>
+    ///     </action>
+    /// </osm>"#).unwrap();
+    ///
+    /// changes.apply(&mut graph).unwrap();
+    /// assert_eq!(graph.node_count(), 3);
+    /// assert_eq!(graph.edge_count(), 2);
+    /// ```
+    pub fn apply(&self, graph: &mut OSMGraph) -> Result<(), Error> {
+
+        //Remove the edges of deleted ways, and of modified ways since they are added again below
+        let replaced: HashSet<u64> = self.deleted_ways.iter()
+            .chain(&self.modified_ways)
+            .map(|way| way.id())
+            .collect();
+        graph.retain_edges(|g, edge| !replaced.contains(&g[edge].way_id()));
+
+        //Update modified nodes and add created ones
+        let mut node_mapping: HashMap<u64, NodeIndex> = graph.node_indices()
+            .map(|index| (graph[index].id(), index))
+            .collect();
+        for node in self.modified_nodes.iter().chain(&self.created_nodes) {
+            match node_mapping.get(&node.id()) {
+                Some(index) => graph[*index] = node.clone(),
+                None => {
+                    node_mapping.insert(node.id(), graph.add_node(node.clone()));
+                }
+            }
+        }
+
+        //The edges that are left and end at a moved node need a new length
+        let moved: HashSet<u64> = self.modified_nodes.iter().map(|node| node.id()).collect();
+        let stale: Vec<_> = graph.edge_indices()
+            .filter(|edge| graph[*edge].nodes().iter().any(|id| moved.contains(id)))
+            .collect();
+        for edge in stale {
+            let (a, b) = graph.edge_endpoints(edge).unwrap();
+            let dist: f64 = node_dist(&graph[a], &graph[b]);
+            let old: &OSMEdge = &graph[edge];
+            let updated = OSMEdge::new(old.nodes(), dist, old.highway_type().to_string())
+                .with_shared_tags(old.shared_tags().clone())
+                .with_way_id(old.way_id());
+            graph[edge] = updated;
+        }
+
+        //Add the edges of created and modified ways
+        let geometry: HashMap<u64, &OSMNode> = self.geometry_nodes.iter()
+            .map(|node| (node.id(), node))
+            .collect();
+        for way in self.created_ways.iter().chain(&self.modified_ways) {
+
+            let mut indexes: Vec<NodeIndex> = Vec::with_capacity(way.nodes().len());
+            for id in way.nodes() {
+                let index: NodeIndex = match node_mapping.get(id) {
+                    Some(index) => *index,
+                    None => {
+                        let node: &OSMNode = geometry.get(id)
+                            .ok_or(Error::MissingNode { way: way.id(), node: *id })?;
+                        let index: NodeIndex = graph.add_node((*node).clone());
+                        node_mapping.insert(*id, index);
+                        index
+                    }
+                };
+                indexes.push(index);
+            }
+
+            let tags: Arc<Tags> = Arc::new(way.tags().clone());
+            for window in indexes.windows(2) {
+                let (n1, n2) = (&graph[window[0]], &graph[window[1]]);
+                let edge = OSMEdge::new([n1.id(), n2.id()], node_dist(n1, n2), way.highway_type().to_string())
+                    .with_shared_tags(tags.clone())
+                    .with_way_id(way.id());
+                graph.add_edge(window[0], window[1], edge);
+            }
+        }
+
+        //Removing nodes moves the indexes of others, so this is done last
+        let deleted: HashSet<u64> = self.deleted_nodes.iter().map(|node| node.id()).collect();
+        graph.retain_nodes(|g, node| !deleted.contains(&g[node].id()));
+
+        Ok(())
+    }
+}
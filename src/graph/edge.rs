@@ -1,9 +1,13 @@
 use std::fmt;
+use std::sync::Arc;
+
+use crate::api::Tags;
 
 /// OSMNode contains all information that we might care about in an edge as stored in
 /// the petgraph. Currently, it contains the two nodes it is connected to (`[u64; 2]` where u64 is
 /// the node ID as defined by OSM, and the first element is the first node, the second element is
-/// the second), the distance between them, and the type of edge (highway, street, sidewalk, etc.)
+/// the second), the distance between them, the type of edge (highway, street, sidewalk, etc.) and
+/// the ID and tags of the way that the edge belongs to. The tags are shared by all edges of a way.
 #[derive(Clone, PartialEq, PartialOrd, Debug, Default)]
 pub struct OSMEdge {
 
@@ -14,7 +18,13 @@ pub struct OSMEdge {
     dist: f64,
 
     //Highway type as defined by OSM
-    highway_type: String
+    highway_type: String,
+
+    //Tags of the way this edge is a part of
+    tags: Arc<Tags>,
+
+    //ID of the way this edge is a part of
+    way_id: u64
 }
 
 impl fmt::Display for OSMEdge {
@@ -36,7 +46,39 @@ impl OSMEdge {
         OSMEdge {
             nodes,
             dist,
-            highway_type
+            highway_type,
+            tags: Arc::new(Tags::new()),
+            way_id: 0
+        }
+    }
+
+    /// Set the tags of this edge. Meant to be used in a functional style
+    ///
+    /// ```rust
+    /// use osmgraph::api::Tags;
+    /// use osmgraph::graph::OSMEdge;
+    ///
+    /// let edge = OSMEdge::new([1, 2], 10., "residential".to_string())
+    ///     .with_tags([("highway", "residential"), ("maxspeed", "25 mph")].into_iter().collect());
+    /// ```
+    pub fn with_tags(&self, tags: Tags) -> Self {
+        self.with_shared_tags(Arc::new(tags))
+    }
+
+    /// Set the tags of this edge to tags that other edges also use, without copying them. Meant
+    /// to be used in a functional style
+    pub fn with_shared_tags(&self, tags: Arc<Tags>) -> Self {
+        Self {
+            tags,
+            ..self.clone()
+        }
+    }
+
+    /// Set the ID of the way that this edge belongs to. Meant to be used in a functional style
+    pub fn with_way_id(&self, way_id: u64) -> Self {
+        Self {
+            way_id,
+            ..self.clone()
         }
     }
 
@@ -52,4 +94,17 @@ impl OSMEdge {
     pub fn highway_type(&self) -> &str {
         &self.highway_type
     }
+    /// Get the tags of the way that this `OSMEdge` belongs to.
+    pub fn tags(&self) -> &Tags {
+        &self.tags
+    }
+    /// Get the tags of the way that this `OSMEdge` belongs to, as shared with the other edges of
+    /// that way.
+    pub fn shared_tags(&self) -> &Arc<Tags> {
+        &self.tags
+    }
+    /// Get the ID of the way that this `OSMEdge` belongs to, or 0 if it was not set.
+    pub fn way_id(&self) -> u64 {
+        self.way_id
+    }
 }
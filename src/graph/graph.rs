@@ -1,13 +1,16 @@
-use std::collections::HashMap;
-use std::error::Error;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use petgraph::{graph::UnGraph, adj::NodeIndex};
 
-use crate::api::Element;
+use crate::api::{Element, Tags, WayFilter, WayMatcher};
+#[cfg(feature = "pbf")]
+use crate::api::{PbfReader, pbf::Kinds};
+use crate::Error;
 
 use super::{
-    way::{OSMWay, get_osm_ways},
-    node::{OSMNode, node_dist, get_osm_nodes},
+    way::{OSMWay, get_osm_way, get_osm_way_with_filter, get_osm_ways, get_osm_ways_with_filter},
+    node::{OSMNode, node_dist, get_osm_node, geometry_nodes, get_osm_nodes, get_nodes_from_geometry, filter_unconnected_nodes},
     edge::OSMEdge
 };
 
@@ -15,11 +18,235 @@ use super::{
 pub type OSMGraph = UnGraph<OSMNode, OSMEdge>;
 
 /// Given a json type structure, this function tries to parse an `OSMGraph` out of that json.
-pub fn create_graph(elements: &Vec<Element>) -> Result<OSMGraph, Box<dyn Error>> {
+///
+/// Nodes are taken from the node elements of the json. If the ways carry inline geometry (from a
+/// `way ... out geom;` query) then nodes are also created from that geometry, so such a response
+/// does not need any separate node elements.
+///
+/// If a way refers to a node that is not in the json, [`Error::MissingNode`] is returned.
+pub fn create_graph(elements: &[Element]) -> Result<OSMGraph, Error> {
+    build_graph(elements, get_osm_ways(elements)?, false)
+}
+
+/// Does the same thing as [`create_graph`], but only keeps the ways that pass a [`WayFilter`],
+/// which do not need a `highway` tag. Only the nodes on those ways are kept, so data that was
+/// fetched with a broad filter (or loaded from a cache) can be narrowed down without fetching it
+/// again.
+///
+/// A regular expression in the filter that does not compile returns [`Error::InvalidFilter`].
+///
+/// ```rust
+/// use osmgraph::api::{OverpassResponse, TagFilter, WayFilter};
+/// use osmgraph::graph::{OSMGraph, create_graph_with_filter};
+///
+/// let response: OverpassResponse = OverpassResponse::load_blocking("./assets/test.json")
+///     .expect("Was not able to load json!");
+///
+/// let filter = WayFilter::all(vec![
+///     WayFilter::highway(&["residential"]),
+///     WayFilter::tag(TagFilter::not_equals("access", "private")),
+/// ]);
+/// let graph: OSMGraph = create_graph_with_filter(response.elements(), &filter)
+///     .expect("Was not able to create the graph!");
+///
+/// assert!(graph.edge_weights().all(|edge| edge.highway_type() == "residential"));
+/// ```
+pub fn create_graph_with_filter(elements: &[Element], filter: &WayFilter) -> Result<OSMGraph, Error> {
+    build_graph(elements, get_osm_ways_with_filter(elements, &filter.matcher()?)?, true)
+}
+
+/// Does the same thing as [`create_graph`], but takes the elements one at a time, such as from an
+/// [`crate::api::ElementReader`]. Only the nodes and ways that end up in the graph are held in
+/// memory, so a response that is much larger than the graph can be read from disk or from the
+/// network. The first error from the elements is returned.
+///
+/// ```rust
+/// use osmgraph::api::ElementReader;
+/// use osmgraph::graph::{OSMGraph, create_graph_from_stream};
+///
+/// let reader = ElementReader::open("./assets/test.json").expect("Was not able to open the file!");
+/// let graph: OSMGraph = create_graph_from_stream(reader).expect("Was not able to create the graph!");
+///
+/// assert!(graph.edge_count() > 0);
+/// ```
+pub fn create_graph_from_stream<I>(elements: I) -> Result<OSMGraph, Error>
+where
+    I: IntoIterator<Item = Result<Element, Error>>
+{
+    let mut builder = GraphBuilder::new();
+    for element in elements {
+        builder.add(element?)?;
+    }
+    builder.build()
+}
+
+/// Build a graph straight from an OSM PBF file, such as a country extract from Geofabrik, keeping
+/// the ways that pass a [`WayFilter`] and the nodes on them (the same as
+/// [`create_graph_with_filter`]). Use `WayFilter::highway(&[])` to keep every way with a `highway`
+/// tag, or a [`crate::api::NetworkType`] for the ways a kind of traveller can use.
+///
+/// The file is read twice. The first time only the ways are read, to find the ones that pass the
+/// filter and the nodes they need; the second time only those nodes are kept. That way nodes that
+/// are not part of the graph (which are most of the nodes in a file) are never held in memory.
+///
+/// This function needs the `pbf` feature. See [`crate::api::PbfReader`] for the files that can be
+/// read.
+///
+/// ```rust,no_run
+/// use osmgraph::api::NetworkType;
+/// use osmgraph::graph::{OSMGraph, create_graph_from_pbf};
+///
+/// let graph: OSMGraph = create_graph_from_pbf("./pennsylvania-latest.osm.pbf", &NetworkType::Drive.into())
+///     .expect("Was not able to create the graph!");
+/// ```
+#[cfg(feature = "pbf")]
+pub fn create_graph_from_pbf(filepath: &str, filter: &WayFilter) -> Result<OSMGraph, Error> {
+
+    //First pass: the ways that pass the filter
+    let mut builder = GraphBuilder::new().with_filter(filter.clone());
+    let ways = PbfReader::open(filepath)?.with_kinds(Kinds { nodes: false, ways: true, relations: false });
+    for element in ways {
+        builder.add(element?)?;
+    }
+
+    //Second pass: the nodes on those ways
+    let needed: HashSet<u64> = builder.ways.iter()
+        .flat_map(|way| way.nodes())
+        .copied()
+        .collect();
+    let nodes = PbfReader::open(filepath)?.with_kinds(Kinds { nodes: true, ways: false, relations: false });
+    for element in nodes {
+        let element: Element = element?;
+        if needed.contains(&element.id()) {
+            builder.add(element)?;
+        }
+    }
+
+    builder.build()
+}
+
+/// `GraphBuilder` builds an `OSMGraph` out of elements that are given to it one at a time, for
+/// when the elements are not all in memory at once. Elements can come in any order: ways may
+/// refer to nodes that are added after them. See [`create_graph_from_stream`] for the common case
+/// of reading an iterator of elements, and [`crate::api::AsyncElementReader`] for an example of
+/// using the builder directly.
+///
+/// Without a filter only ways with a `highway` tag are kept, the same as in [`create_graph`].
+/// With a filter, set by [`GraphBuilder::with_filter`], the ways that pass it are kept and nodes
+/// that are not on them are left out, the same as in [`create_graph_with_filter`].
+#[derive(Clone, Debug, Default)]
+pub struct GraphBuilder {
+    filter: Option<WayFilter>,
+    matcher: Option<WayMatcher>,
+
+    nodes: Vec<OSMNode>,
+    node_ids: HashSet<u64>,
+    ways: Vec<OSMWay>,
+
+    //Nodes that only appear as the inline geometry of a way
+    geometry_nodes: Vec<OSMNode>,
+    geometry_ids: HashSet<u64>
+}
+
+impl GraphBuilder {
+
+    /// Create a builder without any elements.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only keep the ways that pass a [`WayFilter`]. Meant to be used in a functional style
+    ///
+    /// ```rust
+    /// use osmgraph::api::NetworkType;
+    /// use osmgraph::graph::GraphBuilder;
+    ///
+    /// let builder = GraphBuilder::new().with_filter(NetworkType::Walk.into());
+    /// ```
+    pub fn with_filter(&self, filter: WayFilter) -> Self {
+        Self {
+            filter: Some(filter),
+            matcher: None,
+            ..self.clone()
+        }
+    }
+
+    /// Add an element to the graph. A regular expression in the filter that does not compile
+    /// returns [`Error::InvalidFilter`] on the first call.
+    pub fn add(&mut self, element: Element) -> Result<(), Error> {
+
+        if let Some(node) = get_osm_node(&element) {
+            if self.node_ids.insert(node.id()) {
+                self.nodes.push(node);
+            }
+            return Ok(())
+        }
+
+        let way: Option<OSMWay> = match &self.filter {
+            Some(filter) => {
+                if self.matcher.is_none() {
+                    self.matcher = Some(filter.matcher()?);
+                }
+                get_osm_way_with_filter(&element, self.matcher.as_ref().unwrap())
+            },
+            None => get_osm_way(&element)
+        };
+
+        //Only keep the geometry of ways that make it into the graph
+        if let Some(way) = way {
+            for node in geometry_nodes(&element) {
+                if self.geometry_ids.insert(node.id()) {
+                    self.geometry_nodes.push(node);
+                }
+            }
+            self.ways.push(way);
+        }
+
+        Ok(())
+    }
 
-    //Parse out all of the nodes and ways
-    let ways: Vec<OSMWay> = get_osm_ways(elements)?;
-    let nodes: Vec<OSMNode> = get_osm_nodes(elements)?;
+    /// Build the graph out of the elements that were added. If a way refers to a node that was
+    /// not added, [`Error::MissingNode`] is returned.
+    pub fn build(self) -> Result<OSMGraph, Error> {
+
+        let mut nodes: Vec<OSMNode> = self.nodes;
+        let node_ids: HashSet<u64> = self.node_ids;
+        nodes.extend(
+            self.geometry_nodes
+                .into_iter()
+                .filter(|node| !node_ids.contains(&node.id()))
+        );
+        if self.filter.is_some() {
+            nodes = filter_unconnected_nodes(&self.ways, nodes);
+        }
+
+        assemble_graph(nodes, self.ways)
+    }
+}
+
+/// Build a graph out of some ways and the nodes in the json. If `only_connected` is set, nodes
+/// that are not on any of the ways are left out.
+fn build_graph(elements: &[Element], ways: Vec<OSMWay>, only_connected: bool) -> Result<OSMGraph, Error> {
+
+    //Parse out all of the nodes
+    let mut nodes: Vec<OSMNode> = get_osm_nodes(elements)?;
+
+    //Fill in any nodes that only exist as inline way geometry
+    let node_ids: HashSet<u64> = nodes.iter().map(|node| node.id()).collect();
+    nodes.extend(
+        get_nodes_from_geometry(elements)?
+            .into_iter()
+            .filter(|node| !node_ids.contains(&node.id()))
+    );
+    if only_connected {
+        nodes = filter_unconnected_nodes(&ways, nodes);
+    }
+
+    assemble_graph(nodes, ways)
+}
+
+/// Put nodes and the edges of ways into a graph.
+fn assemble_graph(nodes: Vec<OSMNode>, ways: Vec<OSMWay>) -> Result<OSMGraph, Error> {
 
     let mut result = UnGraph::<OSMNode, OSMEdge>::with_capacity(nodes.len(), ways.len());
 
@@ -40,6 +267,9 @@ pub fn create_graph(elements: &Vec<Element>) -> Result<OSMGraph, Box<dyn Error>>
     for way in ways {
         let nodes = way.nodes();
 
+        //Every edge of the way points to the same tags
+        let tags: Arc<Tags> = Arc::new(way.tags().clone());
+
         //Iterate through all pairs of nodes in way
         for window in nodes.windows(2) {
 
@@ -48,8 +278,10 @@ pub fn create_graph(elements: &Vec<Element>) -> Result<OSMGraph, Box<dyn Error>>
             let node_id_2: u64 = window[1];
 
             //Find petgraph node ID
-            let node_index_1: NodeIndex = node_mapping[&node_id_1];
-            let node_index_2: NodeIndex = node_mapping[&node_id_2];
+            let node_index_1: NodeIndex = *node_mapping.get(&node_id_1)
+                .ok_or(Error::MissingNode { way: way.id(), node: node_id_1 })?;
+            let node_index_2: NodeIndex = *node_mapping.get(&node_id_2)
+                .ok_or(Error::MissingNode { way: way.id(), node: node_id_2 })?;
 
             //Get nodes out of petgraph
             let n1: &OSMNode = result.node_weight(node_index_1.into()).unwrap();
@@ -62,6 +294,8 @@ pub fn create_graph(elements: &Vec<Element>) -> Result<OSMGraph, Box<dyn Error>>
                 
                 //Weight information
                 OSMEdge::new([n1.id(), n2.id()], node_dist(n1,n2), way.highway_type().to_string())
+                    .with_shared_tags(tags.clone())
+                    .with_way_id(way.id())
             );
         }
     }
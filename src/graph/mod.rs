@@ -18,6 +18,9 @@
 //! because OSM stores a way as a *polylines* of Nodes, but petgraph stores edges just as a *pair* of
 //! nodes. Storing a (long) list of nodes for each edge is also inefficient. But we still have to
 //! deal with the way that OSM stores this information, therefore we have a distinction.
+//!
+//! Finally, a [`crate::graph::ChangeSet`] holds what changed between two dates, and can update a
+//! graph built at the first date to the second.
 
 pub mod edge;
 pub use edge::*;
@@ -27,5 +30,9 @@ pub use node::*;
 
 pub mod way;
 
+pub mod changes;
+pub use changes::ChangeSet;
+
+#[allow(clippy::module_inception)]
 pub mod graph;
 pub use graph::*;
@@ -1,20 +1,20 @@
 use core::fmt;
-use std::collections::{HashSet, HashMap};
-use std::error::Error;
+use std::collections::HashSet;
 
 use crate::graph::way::OSMWay;
-use crate::api::Element;
-
-type OSMTag = Option<HashMap<String, String>>;
+use crate::api::{Element, Metadata, Tags};
+use crate::Error;
 
 /// OSMNode contains all information that we might care about in a node. Currently, it contains a
-/// node ID (as defined in Overpass API) a latitude and a longitude.
+/// node ID (as defined in Overpass API) a latitude, a longitude, tags and the OSM metadata of the
+/// node (if it was requested with `out meta`).
 #[derive(Clone, PartialEq, Debug, Default)]
 pub struct OSMNode {
     id: u64,
     lat: f64,
     lon: f64,
-    tags: OSMTag
+    tags: Tags,
+    meta: Metadata
 }
 
 impl fmt::Display for OSMNode {
@@ -27,8 +27,24 @@ impl fmt::Display for OSMNode {
 impl OSMNode {
 
     /// Create a new OSMNode from fields.
-    pub fn new(id: u64, lat: f64, lon: f64, tags: OSMTag) -> Self {
-        OSMNode { id, lat, lon, tags}
+    pub fn new(id: u64, lat: f64, lon: f64, tags: Tags) -> Self {
+        OSMNode { id, lat, lon, tags, meta: Metadata::default() }
+    }
+
+    /// Set the OSM metadata of this node. Meant to be used in a functional style
+    ///
+    /// ```rust
+    /// use osmgraph::api::{Metadata, Tags};
+    /// use osmgraph::graph::OSMNode;
+    ///
+    /// let node = OSMNode::new(1, 40.0, -76.0, Tags::new())
+    ///     .with_meta(Metadata::new(Some(3), None, None, None, None));
+    /// ```
+    pub fn with_meta(&self, meta: Metadata) -> Self {
+        Self {
+            meta,
+            ..self.clone()
+        }
     }
 
     /// Get the node ID.
@@ -45,9 +61,13 @@ impl OSMNode {
     }
 
     /// Get a reference tags associated with this node
-    pub fn tags(&self) -> &OSMTag {
+    pub fn tags(&self) -> &Tags {
         &self.tags
     }
+    /// Get the OSM metadata (version, timestamp, changeset, user) of this node.
+    pub fn meta(&self) -> &Metadata {
+        &self.meta
+    }
 }
 
 /// Compute the [haversine distance](https://en.wikipedia.org/wiki/Haversine_formula)
@@ -71,18 +91,52 @@ pub(super) fn node_dist(n1: &OSMNode, n2: &OSMNode) -> f64 {
     )
 }
 
+/// Turn one element into an `OSMNode`, if it is a node.
+pub(crate) fn get_osm_node(element: &Element) -> Option<OSMNode> {
+    if let Element::Node { id, lat, lon, tags, meta } = element {
+        Some(OSMNode { id: *id, lat: *lat, lon: *lon, tags: tags.clone().unwrap_or_default(), meta: meta.clone() })
+    } else {
+        None
+    }
+}
+
+/// Create an `OSMNode` for every point of the inline geometry of one element, if it is a way
+/// printed with `out geom`. A node that the way passes more than once is given more than once.
+pub(crate) fn geometry_nodes(element: &Element) -> impl Iterator<Item = OSMNode> + '_ {
+
+    let (nodes, geometry) = match element {
+        Element::Way { nodes, geometry: Some(geometry), .. } => (nodes.as_slice(), geometry.as_slice()),
+        _ => (&[][..], &[][..])
+    };
+
+    nodes.iter()
+        .zip(geometry)
+        .filter_map(|(id, coordinate)| {
+            coordinate.as_ref().map(|coordinate| OSMNode::new(*id, coordinate.lat(), coordinate.lon(), Tags::new()))
+        })
+}
+
 /// Given a json type structure, this function tries to parse all `OSMNodes` out of that json.
-pub fn get_osm_nodes(elements: &Vec<Element>) -> Result<Vec<OSMNode>, Box<dyn Error>> {
+pub fn get_osm_nodes(elements: &[Element]) -> Result<Vec<OSMNode>, Error> {
 
     //Only get OSM elements that are nodes
-    let node_elements: Vec<OSMNode> = elements.into_iter()
-        .filter_map(|e| {
-            if let Element::Node { id, lat, lon, tags } = e {
-                Some(OSMNode { id: *id, lat: *lat, lon: *lon, tags: tags.clone() })
-            } else {
-                None
-            }
-        })
+    let node_elements: Vec<OSMNode> = elements.iter()
+        .filter_map(get_osm_node)
+        .collect();
+
+    Ok(node_elements)
+}
+
+/// Given a json type structure, this function creates an `OSMNode` for every point of inline way
+/// geometry (as returned by `out geom`). Each node takes its ID from the way's node list, so ways
+/// that share a node produce only one `OSMNode`. Such nodes have no tags or metadata since the
+/// geometry only carries coordinates.
+pub fn get_nodes_from_geometry(elements: &[Element]) -> Result<Vec<OSMNode>, Error> {
+
+    let mut seen: HashSet<u64> = HashSet::new();
+    let node_elements: Vec<OSMNode> = elements.iter()
+        .flat_map(geometry_nodes)
+        .filter(|node| seen.insert(node.id))
         .collect();
 
     Ok(node_elements)
@@ -90,7 +144,7 @@ pub fn get_osm_nodes(elements: &Vec<Element>) -> Result<Vec<OSMNode>, Box<dyn Er
 
 /// Given a set of nodes and ways, this function tries to parse all `OSMNodes` that lie
 /// on one of the ways provided.
-pub fn filter_unconnected_nodes(ways: &Vec<OSMWay>, nodes: Vec<OSMNode>) -> Vec<OSMNode> {
+pub fn filter_unconnected_nodes(ways: &[OSMWay], nodes: Vec<OSMNode>) -> Vec<OSMNode> {
 
     //Create set of node ids
     let mut node_ids: HashSet<u64> = HashSet::with_capacity(ways.len());
@@ -109,8 +163,8 @@ pub fn filter_unconnected_nodes(ways: &Vec<OSMWay>, nodes: Vec<OSMNode>) -> Vec<
 
 /// Given a json type structure and a `Vec<OSMWay>`, this function tries to
 /// parse all `OSMNodes` out of that json if and only if the node lies on one of the ways provided.
-pub fn get_nodes_from_ways(elements: &Vec<Element>, ways: &Vec<OSMWay>)
-    -> Result<Vec<OSMNode>, Box<dyn Error>> { 
+pub fn get_nodes_from_ways(elements: &[Element], ways: &[OSMWay])
+    -> Result<Vec<OSMNode>, Error> { 
 
     //Create set of node ids
     let mut node_ids: HashSet<u64> = HashSet::with_capacity(ways.len());
@@ -121,12 +175,12 @@ pub fn get_nodes_from_ways(elements: &Vec<Element>, ways: &Vec<OSMWay>)
     }
 
     //Only get OSM elements that are nodes
-    let node_elements: Vec<OSMNode> = elements.clone().into_iter()
+    let node_elements: Vec<OSMNode> = elements.iter()
         .filter_map(|e| {
-            if let Element::Node { id, lat, lon, tags } = e {
+            if let Element::Node { id, lat, lon, tags, meta } = e {
 
-                if node_ids.contains(&id) {
-                    Some(OSMNode { id, lat, lon, tags})
+                if node_ids.contains(id) {
+                    Some(OSMNode { id: *id, lat: *lat, lon: *lon, tags: tags.clone().unwrap_or_default(), meta: meta.clone() })
                 } else {
                     None
                 }
@@ -1,36 +1,39 @@
 use std::fmt;
-use std::error::Error;
 
-use crate::api::Element;
+use crate::api::{Element, Metadata, Tags, WayMatcher};
+use crate::Error;
 
 /// OSMWay contains all information that we might care about in a way. Currently, it contains a
 /// way ID (as defined in Overpass API) the nodes indicies on the path, the distances between them,
-/// and the type of way (highway, street, sidewalk, etc).
+/// the type of way (highway, street, sidewalk, etc), the tags of the way and the OSM metadata of
+/// the way (if it was requested with `out meta`).
 #[derive(Clone, PartialEq, PartialOrd, Debug, Default)]
 pub struct OSMWay {
     id: u64,
     nodes: Vec<u64>,
     dists: Vec<f64>,
-    highway_type: String
+    highway_type: String,
+    tags: Tags,
+    meta: Metadata
 }
 
 impl fmt::Display for OSMWay {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "OSMWay(\n")?;
-        write!(f, "  id: {}\n", self.id)?;
-        write!(f, "  type: {}\n", self.highway_type)?;
+        writeln!(f, "OSMWay(")?;
+        writeln!(f, "  id: {}", self.id)?;
+        writeln!(f, "  type: {}", self.highway_type)?;
 
-        write!(f, "  nodes: [\n")?;
+        writeln!(f, "  nodes: [")?;
         for node in &self.nodes {
-            write!(f, "    {}\n", node)?;
+            writeln!(f, "    {}", node)?;
         }
-        write!(f, "  ]\n")?;
+        writeln!(f, "  ]")?;
         
-        write!(f, "  dists: [\n")?;
+        writeln!(f, "  dists: [")?;
         for dist in &self.dists {
-            write!(f, "    {}\n", dist)?;
+            writeln!(f, "    {}", dist)?;
         }
-        write!(f, "  ]\n")?;
+        writeln!(f, "  ]")?;
 
         write!(f, ")")?;
 
@@ -43,7 +46,39 @@ impl OSMWay {
 
     /// Create a new OSMWay from fields.
     pub fn new(id: u64, nodes: Vec<u64>, dists: Vec<f64>, highway_type: String) -> Self {
-        OSMWay { id, nodes, dists, highway_type }
+        OSMWay { id, nodes, dists, highway_type, tags: Tags::new(), meta: Metadata::default() }
+    }
+
+    /// Set the tags of this way. Meant to be used in a functional style
+    ///
+    /// ```rust
+    /// use osmgraph::api::Tags;
+    /// use osmgraph::graph::way::OSMWay;
+    ///
+    /// let way = OSMWay::new(1, vec![1, 2], vec![], "residential".to_string())
+    ///     .with_tags([("highway", "residential"), ("lanes", "2")].into_iter().collect());
+    /// ```
+    pub fn with_tags(&self, tags: Tags) -> Self {
+        Self {
+            tags,
+            ..self.clone()
+        }
+    }
+
+    /// Set the OSM metadata of this way. Meant to be used in a functional style
+    ///
+    /// ```rust
+    /// use osmgraph::api::Metadata;
+    /// use osmgraph::graph::way::OSMWay;
+    ///
+    /// let way = OSMWay::new(1, vec![1, 2], vec![], "residential".to_string())
+    ///     .with_meta(Metadata::new(Some(3), None, None, None, None));
+    /// ```
+    pub fn with_meta(&self, meta: Metadata) -> Self {
+        Self {
+            meta,
+            ..self.clone()
+        }
     }
 
     /// Get the way ID.
@@ -62,35 +97,80 @@ impl OSMWay {
     pub fn highway_type(&self) -> &str {
         &self.highway_type
     }
+    /// Get the tags of this way.
+    pub fn tags(&self) -> &Tags {
+        &self.tags
+    }
+    /// Get the OSM metadata (version, timestamp, changeset, user) of this way.
+    pub fn meta(&self) -> &Metadata {
+        &self.meta
+    }
+}
+
+/// Turn one element into an `OSMWay`, if it is a way with a `highway` tag.
+pub(crate) fn get_osm_way(element: &Element) -> Option<OSMWay> {
+    if let Element::Way { id, nodes, tags, meta, .. } = element {
+
+        let highway_type = tags.as_ref()?.get("highway")?;
+
+        Some(OSMWay {
+            id: *id,
+            nodes: nodes.to_vec(),
+            // We can only compute distance if we have access to the nodes as well
+            // Leave this blank at the moment
+            dists: vec![],
+            highway_type: highway_type.to_string(),
+            tags: tags.clone().unwrap_or_default(),
+            meta: meta.clone()
+        })
+    } else {
+        None
+    }
+}
+
+/// Turn one element into an `OSMWay`, if it is a way that passes a way filter. Ways without a
+/// `highway` tag get an empty highway type.
+pub(crate) fn get_osm_way_with_filter(element: &Element, matcher: &WayMatcher) -> Option<OSMWay> {
+    if let Element::Way { id, nodes, tags, meta, .. } = element {
+
+        if !matcher.matches(tags.as_ref()) {
+            return None
+        }
+
+        Some(OSMWay {
+            id: *id,
+            nodes: nodes.to_vec(),
+            dists: vec![],
+            highway_type: tags.as_ref()
+                .and_then(|tags| tags.get("highway"))
+                .map(|highway| highway.to_string())
+                .unwrap_or_default(),
+            tags: tags.clone().unwrap_or_default(),
+            meta: meta.clone()
+        })
+    } else {
+        None
+    }
 }
 
 /// Given a json type structure, this function tries to parse all `OSMWay` out of that json.
-pub fn get_osm_ways(elements: &Vec<Element>) -> Result<Vec<OSMWay>, Box<dyn Error>> {
+pub fn get_osm_ways(elements: &[Element]) -> Result<Vec<OSMWay>, Error> {
 
     //Only get OSM elements that are ways and the ways must have tags
-    let way_elements: Vec<OSMWay> = elements.into_iter()
-        .filter_map(|elem| {
-            if let Element::Way { id, nodes, tags } = elem {
-
-                if *tags == None {
-                    return None
-                }
-                if let Some(highway_type) = tags.clone()?.get("highway") {
-                    Some(OSMWay {
-                        id: *id,
-                        nodes: nodes.to_vec(),
-                        // We can only compute distance if we have access to the nodes as well
-                        // Leave this blank at the moment
-                        dists: vec![], 
-                        highway_type: highway_type.to_string()
-                    })
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        })
+    let way_elements: Vec<OSMWay> = elements.iter()
+        .filter_map(get_osm_way)
+        .collect();
+
+    Ok(way_elements)
+}
+
+/// Given a json type structure, this function tries to parse every `OSMWay` that passes a way
+/// filter out of that json. Unlike [`get_osm_ways`], ways do not need a `highway` tag: those
+/// without one get an empty highway type.
+pub fn get_osm_ways_with_filter(elements: &[Element], matcher: &WayMatcher) -> Result<Vec<OSMWay>, Error> {
+
+    let way_elements: Vec<OSMWay> = elements.iter()
+        .filter_map(|element| get_osm_way_with_filter(element, matcher))
         .collect();
 
     Ok(way_elements)
@@ -7,7 +7,8 @@
 //!
 //! This crate queries the OverpassAPI which is a *free to use* API for getting OSM data. I caution
 //! users of this crate to not accidentally spam their server with very large requests. In general,
-//! try to query once and then save the result locally while you are developing. The query language
+//! try to query once and then save the result locally while you are developing (a `QueryCache`
+//! given to the `QueryEngine` does this for you). The query language
 //! that Overpass uses is known as the Overpass Query Language and you can learn more about how to
 //! use it [here](https://wiki.openstreetmap.org/wiki/Overpass_API/Overpass_QL).
 //!
@@ -24,7 +25,8 @@
 //!
 //! Example of the basic utility of this crate:
 //!
-//! ```rust
+//! ```rust,no_run
+//! # #[cfg(feature = "reqwest")] {
 //! use osmgraph::graph::{OSMGraph, create_graph};
 //! use osmgraph::api::{QueryEngine, OverpassResponse, Element};
 //!
@@ -51,8 +53,17 @@
 //! //Create graph
 //! let g: OSMGraph = create_graph(elements)
 //!     .expect("Was not able to create graph from json!");
+//! # }
 //! ```
 
 pub mod api;
 
 pub mod graph;
+
+#[cfg(feature = "emulator")]
+pub mod emulator;
+
+pub mod error;
+pub use error::Error;
+
+mod runtime;
@@ -0,0 +1,51 @@
+//! The runtime behind the `*_blocking` functions of this crate.
+
+use std::future::Future;
+use std::sync::OnceLock;
+
+use tokio::runtime::{Builder, Handle, Runtime, RuntimeFlavor};
+
+use crate::Error;
+
+/// Started the first time a blocking function is called, and shared by every call after that.
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// Run a future to completion on the shared runtime and return its result.
+///
+/// Blocking a thread of an async runtime would stall the tasks on it, and tokio panics when it
+/// notices. Inside a multi-threaded runtime the call goes through `block_in_place`, which moves
+/// the other tasks of a worker thread elsewhere and does nothing on other threads (such as those
+/// of `spawn_blocking`). A current-thread runtime cannot do that, and its threads cannot be told
+/// apart, so there this returns [`Error::Runtime`] and the async version of the function should
+/// be used.
+pub(crate) fn block_on<T>(future: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+
+    match Handle::try_current() {
+        Err(_) => shared()?.block_on(future),
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            let runtime: &Runtime = shared()?;
+            tokio::task::block_in_place(|| runtime.block_on(future))
+        },
+        Ok(_) => Err(Error::Runtime(
+            "a current-thread runtime cannot move off its thread, use the async version instead".to_string()
+        ))
+    }
+}
+
+/// Get the shared runtime, starting it if this is the first call.
+fn shared() -> Result<&'static Runtime, Error> {
+
+    let runtime: &Runtime = match RUNTIME.get() {
+        Some(runtime) => runtime,
+        None => {
+            //If another thread gets there first, this runtime is dropped and theirs is used
+            let runtime: Runtime = Builder::new_multi_thread()
+                .enable_all()
+                .thread_name("osmgraph-blocking")
+                .build()?;
+            RUNTIME.get_or_init(|| runtime)
+        }
+    };
+
+    Ok(runtime)
+}
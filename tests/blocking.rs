@@ -0,0 +1,67 @@
+mod common;
+
+#[cfg(test)]
+mod blocking {
+
+    use osmgraph::Error;
+    use osmgraph::api::OverpassResponse;
+
+    use crate::common;
+    #[cfg(feature = "emulator")]
+    use osmgraph::emulator::Emulator;
+
+    #[cfg(feature = "emulator")]
+    #[test]
+    fn shared_runtime() {
+        let response = OverpassResponse::load_blocking("./assets/test.json").expect("Was not able to load json!");
+        let server = Emulator::from_response(&response).serve().expect("Was not able to start the emulator!");
+        let engine = common::engine().with_url(server.url());
+
+        //Many calls in a row, and from several threads at once
+        for _ in 0..20 {
+            engine.query_blocking("[out:json]; node(around:10,40.8091553,-76.8557739); out;".to_string())
+                .expect("Query should succeed!");
+        }
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    engine.fetch_blocking("[out:json]; node(around:10,40.8091553,-76.8557739); out;".to_string())
+                        .expect("Query should succeed!")
+                });
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn inside_runtime() {
+        let engine = common::engine().with_url("http://127.0.0.1:1/api/interpreter".to_string());
+
+        //Returns an error without sending anything, instead of panicking
+        let result = engine.query_blocking("[out:json]; node(1); out;".to_string());
+        match result {
+            //Not to be mistaken for the runtime errors that Overpass reports
+            Err(e @ Error::Runtime(_)) => assert!(e.to_string().starts_with("blocking call inside an async runtime")),
+            other => panic!("Expected a runtime error, got {other:?}")
+        }
+
+        let result = OverpassResponse::load_blocking("./assets/test.json");
+        assert!(matches!(result, Err(Error::Runtime(_))));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn inside_multi_thread_runtime() {
+        //From spawn_blocking, and right on a worker thread or in a task
+        let result = tokio::task::spawn_blocking(|| OverpassResponse::load_blocking("./assets/test.json"))
+            .await
+            .unwrap();
+        assert!(result.is_ok());
+        assert!(OverpassResponse::load_blocking("./assets/test.json").is_ok());
+        let result = tokio::spawn(async { OverpassResponse::load_blocking("./assets/test.json") })
+            .await
+            .unwrap();
+        assert!(result.is_ok());
+
+        //The async version works
+        assert!(OverpassResponse::load("./assets/test.json").await.is_ok());
+    }
+}
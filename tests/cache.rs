@@ -0,0 +1,184 @@
+mod common;
+
+#[cfg(test)]
+mod cache {
+
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use osmgraph::api::{QueryEngine, QueryCache, RetryPolicy};
+    use osmgraph::Error;
+
+    use crate::common::{self, MockServer, Response};
+
+    const BODY: &str = r#"{
+        "version": 0.6,
+        "generator": "mock",
+        "osm3s": { "timestamp_osm_base": "2024-11-18T15:04:05Z", "copyright": "ODbL" },
+        "elements": [{ "type": "node", "id": 1, "lat": 40.0, "lon": -76.0 }]
+    }"#;
+    const QUERY: &str = "[out:json];node(1);out;";
+
+    /// A fresh cache directory for one test.
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("osmgraph-cache-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn engine(server: &MockServer, cache: QueryCache) -> QueryEngine {
+        common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+            .with_cache(cache)
+    }
+
+    #[tokio::test]
+    async fn repeated_query_is_cached() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+        let cache = QueryCache::new(cache_dir("repeated"));
+        let engine = engine(&server, cache.clone());
+
+        let first = engine.query(QUERY.to_string()).await.expect("Query should succeed!");
+        let second = engine.query(QUERY.to_string()).await.expect("Query should succeed!");
+        let parsed = engine.fetch(QUERY.to_string()).await.expect("Fetch should succeed!");
+
+        assert_eq!(first, BODY);
+        assert_eq!(second, BODY);
+        assert_eq!(parsed.elements().len(), 1);
+        assert_eq!(server.requests().len(), 1);
+
+        let entry = cache.entry(&server.interpreter_url(), QUERY)
+            .await
+            .expect("Entry should be readable!")
+            .expect("Entry should exist!");
+        assert_eq!(entry.query(), QUERY);
+        assert_eq!(entry.endpoint(), server.interpreter_url());
+        assert_eq!(entry.timestamp_osm_base(), Some("2024-11-18T15:04:05Z"));
+
+        cache.clear().await.expect("Cache should be cleared!");
+        assert!(cache.entry(&server.interpreter_url(), QUERY).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn fetched_response_is_cached() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+        let cache = QueryCache::new(cache_dir("fetched"));
+        let engine = engine(&server, cache.clone());
+
+        let first = engine.fetch(QUERY.to_string()).await.expect("Fetch should succeed!");
+        let second = engine.fetch(QUERY.to_string()).await.expect("Fetch should succeed!");
+
+        assert_eq!(first, second);
+        assert_eq!(server.requests().len(), 1);
+
+        let entry = cache.entry(&server.interpreter_url(), QUERY).await.unwrap().expect("Entry should exist!");
+        assert_eq!(entry.timestamp_osm_base(), Some("2024-11-18T15:04:05Z"));
+    }
+
+    #[tokio::test]
+    async fn expired_entries_are_refetched() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+        let engine = engine(&server, QueryCache::new(cache_dir("expired")).with_ttl(Some(Duration::ZERO)));
+
+        engine.query(QUERY.to_string()).await.expect("Query should succeed!");
+        engine.query(QUERY.to_string()).await.expect("Query should succeed!");
+
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn key_includes_endpoint_and_query() {
+
+        assert_ne!(QueryCache::key("a", QUERY), QueryCache::key("b", QUERY));
+        assert_ne!(QueryCache::key("a", QUERY), QueryCache::key("a", "[out:json];node(2);out;"));
+        assert_eq!(QueryCache::key("a", QUERY), QueryCache::key("a", QUERY));
+
+        let dir = cache_dir("endpoints");
+        let first = MockServer::start(|_, _| Response::json(BODY));
+        let second = MockServer::start(|_, _| Response::json(BODY));
+
+        engine(&first, QueryCache::new(&dir)).query(QUERY.to_string()).await.unwrap();
+        engine(&second, QueryCache::new(&dir)).query(QUERY.to_string()).await.unwrap();
+
+        assert_eq!(first.requests().len(), 1);
+        assert_eq!(second.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn entries_for_other_queries_are_ignored() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+        let dir = cache_dir("collision");
+        let cache = QueryCache::new(&dir);
+        engine(&server, cache.clone()).query(QUERY.to_string()).await.expect("Query should succeed!");
+
+        //Stand in for another query whose key is the same
+        let key = QueryCache::key(&server.interpreter_url(), QUERY);
+        let meta = dir.join(format!("{key}.meta.json"));
+        let text = std::fs::read_to_string(&meta).unwrap().replace("node(1)", "node(2)");
+        std::fs::write(&meta, text).unwrap();
+
+        assert!(cache.entry(&server.interpreter_url(), QUERY).await.unwrap().is_none());
+        let offline = engine(&server, cache.with_offline(true));
+        assert!(matches!(offline.query(QUERY.to_string()).await, Err(Error::CacheMiss(_))));
+    }
+
+    #[tokio::test]
+    async fn files_are_moved_into_place() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+        let dir = cache_dir("files");
+        engine(&server, QueryCache::new(&dir)).query(QUERY.to_string()).await.expect("Query should succeed!");
+
+        let key = QueryCache::key(&server.interpreter_url(), QUERY);
+        let mut files: Vec<String> = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+
+        assert_eq!(files, vec![key.clone(), format!("{key}.meta.json")]);
+        assert_eq!(std::fs::read_to_string(dir.join(key)).unwrap(), BODY);
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_cached() {
+
+        let server = MockServer::start(|_, previous| match previous {
+            0 => Response::status(400),
+            _ => Response::json(BODY)
+        });
+        let engine = engine(&server, QueryCache::new(cache_dir("errors")));
+
+        assert!(engine.query(QUERY.to_string()).await.is_err());
+        assert_eq!(engine.query(QUERY.to_string()).await.unwrap(), BODY);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn offline() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+        let cache = QueryCache::new(cache_dir("offline"));
+
+        //Fill the cache while online
+        engine(&server, cache.clone()).query_blocking(QUERY.to_string()).unwrap();
+
+        let offline = engine(&server, cache.with_offline(true));
+        assert_eq!(offline.query_blocking(QUERY.to_string()).unwrap(), BODY);
+
+        //Responses that have expired are still used
+        let expired = engine(&server, cache.with_offline(true).with_ttl(Some(Duration::ZERO)));
+        assert_eq!(expired.query_blocking(QUERY.to_string()).unwrap(), BODY);
+
+        match offline.query_blocking("[out:json];node(2);out;".to_string()) {
+            Err(Error::CacheMiss(query)) => assert_eq!(query, "[out:json];node(2);out;"),
+            other => panic!("Expected a cache miss, got {other:?}")
+        }
+
+        assert_eq!(server.requests().len(), 1);
+    }
+}
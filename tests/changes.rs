@@ -0,0 +1,189 @@
+mod common;
+
+#[cfg(test)]
+mod changes {
+
+    use osmgraph::api::{OverpassResponse, RetryPolicy, TagFilter, WayFilter};
+    use osmgraph::graph::{ChangeSet, OSMGraph, OSMNode, create_graph, way::OSMWay};
+
+    use crate::common::{self, MockServer, Response};
+
+    /// A residential street 1-2-3 and a footway 3-4.
+    const BEFORE: &str = r#"{"version": 0.6, "generator": "Overpass API", "osm3s": {}, "elements": [
+        {"type": "node", "id": 1, "lat": 40.000, "lon": -76.0},
+        {"type": "node", "id": 2, "lat": 40.001, "lon": -76.0},
+        {"type": "node", "id": 3, "lat": 40.002, "lon": -76.0},
+        {"type": "node", "id": 4, "lat": 40.003, "lon": -76.0},
+        {"type": "way", "id": 10, "nodes": [1, 2, 3], "tags": {"highway": "residential"}},
+        {"type": "way", "id": 11, "nodes": [3, 4], "tags": {"highway": "footway"}}
+    ]}"#;
+
+    /// Node 2 moves, node 5 and way 12 (3-5, through node 6 which only exists as geometry) are
+    /// created, way 11 is deleted along with node 4, and way 13 becomes a highway.
+    const ADIFF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <osm version="0.6" generator="Overpass API">
+          <meta osm_base="2021-01-01T00:00:00Z"/>
+          <action type="modify">
+            <old><node id="2" lat="40.001" lon="-76.0" version="1"/></old>
+            <new><node id="2" lat="40.001" lon="-76.001" version="2" timestamp="2020-06-01T00:00:00Z"/></new>
+          </action>
+          <action type="create">
+            <node id="5" lat="40.002" lon="-76.002" version="1"/>
+          </action>
+          <action type="create">
+            <way id="12" version="1">
+              <nd ref="3" lat="40.002" lon="-76.0"/>
+              <nd ref="6" lat="40.002" lon="-76.001"/>
+              <nd ref="5" lat="40.002" lon="-76.002"/>
+              <tag k="highway" v="service"/>
+            </way>
+          </action>
+          <action type="delete">
+            <old>
+              <way id="11" version="1">
+                <nd ref="3" lat="40.002" lon="-76.0"/>
+                <nd ref="4" lat="40.003" lon="-76.0"/>
+                <tag k="highway" v="footway"/>
+              </way>
+            </old>
+            <new><way id="11" visible="false" version="2"/></new>
+          </action>
+          <action type="delete">
+            <old><node id="4" lat="40.003" lon="-76.0" version="1"/></old>
+            <new><node id="4" visible="false" version="2"/></new>
+          </action>
+          <action type="modify">
+            <old><way id="13" version="1"><nd ref="1"/><nd ref="4"/><tag k="building" v="yes"/></way></old>
+            <new>
+              <way id="13" version="2">
+                <nd ref="1" lat="40.000" lon="-76.0"/>
+                <nd ref="5" lat="40.002" lon="-76.002"/>
+                <tag k="highway" v="track"/>
+              </way>
+            </new>
+          </action>
+        </osm>"#;
+
+    fn before() -> OSMGraph {
+        let response: OverpassResponse = serde_json::from_str(BEFORE).unwrap();
+        create_graph(response.elements()).expect("Was unable to parse graph!")
+    }
+
+    #[test]
+    fn from_xml() {
+        let changes = ChangeSet::from_xml(ADIFF.as_bytes()).expect("Was not able to read the diff!");
+
+        let node_ids = |nodes: &Vec<OSMNode>| -> Vec<u64> { nodes.iter().map(|n| n.id()).collect() };
+        let way_ids = |ways: &Vec<OSMWay>| -> Vec<u64> { ways.iter().map(|w| w.id()).collect() };
+        assert_eq!(node_ids(changes.created_nodes()), vec![5]);
+        assert_eq!(node_ids(changes.modified_nodes()), vec![2]);
+        assert_eq!(node_ids(changes.deleted_nodes()), vec![4]);
+        assert_eq!(way_ids(changes.created_ways()), vec![12, 13]);
+        assert!(changes.modified_ways().is_empty());
+        assert_eq!(way_ids(changes.deleted_ways()), vec![11]);
+
+        assert_eq!(changes.modified_nodes()[0].lon(), -76.001);
+        assert_eq!(changes.modified_nodes()[0].meta().version(), Some(2));
+        assert_eq!(changes.deleted_ways()[0].highway_type(), "footway");
+        assert_eq!(changes.osm_base(), Some("2021-01-01T00:00:00Z"));
+        assert!(!changes.is_empty());
+    }
+
+    #[test]
+    fn apply() {
+        let mut graph: OSMGraph = before();
+        let changes = ChangeSet::from_xml(ADIFF.as_bytes()).unwrap();
+
+        let length = |graph: &OSMGraph, way: u64| -> f64 {
+            graph.edge_weights().filter(|e| e.way_id() == way).map(|e| e.dist()).sum()
+        };
+        let old_length: f64 = length(&graph, 10);
+
+        changes.apply(&mut graph).expect("Was not able to apply the changes!");
+
+        //Nodes 1, 2, 3, 5 and the geometry-only node 6
+        let mut nodes: Vec<u64> = graph.node_weights().map(|n| n.id()).collect();
+        nodes.sort();
+        assert_eq!(nodes, vec![1, 2, 3, 5, 6]);
+
+        //Way 10 is still there, but longer since node 2 moved off the line
+        assert_eq!(graph.edge_weights().filter(|e| e.way_id() == 10).count(), 2);
+        assert!(length(&graph, 10) > old_length);
+
+        assert_eq!(graph.edge_weights().filter(|e| e.way_id() == 11).count(), 0);
+        assert_eq!(graph.edge_weights().filter(|e| e.way_id() == 12).count(), 2);
+        assert_eq!(graph.edge_weights().filter(|e| e.way_id() == 13).count(), 1);
+        assert_eq!(graph.edge_count(), 5);
+
+        //Applying nothing changes nothing
+        let copy: OSMGraph = graph.clone();
+        ChangeSet::default().apply(&mut graph).unwrap();
+        assert_eq!(graph.node_count(), copy.node_count());
+        assert_eq!(graph.edge_count(), copy.edge_count());
+    }
+
+    #[test]
+    fn missing_node() {
+        let mut graph: OSMGraph = before();
+        let changes = ChangeSet::from_xml(br#"<osm>
+            <action type="create"><way id="20"><nd ref="1"/><nd ref="99"/><tag k="highway" v="service"/></way></action>
+        </osm>"#).unwrap();
+
+        assert!(matches!(changes.apply(&mut graph), Err(osmgraph::Error::MissingNode { way: 20, node: 99 })));
+    }
+
+    #[test]
+    fn from_xml_with_filter() {
+        let filter = WayFilter::any(vec![
+            WayFilter::highway(&["service"]),
+            WayFilter::tag(TagFilter::equals("building", "yes")),
+        ]);
+        let changes = ChangeSet::from_xml_with_filter(ADIFF.as_bytes(), &filter).expect("Was not able to read the diff!");
+
+        //Way 13 stops being a building, and the footway was never kept
+        let way_ids = |ways: &Vec<OSMWay>| -> Vec<u64> { ways.iter().map(|w| w.id()).collect() };
+        assert_eq!(way_ids(changes.created_ways()), vec![12]);
+        assert!(changes.modified_ways().is_empty());
+        assert_eq!(way_ids(changes.deleted_ways()), vec![13]);
+        assert_eq!(changes.created_nodes().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn engine_way_filter() {
+        let server = MockServer::start(|_, _| Response::text(ADIFF));
+
+        let changes: ChangeSet = common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+            .with_way_filter(WayFilter::highway(&["track"]))
+            .changes_bbox(40.0, -76.1, 40.1, -75.9, "2020-01-01T00:00:00Z".to_string(), None)
+            .await
+            .expect("Was not able to fetch the changes!");
+
+        let way_ids: Vec<u64> = changes.created_ways().iter().map(|w| w.id()).collect();
+        assert_eq!(way_ids, vec![13]);
+        assert!(changes.deleted_ways().is_empty());
+    }
+
+    #[tokio::test]
+    async fn engine() {
+        let server = MockServer::start(|_, _| Response::text(ADIFF));
+
+        let engine = common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+            .with_filters(vec!["residential".to_string()])
+            .with_timeout(600);
+
+        let changes: ChangeSet = engine
+            .changes_bbox(40.0, -76.1, 40.1, -75.9, "2020-01-01T00:00:00Z".to_string(), Some("2021-01-01T00:00:00Z".to_string()))
+            .await
+            .expect("Was not able to fetch the changes!");
+        assert_eq!(changes.created_ways().len(), 2);
+
+        let sent = server.requests()[0].form_field("data").expect("Request should have a data field!");
+        assert!(sent.starts_with("[out:xml][timeout:600][adiff:\"2020-01-01T00:00:00Z\",\"2021-01-01T00:00:00Z\"];"));
+        assert!(sent.contains("way[\"highway\"~\"residential\"](40,-76.1,40.1,-75.9);"));
+        assert!(sent.ends_with("(._; >;);\nout meta geom;"));
+    }
+}
@@ -0,0 +1,241 @@
+//! A tiny HTTP server that stands in for Overpass in tests. Each request is answered by a handler
+//! closure, and every request is recorded so that tests can check what the engine sent.
+//!
+//! [`engine`] gives a `QueryEngine` that talks to it over a plain `std` socket, so the tests also
+//! run without the `reqwest` feature.
+
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use osmgraph::api::{HttpRequest, HttpResponse, Method, QueryEngine, Transport, TransportFuture};
+use osmgraph::Error;
+
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub received: Instant,
+}
+
+impl Request {
+    /// Decode a form encoded field of the body, such as the `data` field that holds the query.
+    pub fn form_field(&self, name: &str) -> Option<String> {
+        self.body.split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| decode_form_value(value))
+    }
+
+    /// Get the value of a header, ignoring the case of its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn decode_form_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap();
+                decoded.push(u8::from_str_radix(hex, 16).unwrap());
+                i += 2;
+            },
+            b => decoded.push(b)
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded).unwrap()
+}
+
+#[derive(Clone, Debug)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json(body: &str) -> Self {
+        Response { status: 200, content_type: "application/json".to_string(), body: body.to_string() }
+    }
+    pub fn text(body: &str) -> Self {
+        Response { status: 200, content_type: "text/plain".to_string(), body: body.to_string() }
+    }
+    pub fn status(status: u16) -> Self {
+        Response { status, content_type: "text/html".to_string(), body: format!("<p>{status}</p>") }
+    }
+}
+
+pub struct MockServer {
+    url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+
+    /// Start a server on a free local port. The handler is given every request along with the
+    /// number of requests to the same path that came before it.
+    pub fn start<F>(handler: F) -> Self
+        where F: Fn(&Request, usize) -> Response + Send + 'static {
+
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Could not bind mock server!");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let requests: Arc<Mutex<Vec<Request>>> = Arc::new(Mutex::new(vec![]));
+        let recorded = requests.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let Some(request) = read_request(&mut stream) else { continue };
+
+                let previous = {
+                    let mut recorded = recorded.lock().unwrap();
+                    let previous = recorded.iter().filter(|r| r.path == request.path).count();
+                    recorded.push(request.clone());
+                    previous
+                };
+
+                let response = handler(&request, previous);
+                let _ = write!(stream,
+                    "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.status, response.content_type, response.body.len(), response.body
+                );
+            }
+        });
+
+        MockServer { url, requests }
+    }
+
+    /// The url of the interpreter endpoint on this server.
+    pub fn interpreter_url(&self) -> String {
+        format!("{}/api/interpreter", self.url)
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn requests_to(&self, path: &str) -> Vec<Request> {
+        self.requests().into_iter().filter(|r| r.path == path).collect()
+    }
+}
+
+fn read_request(stream: &mut std::net::TcpStream) -> Option<Request> {
+
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut content_length: usize = 0;
+    let mut headers: Vec<(String, String)> = vec![];
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().ok()?;
+            }
+            headers.push((name.to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+        received: Instant::now(),
+    })
+}
+
+/// Sends requests over a `std` socket, one connection per request. Only enough of HTTP/1.1 for
+/// local servers that close the connection after answering, such as [`MockServer`] and the
+/// emulator.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdTransport;
+
+impl StdTransport {
+    fn send_blocking(request: &HttpRequest) -> std::io::Result<HttpResponse> {
+
+        let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string());
+
+        let rest: &str = request.url().strip_prefix("http://").ok_or_else(|| invalid("only http urls are supported"))?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/")
+        };
+
+        let mut stream = TcpStream::connect(host)?;
+        let method: &str = match request.method() {
+            Method::Get => "GET",
+            Method::Post => "POST"
+        };
+        let mut head: String = format!("{method} {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\nContent-Length: {}\r\n", request.body().len());
+        for (name, value) in request.headers() {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(request.body())?;
+
+        let mut response: Vec<u8> = vec![];
+        stream.read_to_end(&mut response)?;
+
+        let end: usize = response.windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .ok_or_else(|| invalid("the response has no end of headers"))?;
+        let head: String = String::from_utf8_lossy(&response[..end]).into_owned();
+        let mut lines = head.split("\r\n");
+        let status: u16 = lines.next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| invalid("the response has no status"))?;
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect();
+
+        Ok(HttpResponse::new(status, headers, response[end + 4..].to_vec()))
+    }
+}
+
+impl Transport for StdTransport {
+    fn send(&self, request: HttpRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || Self::send_blocking(&request))
+                .await
+                .map_err(|e| Error::Transport(Box::new(e)))?
+                .map_err(|e| Error::Transport(Box::new(e)))
+        })
+    }
+}
+
+/// A `QueryEngine` that sends its requests with [`StdTransport`].
+pub fn engine() -> QueryEngine {
+    QueryEngine::from_transport(StdTransport)
+}
@@ -0,0 +1,188 @@
+#[cfg(test)]
+mod emulator {
+
+    use osmgraph::Error;
+    use osmgraph::api::{Element, OverpassResponse, Polygon, QueryEngine};
+    use osmgraph::emulator::Emulator;
+
+    fn emulator() -> Emulator {
+        Emulator::load_blocking("./assets/test.json").expect("Was not able to load the test data!")
+    }
+
+    fn run(emulator: &Emulator, query: &str) -> OverpassResponse {
+        serde_json::from_str(&emulator.run(query).expect("Query failed!")).expect("Could not parse!")
+    }
+
+    fn tag<'a>(element: &'a Element, key: &str) -> Option<&'a str> {
+        match element {
+            Element::Node { tags, .. } | Element::Way { tags, .. } | Element::Relation { tags, .. } => {
+                tags.as_ref().and_then(|tags| tags.get(key))
+            }
+        }
+    }
+
+    #[test]
+    fn highway_filter_and_recursion() {
+        let response = run(&emulator(), r#"
+            [out:json];
+            way["highway"~"^(residential|primary)$"](40.79,-76.87,40.80,-76.85);
+            (._; >;);
+            out body;
+        "#);
+
+        let ways: Vec<&Element> = response.elements().iter()
+            .filter(|e| matches!(e, Element::Way { .. }))
+            .collect();
+        assert!(!ways.is_empty());
+        assert!(ways.iter().all(|way| matches!(tag(way, "highway"), Some("residential" | "primary"))));
+
+        //Every node of every way was printed, after the ways' nodes in ID order
+        for way in ways {
+            let Element::Way { nodes, .. } = way else { unreachable!() };
+            for node in nodes {
+                assert!(response.elements().iter().any(|e| matches!(e, Element::Node { id, .. } if id == node)));
+            }
+        }
+        assert_eq!(response.osm3s()["timestamp_osm_base"], "2024-09-07T18:21:17Z");
+    }
+
+    #[test]
+    fn areas_from_closed_ways() {
+        let emulator = emulator();
+
+        //Areas made from ways get the ID of the way plus 2400000000
+        let text: String = emulator.run(r#"[out:json]; area["name"="Union Cemetery"]; out;"#).unwrap();
+        assert!(text.contains(r#""id":2463482913"#));
+
+        let inside = run(&emulator, r#"
+            [out:json];
+            area["name"="Union Cemetery"]->.a;
+            node(area.a);
+            out skel;
+        "#);
+        let everything = run(&emulator, "[out:json]; node; out skel;");
+        assert!(!inside.elements().is_empty());
+        assert!(inside.elements().len() < everything.elements().len());
+    }
+
+    #[test]
+    fn around_and_poly() {
+        let emulator = emulator();
+
+        let around = run(&emulator, "[out:json]; node(around:100,40.8091553,-76.8557739); out;");
+        assert!(around.elements().iter().any(|e| e.id() == 26514340));
+        assert!(around.elements().iter().all(|e| match e {
+            Element::Node { lat, .. } => (lat - 40.8091553).abs() < 0.001,
+            _ => false
+        }));
+
+        let poly = run(&emulator, r#"[out:json]; node(poly:"40.809 -76.856 40.810 -76.856 40.810 -76.855 40.809 -76.855"); out;"#);
+        assert!(poly.elements().iter().any(|e| e.id() == 26514340));
+    }
+
+    #[test]
+    fn out_modes() {
+        let emulator = emulator();
+
+        let text: String = emulator.run("[out:json]; way[highway=residential](40.79,-76.87,40.80,-76.85); out ids;").unwrap();
+        assert!(!text.contains("\"nodes\""));
+
+        let text: String = emulator.run("[out:json]; way[highway=residential](40.79,-76.87,40.80,-76.85); out tags geom bb;").unwrap();
+        assert!(text.contains("\"geometry\"") && text.contains("\"bounds\"") && text.contains("\"tags\""));
+    }
+
+    #[test]
+    fn geometry_of_missing_nodes() {
+        let data: OverpassResponse = serde_json::from_str(r#"{"version": 0.6, "generator": "test", "osm3s": {}, "elements": [
+            {"type": "node", "id": 1, "lat": 40.0, "lon": -76.0},
+            {"type": "node", "id": 3, "lat": 40.002, "lon": -76.0},
+            {"type": "way", "id": 10, "nodes": [1, 2, 3], "tags": {"highway": "residential"}}
+        ]}"#).unwrap();
+
+        //Node 2 is not in the data, so its place in the geometry is empty
+        let response = run(&Emulator::from_response(&data), "[out:json]; way[highway=residential](39.0,-77.0,41.0,-75.0); out geom;");
+        match &response.elements()[0] {
+            Element::Way { geometry: Some(geometry), .. } => {
+                assert_eq!(geometry.len(), 3);
+                assert_eq!(geometry[1], None);
+                assert_eq!(geometry[2].map(|point| point.lat()), Some(40.002));
+            },
+            other => panic!("Expected a way with geometry, got {other:?}")
+        }
+    }
+
+    #[test]
+    fn osm_xml() {
+        let path = std::env::temp_dir().join("osmgraph_emulator.osm");
+        std::fs::write(&path, r#"<?xml version="1.0" encoding="UTF-8"?>
+            <osm version="0.6">
+              <meta osm_base="2024-01-01T00:00:00Z"/>
+              <node id="1" lat="40.0" lon="-76.0" version="2"/>
+              <node id="2" lat="40.0" lon="-75.0"/>
+              <node id="3" lat="41.0" lon="-75.0"/>
+              <node id="4" lat="40.4" lon="-75.2"><tag k="amenity" v="cafe"/></node>
+              <way id="10"><nd ref="1"/><nd ref="2"/><tag k="highway" v="primary"/></way>
+              <way id="11"><nd ref="2"/><nd ref="3"/><nd ref="1"/></way>
+              <relation id="20">
+                <member type="way" ref="10" role="outer"/>
+                <member type="way" ref="11" role="outer"/>
+                <tag k="type" v="boundary"/>
+                <tag k="name" v="Triangle"/>
+              </relation>
+            </osm>"#).unwrap();
+
+        let emulator = Emulator::load_blocking(path.to_str().unwrap()).expect("Was not able to load xml!");
+
+        let response = run(&emulator, r#"
+            [out:json];
+            area[name=Triangle]->.searchArea;
+            node[amenity](area.searchArea);
+            out meta;
+        "#);
+        assert_eq!(response.elements().len(), 1);
+        assert_eq!(response.elements()[0].id(), 4);
+        assert_eq!(response.osm3s()["timestamp_osm_base"], "2024-01-01T00:00:00Z");
+
+        let up = run(&emulator, "[out:json]; node(40.9,-75.1,41.1,-74.9); <; out skel;");
+        let ids: Vec<u64> = up.elements().iter().map(|e| e.id()).collect();
+        assert_eq!(ids, vec![11, 20]);
+    }
+
+    #[test]
+    fn unsupported_queries() {
+        let emulator = emulator();
+
+        for query in ["way(1); out;", "[out:xml]; way; out;", "[out:json][date:\"2020-01-01T00:00:00Z\"]; way; out;"] {
+            assert!(matches!(emulator.run(query), Err(Error::Overpass(message)) if message.contains("parse error")));
+        }
+    }
+
+    #[test]
+    fn serve() {
+        let boundary = Polygon::new(vec![(40.78, -76.88), (40.81, -76.88), (40.81, -76.84), (40.78, -76.84)])
+            .expect("Polygon should be valid!");
+        let server = emulator()
+            .with_area([("name", "Selinsgrove")].into_iter().collect(), boundary)
+            .serve()
+            .expect("Was not able to start the emulator!");
+
+        let engine = QueryEngine::new()
+            .with_url(server.url())
+            .with_filters(vec!["residential".to_string()]);
+
+        let graph = engine.graph_from_place_blocking("Selinsgrove".to_string(), None)
+            .expect("Was not able to build a graph!");
+        assert!(graph.node_count() > 0);
+
+        let response = engine.fetch_bbox_blocking(40.79, -76.87, 40.80, -76.85)
+            .expect("Was not able to query the emulator!");
+        assert!(response.elements().iter()
+            .filter(|e| matches!(e, Element::Way { .. }))
+            .all(|way| tag(way, "highway") == Some("residential")));
+
+        match engine.query_blocking("[out:json]; way(1); out;".to_string()) {
+            Err(Error::HttpStatus { status: 400, body }) => assert!(body.contains("parse error")),
+            other => panic!("expected a parse error, got {other:?}")
+        }
+    }
+}
@@ -24,3 +24,118 @@ mod create_graph {
         assert!(graph.raw_edges()[0].weight.highway_type() != "");
     }
 }
+
+#[cfg(test)]
+mod create_graph_from_geometry {
+
+    use osmgraph::api::OverpassResponse;
+    use osmgraph::graph::create_graph;
+
+    #[test]
+    fn test_create_graph_from_geometry() {
+
+        //Response to a `way[highway](...); out geom;` query. There are no node elements.
+        let json: OverpassResponse = serde_json::from_str(r#"{
+            "version": 0.6,
+            "generator": "Overpass API",
+            "osm3s": {},
+            "elements": [
+                {
+                    "type": "way", "id": 10, "nodes": [1, 2, 3],
+                    "geometry": [
+                        { "lat": 40.0, "lon": -76.0 },
+                        { "lat": 40.001, "lon": -76.0 },
+                        { "lat": 40.002, "lon": -76.0 }
+                    ],
+                    "tags": { "highway": "residential" }
+                },
+                {
+                    "type": "way", "id": 11, "nodes": [3, 4],
+                    "geometry": [
+                        { "lat": 40.002, "lon": -76.0 },
+                        { "lat": 40.002, "lon": -76.001 }
+                    ],
+                    "tags": { "highway": "service" }
+                }
+            ]
+        }"#).expect("Was not able to parse json!");
+
+        let graph = create_graph(json.elements())
+            .expect("Was unable to parse graph!");
+
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 3);
+
+        let dist: f64 = graph.raw_edges()[0].weight.dist();
+        assert!((dist - 111.2).abs() < 0.5);
+    }
+}
+
+#[cfg(test)]
+mod tags {
+
+    use std::sync::Arc;
+
+    use osmgraph::api::OverpassResponse;
+    use osmgraph::graph::{OSMEdge, create_graph};
+
+    #[test]
+    fn test_edge_tags() {
+
+        let json: OverpassResponse = OverpassResponse::load_blocking("./assets/test.json")
+            .expect("Was not able to load json!");
+
+        let graph = create_graph(json.elements())
+            .expect("Was unable to parse graph!");
+
+        for edge in graph.raw_edges() {
+            assert_eq!(edge.weight.tags().get("highway"), Some(edge.weight.highway_type()));
+        }
+    }
+
+    #[test]
+    fn test_shared_edge_tags() {
+
+        let json: OverpassResponse = OverpassResponse::load_blocking("./assets/test.json")
+            .expect("Was not able to load json!");
+
+        let graph = create_graph(json.elements())
+            .expect("Was unable to parse graph!");
+
+        //Edges of the same way point to the same tags instead of copies
+        let edges: Vec<&OSMEdge> = graph.edge_weights().collect();
+        for (a, b) in edges.iter().zip(edges.iter().skip(1)) {
+            assert_eq!(a.way_id() == b.way_id(), Arc::ptr_eq(a.shared_tags(), b.shared_tags()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod errors {
+
+    use osmgraph::api::OverpassResponse;
+    use osmgraph::graph::create_graph;
+    use osmgraph::Error;
+
+    #[test]
+    fn test_missing_node() {
+
+        let json: OverpassResponse = serde_json::from_str(r#"{
+            "version": 0.6,
+            "generator": "Overpass API",
+            "osm3s": {},
+            "elements": [
+                { "type": "node", "id": 1, "lat": 40.0, "lon": -76.0 },
+                { "type": "way", "id": 10, "nodes": [1, 2], "tags": { "highway": "residential" } }
+            ]
+        }"#).expect("Was not able to parse json!");
+
+        match create_graph(json.elements()) {
+            Err(Error::MissingNode { way, node }) => {
+                assert_eq!(way, 10);
+                assert_eq!(node, 2);
+            },
+            other => panic!("Expected a missing node error, got {other:?}")
+        }
+    }
+}
@@ -0,0 +1,72 @@
+mod common;
+
+#[cfg(test)]
+mod network_type {
+
+    use osmgraph::api::{NetworkType, OverpassResponse};
+    #[cfg(feature = "emulator")]
+    use osmgraph::api::WayFilter;
+    #[cfg(feature = "emulator")]
+    use osmgraph::emulator::Emulator;
+    use osmgraph::graph::{OSMGraph, create_graph_with_filter};
+
+    #[cfg(feature = "emulator")]
+    use crate::common;
+
+    fn graph(network_type: NetworkType) -> OSMGraph {
+        let response = OverpassResponse::load_blocking("./assets/test.json").expect("Was not able to load json!");
+        create_graph_with_filter(response.elements(), &network_type.way_filter()).expect("Was unable to parse graph!")
+    }
+
+    fn has(graph: &OSMGraph, highway: &str) -> bool {
+        graph.edge_weights().any(|edge| edge.highway_type() == highway)
+    }
+
+    #[test]
+    fn presets() {
+        let drive = graph(NetworkType::Drive);
+        assert!(has(&drive, "residential") && has(&drive, "motorway"));
+        assert!(!has(&drive, "footway") && !has(&drive, "service"));
+
+        let service = graph(NetworkType::Service);
+        assert!(has(&service, "residential") && has(&service, "service"));
+        assert!(service.edge_weights().all(|edge| {
+            !matches!(edge.tags().get("service"), Some("parking_aisle" | "driveway" | "emergency_access"))
+        }));
+
+        let walk = graph(NetworkType::Walk);
+        assert!(has(&walk, "footway") && has(&walk, "path") && has(&walk, "residential"));
+        assert!(!has(&walk, "motorway") && !has(&walk, "motorway_link"));
+
+        let bike = graph(NetworkType::Bike);
+        assert!(has(&bike, "path") && has(&bike, "residential"));
+        assert!(!has(&bike, "footway") && !has(&bike, "motorway"));
+
+        //Nothing private, and no areas, in any network
+        let all = graph(NetworkType::All);
+        assert!(has(&all, "footway") && has(&all, "motorway") && has(&all, "service"));
+        for graph in [&drive, &service, &walk, &bike, &all] {
+            assert!(graph.edge_weights().all(|edge| {
+                edge.tags().get("access") != Some("private") && edge.tags().get("area") != Some("yes")
+            }));
+            assert!(graph.edge_count() <= all.edge_count());
+        }
+    }
+
+    #[cfg(feature = "emulator")]
+    #[test]
+    fn engine() {
+        let response = OverpassResponse::load_blocking("./assets/test.json").expect("Was not able to load json!");
+        let server = Emulator::from_response(&response).serve().expect("Was not able to start the emulator!");
+
+        let engine = common::engine()
+            .with_url(server.url())
+            .with_network_type(NetworkType::Walk);
+        assert_eq!(engine.way_filter(), Some(&WayFilter::from(NetworkType::Walk)));
+
+        let graph: OSMGraph = engine.graph_from_bbox_blocking(40.70, -76.95, 40.90, -76.75)
+            .expect("Was not able to build a graph!");
+        assert!(has(&graph, "footway"));
+        assert!(!has(&graph, "motorway"));
+    }
+}
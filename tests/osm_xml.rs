@@ -0,0 +1,173 @@
+#[cfg(test)]
+mod osm_xml {
+
+    use std::io::BufReader;
+
+    use osmgraph::api::{Element, OsmXmlReader, OverpassResponse};
+    use osmgraph::graph::{OSMGraph, create_graph, create_graph_from_stream};
+    use osmgraph::Error;
+
+    const JOSM: &str = r#"<?xml version='1.0' encoding='UTF-8'?>
+<osm version='0.6' upload='false' generator='JOSM'>
+  <bounds minlat='39.9' minlon='-76.1' maxlat='40.1' maxlon='-75.9' origin='CGImap 0.9.3' />
+  <node id='1' timestamp='2024-01-01T00:00:00Z' uid='7' user='mapper' visible='true' version='2' changeset='100' lat='40.0' lon='-76.0'>
+    <tag k='highway' v='crossing' />
+  </node>
+  <node id='2' visible='true' version='1' lat='40.001' lon='-76.0' />
+  <node id='3' action='delete' visible='true' version='1' lat='40.002' lon='-76.0' />
+  <way id='10' visible='true' version='4' user='mapper'>
+    <nd ref='1' />
+    <nd ref='2' />
+    <tag k='highway' v='residential' />
+    <tag k='name' v='Main &amp; Front' />
+  </way>
+  <relation id='100' version='1'>
+    <member type='way' ref='10' role='outer' />
+    <tag k='type' v='route' />
+  </relation>
+</osm>"#;
+
+    const JSON: &str = r#"{"version": 0.6, "generator": "JOSM", "osm3s": {}, "elements": [
+        {"type": "node", "id": 1, "lat": 40.0, "lon": -76.0, "tags": {"highway": "crossing"},
+         "timestamp": "2024-01-01T00:00:00Z", "version": 2, "changeset": 100, "user": "mapper", "uid": 7},
+        {"type": "node", "id": 2, "lat": 40.001, "lon": -76.0, "version": 1},
+        {"type": "way", "id": 10, "nodes": [1, 2], "tags": {"highway": "residential", "name": "Main & Front"},
+         "version": 4, "user": "mapper"},
+        {"type": "relation", "id": 100, "members": [{"type": "way", "ref": 10, "role": "outer"}],
+         "tags": {"type": "route"}, "version": 1}
+    ]}"#;
+
+    #[test]
+    fn same_as_json() {
+
+        let xml = OverpassResponse::from_xml(JOSM.as_bytes()).expect("Xml should parse!");
+        let json: OverpassResponse = serde_json::from_str(JSON).unwrap();
+
+        assert_eq!(xml, json);
+        assert_eq!(create_graph(xml.elements()).unwrap().edge_count(), 1);
+    }
+
+    #[test]
+    fn overpass_output() {
+
+        let xml = br#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6" generator="Overpass API 0.7.62">
+<note>The data included in this document is from www.openstreetmap.org.</note>
+<meta osm_base="2024-11-18T00:00:00Z"/>
+  <way id="10">
+    <bounds minlat="40.0" minlon="-76.0" maxlat="40.001" maxlon="-76.0"/>
+    <nd ref="1" lat="40.0" lon="-76.0"/>
+    <nd ref="2" lat="40.001" lon="-76.0"/>
+    <tag k="highway" v="service"/>
+  </way>
+  <relation id="100">
+    <center lat="40.0005" lon="-76.0"/>
+  </relation>
+<remark> runtime error: Query timed out in "query" at line 3 after 26 seconds. </remark>
+</osm>"#;
+
+        let response = OverpassResponse::from_xml(xml).expect("Xml should parse!");
+        assert_eq!(response.version(), 0.6);
+        assert_eq!(response.generator(), "Overpass API 0.7.62");
+        assert_eq!(response.osm3s()["timestamp_osm_base"], "2024-11-18T00:00:00Z");
+        assert_eq!(response.remark(), Some(r#"runtime error: Query timed out in "query" at line 3 after 26 seconds."#));
+        assert!(!response.is_complete());
+
+        match &response.elements()[0] {
+            Element::Way { bounds, geometry, .. } => {
+                assert_eq!(bounds.unwrap().maxlat(), 40.001);
+                assert_eq!(geometry.as_ref().unwrap().len(), 2);
+            },
+            other => panic!("Expected a way, got {other:?}")
+        }
+        match &response.elements()[1] {
+            Element::Relation { center, .. } => assert_eq!(center.unwrap().lat(), 40.0005),
+            other => panic!("Expected a relation, got {other:?}")
+        }
+
+        //The nodes only come as geometry
+        let graph: OSMGraph = create_graph(response.elements()).unwrap();
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.edge_count(), 1);
+
+        //The reader ends with the runtime error, after the elements
+        let mut results: Vec<Result<Element, Error>> = OsmXmlReader::new(&xml[..]).collect();
+        match results.pop() {
+            Some(Err(Error::Incomplete { remark, body })) => {
+                assert!(remark.starts_with("runtime error: Query timed out"));
+                assert!(body.is_empty());
+            },
+            other => panic!("Expected an incomplete response, got {other:?}")
+        }
+        assert_eq!(results.len(), 2);
+        assert!(matches!(create_graph_from_stream(OsmXmlReader::new(&xml[..])), Err(Error::Incomplete { .. })));
+    }
+
+    #[test]
+    fn reader() {
+
+        //Read a byte at a time, so every event is split between reads
+        let mut reader = OsmXmlReader::new(BufReader::with_capacity(1, JOSM.as_bytes()));
+
+        let elements: Vec<Element> = reader.by_ref()
+            .collect::<Result<_, _>>()
+            .expect("Elements should be read!");
+        let json: OverpassResponse = serde_json::from_str(JSON).unwrap();
+        assert_eq!(&elements, json.elements());
+
+        assert_eq!(reader.version(), Some("0.6"));
+        assert_eq!(reader.generator(), Some("JOSM"));
+        assert_eq!(reader.osm_base(), None);
+    }
+
+    #[test]
+    fn file() {
+
+        let path = std::env::temp_dir().join("osmgraph_osm_xml.osm");
+        std::fs::write(&path, JOSM).unwrap();
+        let path = path.to_str().unwrap();
+
+        let graph: OSMGraph = create_graph_from_stream(OsmXmlReader::open(path).unwrap())
+            .expect("Graph should be created!");
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.edge_count(), 1);
+
+        let response = OverpassResponse::load_xml_blocking(path).expect("File should load!");
+        assert_eq!(response.elements().len(), 4);
+    }
+
+    #[test]
+    fn errors() {
+
+        //Objects that were never uploaded have negative IDs
+        let xml = "<osm>\n  <node id='1' lat='40.0' lon='-76.0'/>\n  <node id='-1' lat='40.0' lon='-76.0'/>\n</osm>";
+        let results: Vec<Result<Element, Error>> = OsmXmlReader::new(xml.as_bytes()).collect();
+        assert_eq!(results.len(), 2);
+        match &results[1] {
+            Err(Error::Parse { message, line, column }) => {
+                assert!(message.contains("`id`"));
+                assert_eq!((*line, *column), (3, 3));
+            },
+            other => panic!("Expected a parse error, got {other:?}")
+        }
+
+        let xml = "<osm>\n  <way id='1'>\n    <nd ref='1'/>\n  </node>\n</osm>";
+        match OverpassResponse::from_xml(xml.as_bytes()) {
+            Err(Error::Parse { line, .. }) => assert_eq!(line, 4),
+            other => panic!("Expected a parse error, got {other:?}")
+        }
+
+        //Documents that end too soon, inside an element, an action or the root
+        let documents = [
+            "<osm>\n  <way id='1'>\n    <nd ref='1'/>",
+            "<osmAugmentedDiff>\n  <action type='create'>\n    <node id='1' lat='40.0' lon='-76.0'/>",
+            "<osm>\n  <node id='1' lat='40.0' lon='-76.0'/>\n"
+        ];
+        for xml in documents {
+            match OverpassResponse::from_xml(xml.as_bytes()) {
+                Err(Error::Parse { message, .. }) => assert!(message.contains("is not closed"), "{message}"),
+                other => panic!("Expected a parse error, got {other:?}")
+            }
+        }
+    }
+}
@@ -1,3 +1,28 @@
+//! These tests run against the emulator, loaded with the data in `assets/test.json`, so that they
+//! do not depend on the public Overpass API.
+
+use osmgraph::api::{OverpassResponse, Polygon};
+use osmgraph::emulator::{Emulator, EmulatorServer};
+
+/// Serve the test data, with the boundary of Selinsgrove added since it is not part of the data.
+/// The file is read without tokio, since this is called from both sync and async tests.
+fn serve() -> EmulatorServer {
+    let data: Vec<u8> = std::fs::read("./assets/test.json").expect("Was not able to read the test data!");
+    let response: OverpassResponse = serde_json::from_slice(&data).expect("Could not parse the test data!");
+
+    let boundary = Polygon::new(vec![
+        (40.776, -76.884),
+        (40.815, -76.884),
+        (40.815, -76.840),
+        (40.776, -76.840),
+    ]).expect("Polygon should be valid!");
+
+    Emulator::from_response(&response)
+        .with_area([("name", "Selinsgrove"), ("admin_level", "8")].into_iter().collect(), boundary)
+        .serve()
+        .expect("Was not able to start the emulator!")
+}
+
 #[cfg(test)]
 mod query {
 
@@ -6,7 +31,8 @@ mod query {
     #[tokio::test]
     async fn query() {
 
-        let engine = QueryEngine::new();
+        let server = super::serve();
+        let engine = QueryEngine::new().with_url(server.url());
 
         let response: String = engine.query(r#"
             [out:json];
@@ -21,41 +47,42 @@ mod query {
         "#.to_string()
         ).await.expect("OSM request failed!");
 
-        assert!(response.len() > 0);
+        assert!(!response.is_empty());
 
         let next_response: String = engine
             .query_place("Selinsgrove".to_string(), None)
             .await
             .expect("OSM request failed!");
 
-        assert!(next_response.len() > 0);
+        assert!(!next_response.is_empty());
 
         let third_response: String = engine
             .query_place("Selinsgrove".to_string(), Some(8))
             .await
             .expect("OSM request failed!");
 
-        assert!(third_response.len() > 0);
+        assert!(!third_response.is_empty());
 
         let fourth_response: String = engine
             .query_poly(vec![
-                (32.407, -64.896),
-                (32.407, -64.630),
-                (32.224, -64.630),
-                (32.224, -64.896),
-                (32.407, -64.896),
+                (40.807, -76.873),
+                (40.807, -76.851),
+                (40.785, -76.851),
+                (40.785, -76.873),
+                (40.807, -76.873),
             ])
             .await
             .expect("OSM request failed!");
 
-        assert!(fourth_response.len() > 0);
+        assert!(!fourth_response.is_empty());
     }
 
 
     #[test]
     fn query_blocking() {
 
-        let engine = QueryEngine::new();
+        let server = super::serve();
+        let engine = QueryEngine::new().with_url(server.url());
 
         let response: String = engine.query_blocking(r#"
             [out:json];
@@ -69,31 +96,31 @@ mod query {
             out skel qt;
         "#.to_string()).expect("OSM request failed!");
 
-        assert!(response.len() > 0);
+        assert!(!response.is_empty());
 
         let next_response: String = engine
             .query_place_blocking("Selinsgrove".to_string(), None)
             .expect("OSM request failed!");
 
-        assert!(next_response.len() > 0);
+        assert!(!next_response.is_empty());
 
         let third_response: String = engine
             .query_place_blocking("Selinsgrove".to_string(), Some(8))
             .expect("OSM request failed!");
 
-        assert!(third_response.len() > 0);
+        assert!(!third_response.is_empty());
 
         let fourth_response: String = engine
             .query_poly_blocking(vec![
-                (32.407, -64.896),
-                (32.407, -64.630),
-                (32.224, -64.630),
-                (32.224, -64.896),
-                (32.407, -64.896),
+                (40.807, -76.873),
+                (40.807, -76.851),
+                (40.785, -76.851),
+                (40.785, -76.873),
+                (40.807, -76.873),
             ])
             .expect("OSM request failed!");
 
-        assert!(fourth_response.len() > 0);
+        assert!(!fourth_response.is_empty());
     }
 }
 
@@ -101,13 +128,14 @@ mod query {
 mod parse {
 
     use osmgraph::api::{QueryEngine, OverpassResponse};
-    
+
     use serde_json::json;
 
     #[tokio::test]
     async fn parse() {
 
-        let engine = QueryEngine::new();
+        let server = super::serve();
+        let engine = QueryEngine::new().with_url(server.url());
 
         let response: String = engine.query(r#"
             [out:json];
@@ -124,7 +152,7 @@ mod parse {
         let json: OverpassResponse = serde_json::from_str(&response)
             .expect("Could not parse!");
 
-        assert!(json.elements().len() > 0);
+        assert!(!json.elements().is_empty());
         assert!(*json.generator() != json!(null));
         assert!(*json.osm3s()     != json!(null));
         assert!(*json.version()   != json!(null));
@@ -139,7 +167,8 @@ mod save_load {
     #[tokio::test]
     async fn save_load() {
 
-        let engine = QueryEngine::new();
+        let server = super::serve();
+        let engine = QueryEngine::new().with_url(server.url());
 
         let response: String = engine.query(r#"
             [out:json];
@@ -156,19 +185,25 @@ mod save_load {
         let json: OverpassResponse = serde_json::from_str(&response)
             .expect("Could not parse");
 
-        json.save("./assets/test.json")
+        let path = std::env::temp_dir().join("osmgraph_save_load.json");
+        let path: &str = path.to_str().unwrap();
+
+        json.save(path)
             .await
             .expect("Was not able to save json!");
 
-        let _: OverpassResponse = OverpassResponse::load("./assets/test.json")
+        let loaded: OverpassResponse = OverpassResponse::load(path)
             .await
             .expect("Was not able to load json!");
+
+        assert_eq!(loaded, json);
     }
-    
+
     #[test]
     fn save_load_blocking() {
-        
-        let engine = QueryEngine::new();
+
+        let server = super::serve();
+        let engine = QueryEngine::new().with_url(server.url());
 
         let response: String = engine.query_blocking(r#"
             [out:json];
@@ -181,14 +216,19 @@ mod save_load {
             >;
             out skel qt;
         "#.to_string()).expect("OSM request failed!");
-        
+
         let json: OverpassResponse = serde_json::from_str(&response)
             .expect("Could not parse");
 
-        json.save_blocking("./assets/test.json")
+        let path = std::env::temp_dir().join("osmgraph_save_load_blocking.json");
+        let path: &str = path.to_str().unwrap();
+
+        json.save_blocking(path)
             .expect("Was not able to save json!");
 
-        let _: OverpassResponse = OverpassResponse::load_blocking("./assets/test.json")
+        let loaded: OverpassResponse = OverpassResponse::load_blocking(path)
             .expect("Was not able to load json!");
+
+        assert_eq!(loaded, json);
     }
 }
@@ -0,0 +1,303 @@
+#[cfg(test)]
+mod relation {
+
+    use osmgraph::api::{OverpassResponse, Element, MemberType};
+
+    const RESPONSE: &str = r#"{
+        "version": 0.6,
+        "generator": "Overpass API",
+        "osm3s": { "timestamp_osm_base": "2024-11-18T00:00:00Z" },
+        "elements": [
+            { "type": "node", "id": 1, "lat": 40.0, "lon": -76.0 },
+            { "type": "way", "id": 10, "nodes": [1, 2], "tags": { "highway": "residential" } },
+            {
+                "type": "relation",
+                "id": 100,
+                "members": [
+                    { "type": "way", "ref": 10, "role": "from" },
+                    { "type": "node", "ref": 1, "role": "via" },
+                    { "type": "way", "ref": 11, "role": "to" }
+                ],
+                "tags": { "type": "restriction", "restriction": "no_left_turn" }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn parse_relation() {
+
+        let json: OverpassResponse = serde_json::from_str(RESPONSE)
+            .expect("Could not parse!");
+
+        let relation = json.elements().iter()
+            .find(|e| matches!(e, Element::Relation { .. }))
+            .expect("Relation was not parsed!");
+
+        if let Element::Relation { id, members, tags, .. } = relation {
+            assert_eq!(*id, 100);
+            assert_eq!(members.len(), 3);
+            assert_eq!(members[1].member_type(), MemberType::Node);
+            assert_eq!(members[1].reference(), 1);
+            assert_eq!(members[1].role(), "via");
+            assert_eq!(tags.as_ref().unwrap().get("restriction"), Some("no_left_turn"));
+        }
+    }
+
+    #[test]
+    fn save_load_relation() {
+
+        let json: OverpassResponse = serde_json::from_str(RESPONSE)
+            .expect("Could not parse!");
+
+        let filepath = std::env::temp_dir().join("osmgraph_relation_test.json");
+        let filepath = filepath.to_str().unwrap();
+
+        json.save_blocking(filepath)
+            .expect("Was not able to save json!");
+        let loaded = OverpassResponse::load_blocking(filepath)
+            .expect("Was not able to load json!");
+
+        assert_eq!(json, loaded);
+    }
+}
+
+#[cfg(test)]
+mod lenient {
+
+    use osmgraph::api::{OverpassResponse, Element};
+
+    const RESPONSE: &str = r#"{
+        "version": 0.6,
+        "generator": "Overpass API",
+        "osm3s": { "timestamp_osm_base": "2024-11-18T00:00:00Z" },
+        "elements": [
+            { "type": "node", "id": 1, "lat": 40.0, "lon": -76.0 },
+            { "type": "area", "id": 3600000001, "tags": { "name": "Selinsgrove" } },
+            { "type": "way", "id": 10, "tags": { "highway": "residential" } },
+            { "type": "count", "id": 0, "tags": { "nodes": "1" } },
+            { "id": 2, "lat": 40.0, "lon": -76.0 },
+            { "type": "way", "id": 11, "nodes": [1, 2] }
+        ]
+    }"#;
+
+    #[test]
+    fn strict_parse_fails() {
+        assert!(serde_json::from_str::<OverpassResponse>(RESPONSE).is_err());
+    }
+
+    #[test]
+    fn lenient_parse() {
+
+        let (json, diagnostics) = OverpassResponse::from_str_lenient(RESPONSE)
+            .expect("Could not parse!");
+
+        assert_eq!(json.elements().len(), 2);
+        assert!(matches!(json.elements()[1], Element::Way { id: 11, .. }));
+        assert_eq!(json.osm3s()["timestamp_osm_base"], "2024-11-18T00:00:00Z");
+
+        let indices: Vec<usize> = diagnostics.iter().map(|d| d.index()).collect();
+        assert_eq!(indices, vec![1, 2, 3, 4]);
+
+        assert_eq!(diagnostics[0].reason(), "unknown element type `area`");
+        assert!(diagnostics[1].reason().contains("nodes"));
+        assert_eq!(diagnostics[3].reason(), "element has no `type` field");
+    }
+
+    #[test]
+    fn lenient_parse_not_json() {
+        assert!(OverpassResponse::from_str_lenient("<html></html>").is_err());
+    }
+}
+
+#[cfg(test)]
+mod metadata {
+
+    use osmgraph::api::{OverpassResponse, Element};
+    use osmgraph::graph::{get_osm_nodes, way::get_osm_ways};
+
+    const RESPONSE: &str = r#"{
+        "version": 0.6,
+        "generator": "Overpass API",
+        "osm3s": { "timestamp_osm_base": "2024-11-18T00:00:00Z" },
+        "elements": [
+            {
+                "type": "node", "id": 1, "lat": 40.0, "lon": -76.0,
+                "timestamp": "2019-03-02T17:22:41Z", "version": 4, "changeset": 67752519,
+                "user": "mapper", "uid": 1234
+            },
+            { "type": "node", "id": 2, "lat": 40.1, "lon": -76.1 },
+            {
+                "type": "way", "id": 10, "nodes": [1, 2], "tags": { "highway": "residential" },
+                "timestamp": "2012-01-15T04:26:02Z", "version": 2, "changeset": 10426187,
+                "user": "other_mapper", "uid": 5678
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn parse_metadata() {
+
+        let json: OverpassResponse = serde_json::from_str(RESPONSE)
+            .expect("Could not parse!");
+
+        if let Element::Node { meta, .. } = &json.elements()[0] {
+            assert_eq!(meta.version(), Some(4));
+            assert_eq!(meta.timestamp(), Some("2019-03-02T17:22:41Z"));
+            assert_eq!(meta.changeset(), Some(67752519));
+            assert_eq!(meta.user(), Some("mapper"));
+            assert_eq!(meta.uid(), Some(1234));
+        } else {
+            panic!("First element should be a node!");
+        }
+
+        if let Element::Node { meta, .. } = &json.elements()[1] {
+            assert!(meta.is_empty());
+        }
+
+        let nodes = get_osm_nodes(json.elements()).expect("Could not get nodes!");
+        assert_eq!(nodes[0].meta().user(), Some("mapper"));
+        assert!(nodes[1].meta().is_empty());
+
+        let ways = get_osm_ways(json.elements()).expect("Could not get ways!");
+        assert_eq!(ways[0].meta().version(), Some(2));
+        assert_eq!(ways[0].meta().uid(), Some(5678));
+    }
+
+    #[test]
+    fn save_load_metadata() {
+
+        let json: OverpassResponse = serde_json::from_str(RESPONSE)
+            .expect("Could not parse!");
+
+        let filepath = std::env::temp_dir().join("osmgraph_metadata_test.json");
+        let filepath = filepath.to_str().unwrap();
+
+        json.save_blocking(filepath)
+            .expect("Was not able to save json!");
+        let loaded = OverpassResponse::load_blocking(filepath)
+            .expect("Was not able to load json!");
+
+        assert_eq!(json, loaded);
+
+        //Elements without metadata should not gain empty fields when saved
+        let saved = std::fs::read_to_string(filepath).unwrap();
+        assert_eq!(saved.matches("changeset").count(), 2);
+    }
+}
+
+#[cfg(test)]
+mod geometry {
+
+    use osmgraph::api::{OverpassResponse, Element};
+
+    const RESPONSE: &str = r#"{
+        "version": 0.6,
+        "generator": "Overpass API",
+        "osm3s": { "timestamp_osm_base": "2024-11-18T00:00:00Z" },
+        "elements": [
+            {
+                "type": "way", "id": 10, "nodes": [1, 2, 3],
+                "bounds": { "minlat": 40.0, "minlon": -76.2, "maxlat": 40.2, "maxlon": -76.0 },
+                "geometry": [
+                    { "lat": 40.0, "lon": -76.0 },
+                    { "lat": 40.1, "lon": -76.1 },
+                    null
+                ],
+                "tags": { "highway": "residential" }
+            },
+            {
+                "type": "relation", "id": 100, "members": [],
+                "center": { "lat": 40.05, "lon": -76.05 },
+                "tags": { "type": "route" }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn parse_geometry() {
+
+        let json: OverpassResponse = serde_json::from_str(RESPONSE)
+            .expect("Could not parse!");
+
+        if let Element::Way { bounds, geometry, center, .. } = &json.elements()[0] {
+            assert_eq!(bounds.unwrap().maxlon(), -76.0);
+            assert!(center.is_none());
+
+            let geometry = geometry.as_ref().unwrap();
+            assert_eq!(geometry.len(), 3);
+            assert_eq!(geometry[1].unwrap().lat(), 40.1);
+            assert!(geometry[2].is_none());
+        } else {
+            panic!("First element should be a way!");
+        }
+
+        if let Element::Relation { center, .. } = &json.elements()[1] {
+            assert_eq!(center.unwrap().lon(), -76.05);
+        } else {
+            panic!("Second element should be a relation!");
+        }
+
+        //Round trip
+        let saved = serde_json::to_string(&json).unwrap();
+        let loaded: OverpassResponse = serde_json::from_str(&saved).unwrap();
+        assert_eq!(json, loaded);
+    }
+}
+
+#[cfg(test)]
+mod errors {
+
+    use osmgraph::api::OverpassResponse;
+    use osmgraph::Error;
+
+    #[test]
+    fn parse_error_position() {
+
+        let result = OverpassResponse::from_str_lenient("{\n  \"elements\": [\n    {]\n}");
+
+        match result {
+            Err(Error::Parse { line, column, .. }) => {
+                assert_eq!(line, 3);
+                assert_eq!(column, 6);
+            },
+            other => panic!("Expected a parse error, got {other:?}")
+        }
+    }
+
+    #[test]
+    fn load_missing_file() {
+        let result = OverpassResponse::load_blocking("./assets/does_not_exist.json");
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+}
+
+#[cfg(test)]
+mod remark {
+
+    use osmgraph::api::OverpassResponse;
+
+    #[test]
+    fn incomplete_response() {
+
+        let json: OverpassResponse = serde_json::from_str(r#"{
+            "version": 0.6,
+            "generator": "Overpass API",
+            "osm3s": {},
+            "elements": [ { "type": "node", "id": 1, "lat": 40.0, "lon": -76.0 } ],
+            "remark": "runtime error: Query timed out in \"query\" at line 3 after 26 seconds."
+        }"#).expect("Could not parse!");
+
+        assert!(!json.is_complete());
+        assert!(json.remark().unwrap().contains("timed out"));
+    }
+
+    #[test]
+    fn complete_response() {
+
+        let json: OverpassResponse = OverpassResponse::load_blocking("./assets/test.json")
+            .expect("Was not able to load json!");
+
+        assert!(json.is_complete());
+        assert_eq!(json.remark(), None);
+    }
+}
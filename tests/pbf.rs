@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod pbf {
+
+    use osmgraph::api::{Element, MemberType, PbfReader, RelationMember, WayFilter};
+    use osmgraph::graph::create_graph_from_pbf;
+    use osmgraph::Error;
+
+    //A header block, a block of dense nodes, an unknown blob and a compressed block of ways and
+    //relations. The history file is the same but requires `HistoricalInformation`.
+    const FILE: &str = "./assets/test.osm.pbf";
+    const HISTORY: &str = "./assets/test_history.osm.pbf";
+
+    #[test]
+    fn read() {
+
+        let mut reader = PbfReader::open(FILE).expect("File should open!");
+        let elements: Vec<Element> = reader.by_ref().collect::<Result<_, _>>().expect("File should be read!");
+
+        assert_eq!(reader.writing_program(), Some("osmium/1.16.0"));
+        assert_eq!(reader.replication_timestamp(), Some("2024-11-18T00:00:00Z"));
+        //Node 3 is not visible
+        assert_eq!(elements.iter().map(|element| element.id()).collect::<Vec<_>>(), vec![1, 2, 10, 100]);
+
+        match &elements[0] {
+            Element::Node { lat, lon, tags, meta, .. } => {
+                assert!((lat - 40.0).abs() < 1e-9 && (lon + 76.0).abs() < 1e-9);
+                assert_eq!(tags.as_ref().unwrap().get("highway"), Some("crossing"));
+                assert_eq!(meta.version(), Some(2));
+                assert_eq!(meta.timestamp(), Some("2023-11-14T22:13:20Z"));
+                assert_eq!(meta.changeset(), Some(100));
+                assert_eq!(meta.user(), Some("mapper"));
+                assert_eq!(meta.uid(), Some(7));
+            },
+            other => panic!("Expected a node, got {other:?}")
+        }
+        match &elements[1] {
+            Element::Node { lat, tags, .. } => {
+                assert!((lat - 40.001).abs() < 1e-9);
+                assert!(tags.is_none());
+            },
+            other => panic!("Expected a node, got {other:?}")
+        }
+        match &elements[2] {
+            Element::Way { nodes, tags, .. } => {
+                assert_eq!(nodes, &vec![1, 2]);
+                assert_eq!(tags.as_ref().unwrap().get("name"), Some("Main"));
+            },
+            other => panic!("Expected a way, got {other:?}")
+        }
+        match &elements[3] {
+            Element::Relation { members, .. } => {
+                assert_eq!(members[0], RelationMember::new(MemberType::Way, 10, "from".to_string()));
+                assert_eq!(members[1], RelationMember::new(MemberType::Node, 1, String::new()));
+            },
+            other => panic!("Expected a relation, got {other:?}")
+        }
+    }
+
+    #[test]
+    fn create_graph() {
+
+        let graph = create_graph_from_pbf(FILE, &WayFilter::highway(&["residential"]))
+            .expect("Graph should be created!");
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.edge_count(), 1);
+
+        let graph = create_graph_from_pbf(FILE, &WayFilter::highway(&["service"])).unwrap();
+        assert_eq!(graph.node_count(), 0);
+    }
+
+    #[test]
+    fn errors() {
+
+        let results: Vec<Result<Element, Error>> = PbfReader::open(HISTORY).unwrap().collect();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(&results[0], Err(Error::Parse { message, .. }) if message.contains("HistoricalInformation")));
+
+        //Cut the file in the middle of the last block
+        let file: Vec<u8> = std::fs::read(FILE).unwrap();
+        let results: Vec<Result<Element, Error>> = PbfReader::new(&file[..file.len() - 5]).collect();
+        assert_eq!(results.len(), 3);
+        assert!(matches!(&results[2], Err(Error::Parse { message, .. }) if message.starts_with("block 3")));
+
+        assert!(matches!(PbfReader::open("./assets/missing.osm.pbf"), Err(Error::Io(_))));
+    }
+}
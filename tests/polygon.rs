@@ -0,0 +1,194 @@
+mod common;
+
+#[cfg(test)]
+mod polygon {
+
+    use osmgraph::api::Polygon;
+    use osmgraph::Error;
+
+    const SQUARE: [(f64, f64); 4] = [(40.0, -76.0), (41.0, -76.0), (41.0, -75.0), (40.0, -75.0)];
+
+    #[test]
+    fn closes_open_rings() {
+        let polygon = Polygon::new(SQUARE.to_vec()).expect("Square should be valid!");
+        assert_eq!(polygon.points().len(), 5);
+        assert_eq!(polygon.points()[0], polygon.points()[4]);
+    }
+
+    #[test]
+    fn keeps_closed_rings() {
+        let mut ring = SQUARE.to_vec();
+        ring.push(ring[0]);
+
+        let polygon = Polygon::new(ring.clone()).expect("Square should be valid!");
+        assert_eq!(polygon.points(), &ring);
+    }
+
+    #[test]
+    fn removes_duplicate_points() {
+        let ring = vec![
+            (40.0, -76.0), (40.0, -76.0), (41.0, -76.0), (41.0, -75.0),
+            (41.0, -75.0), (41.0, -75.0), (40.0, -75.0), (40.0, -76.0), (40.0, -76.0)
+        ];
+
+        let polygon = Polygon::new(ring).expect("Square should be valid!");
+        assert_eq!(polygon.points().len(), 5);
+    }
+
+    #[test]
+    fn degenerate_rings() {
+        assert!(matches!(Polygon::new(vec![]), Err(Error::InvalidGeometry(_))));
+        assert!(matches!(Polygon::new(vec![(40.0, -76.0), (41.0, -76.0), (40.0, -76.0)]), Err(Error::InvalidGeometry(_))));
+        assert!(matches!(Polygon::new(vec![(40.0, -76.0); 4]), Err(Error::InvalidGeometry(_))));
+
+        //All points on one line
+        assert!(matches!(
+            Polygon::new(vec![(40.0, -76.0), (40.5, -75.5), (41.0, -75.0)]),
+            Err(Error::InvalidGeometry(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_coordinates() {
+        assert!(Polygon::new(vec![(95.0, -76.0), (41.0, -76.0), (41.0, -75.0)]).is_err());
+        assert!(Polygon::new(vec![(40.0, -190.0), (41.0, -76.0), (41.0, -75.0)]).is_err());
+        assert!(Polygon::new(vec![(f64::NAN, -76.0), (41.0, -76.0), (41.0, -75.0)]).is_err());
+    }
+
+    #[test]
+    fn self_intersections() {
+        //Bow tie
+        assert!(Polygon::new(vec![(40.0, -76.0), (41.0, -75.0), (41.0, -76.0), (40.0, -75.0)]).is_err());
+
+        //A ring that touches itself at one point
+        assert!(Polygon::new(vec![
+            (40.0, -76.0), (41.0, -76.0), (40.5, -75.5), (41.0, -75.0), (40.0, -75.0), (40.5, -75.5)
+        ]).is_err());
+
+        //A spike that folds back along its own edge
+        assert!(Polygon::new(vec![(40.0, -76.0), (42.0, -76.0), (41.0, -76.0), (41.0, -75.0)]).is_err());
+
+        //Concave but simple
+        assert!(Polygon::new(vec![(40.0, -76.0), (41.0, -76.0), (40.5, -75.5), (41.0, -75.0), (40.0, -75.0)]).is_ok());
+    }
+
+    #[test]
+    fn geojson_polygon() {
+        let polygons = Polygon::from_geojson(r#"{
+            "type": "Polygon",
+            "coordinates": [
+                [[-76.0, 40.0], [-76.0, 41.0], [-75.0, 41.0], [-75.0, 40.0], [-76.0, 40.0]],
+                [[-75.8, 40.2], [-75.8, 40.4], [-75.6, 40.4], [-75.8, 40.2]]
+            ]
+        }"#).expect("GeoJSON should be valid!");
+
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].points(), &vec![(40.0, -76.0), (41.0, -76.0), (41.0, -75.0), (40.0, -75.0), (40.0, -76.0)]);
+    }
+
+    #[test]
+    fn geojson_multipolygon_feature() {
+        let polygons = Polygon::from_geojson(r#"{
+            "type": "FeatureCollection",
+            "features": [{
+                "type": "Feature",
+                "properties": { "name": "drawn" },
+                "geometry": {
+                    "type": "MultiPolygon",
+                    "coordinates": [
+                        [[[-76.0, 40.0], [-76.0, 41.0], [-75.0, 41.0]]],
+                        [[[-74.0, 40.0], [-74.0, 41.0], [-73.0, 41.0], [-74.0, 40.0]]]
+                    ]
+                }
+            }]
+        }"#).expect("GeoJSON should be valid!");
+
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(polygons[1].points()[2], (41.0, -73.0));
+    }
+
+    #[test]
+    fn geojson_errors() {
+        assert!(matches!(Polygon::from_geojson("{"), Err(Error::Parse { .. })));
+        assert!(matches!(
+            Polygon::from_geojson(r#"{"type": "Point", "coordinates": [-76.0, 40.0]}"#),
+            Err(Error::InvalidGeometry(_))
+        ));
+        assert!(matches!(
+            Polygon::from_geojson(r#"{"type": "Polygon", "coordinates": [[["a", 40.0]]]}"#),
+            Err(Error::InvalidGeometry(_))
+        ));
+        assert!(matches!(
+            Polygon::from_geojson(r#"{"type": "FeatureCollection", "features": []}"#),
+            Err(Error::InvalidGeometry(_))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod engine_queries {
+
+    use osmgraph::api::RetryPolicy;
+    use osmgraph::Error;
+
+    use crate::common::{self, MockServer, Response};
+
+    const BODY: &str = r#"{"version":0.6,"generator":"mock","osm3s":{},"elements":[]}"#;
+
+    #[tokio::test]
+    async fn open_ring_is_closed() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+            .with_filters(vec![])
+            .query_poly(vec![(40.0, -76.0), (41.0, -76.0), (41.0, -75.0)])
+            .await
+            .expect("Query should succeed!");
+
+        let query = server.requests()[0].form_field("data").unwrap();
+        assert!(query.contains("way(poly:\"40 -76 41 -76 41 -75 40 -76\");"));
+    }
+
+    #[tokio::test]
+    async fn invalid_polygon_is_not_sent() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        let result = common::engine()
+            .with_url(server.interpreter_url())
+            .query_poly(vec![(40.0, -76.0), (41.0, -75.0), (41.0, -76.0), (40.0, -75.0)])
+            .await;
+
+        assert!(matches!(result, Err(Error::InvalidGeometry(_))));
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn geojson_multipolygon_union() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+            .with_filters(vec!["primary".to_string()])
+            .query_geojson(r#"{
+                "type": "MultiPolygon",
+                "coordinates": [
+                    [[[-76.0, 40.0], [-76.0, 41.0], [-75.0, 41.0]]],
+                    [[[-74.0, 40.0], [-74.0, 41.0], [-73.0, 41.0]]]
+                ]
+            }"#)
+            .await
+            .expect("Query should succeed!");
+
+        let query = server.requests()[0].form_field("data").unwrap();
+        assert!(query.contains(concat!(
+            "(way[\"highway\"~\"primary\"](poly:\"40 -76 41 -76 41 -75 40 -76\"); ",
+            "way[\"highway\"~\"primary\"](poly:\"40 -74 41 -74 41 -73 40 -74\"););"
+        )));
+    }
+}
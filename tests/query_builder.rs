@@ -0,0 +1,288 @@
+mod common;
+
+#[cfg(test)]
+mod query_builder {
+
+    use osmgraph::api::{escape, Diff, QueryBuilder, Selector, Settings, SpatialFilter, Statement, TagFilter, OutMode};
+    use osmgraph::Error;
+
+    #[test]
+    fn escape_values() {
+        assert_eq!(escape("Selinsgrove"), "Selinsgrove");
+        assert_eq!(escape("Val d'Or \"Nord\""), "Val d'Or \\\"Nord\\\"");
+        assert_eq!(escape("back\\slash\nnew line"), "back\\\\slash\\nnew line");
+    }
+
+    #[test]
+    fn settings() {
+        let query = QueryBuilder::new()
+            .with_timeout(180)
+            .with_maxsize(1073741824)
+            .with_date("2020-01-01T00:00:00Z".to_string())
+            .build()
+            .unwrap();
+
+        assert_eq!(query, "[out:json][timeout:180][maxsize:1073741824][date:\"2020-01-01T00:00:00Z\"];");
+        assert_eq!(QueryBuilder::new().build().unwrap(), "[out:json];");
+    }
+
+    #[test]
+    fn diff_settings() {
+        let settings = Settings::default()
+            .with_timeout(180)
+            .with_date("2019-01-01T00:00:00Z".to_string())
+            .with_diff(Diff::Plain { from: "2020-01-01T00:00:00Z".to_string(), to: Some("2021-01-01T00:00:00Z".to_string()) });
+
+        //Diffs are only sent as XML, and replace the date
+        assert_eq!(settings.date(), None);
+        assert_eq!(settings.to_string(), "[out:xml][timeout:180][diff:\"2020-01-01T00:00:00Z\",\"2021-01-01T00:00:00Z\"];");
+
+        let settings = settings.with_date("2019-01-01T00:00:00Z".to_string());
+        assert_eq!(settings.diff(), None);
+        assert_eq!(settings.to_string(), "[out:json][timeout:180][date:\"2019-01-01T00:00:00Z\"];");
+    }
+
+    #[test]
+    fn tag_filters() {
+        let selector = Selector::nwr()
+            .with_tag(TagFilter::equals("amenity", "cafe"))
+            .with_tag(TagFilter::not_equals("access", "private"))
+            .with_tag(TagFilter::regex("cuisine", "^(coffee|tea)$"))
+            .with_tag(TagFilter::not_regex("name", "Star"))
+            .with_tag(TagFilter::exists("opening_hours"))
+            .with_tag(TagFilter::not_exists("disused"));
+
+        assert_eq!(selector.to_string(), concat!(
+            "nwr[\"amenity\"=\"cafe\"][\"access\"!=\"private\"][\"cuisine\"~\"^(coffee|tea)$\"]",
+            "[\"name\"!~\"Star\"][\"opening_hours\"][!\"disused\"]"
+        ));
+    }
+
+    #[test]
+    fn spatial_filters() {
+        assert_eq!(Selector::node().with_bbox(40.0, -77.0, 41.0, -76.0).to_string(), "node(40,-77,41,-76)");
+        assert_eq!(Selector::node().with_around(250.0, 40.5, -76.5).to_string(), "node(around:250,40.5,-76.5)");
+        assert_eq!(Selector::way().with_area("a").to_string(), "way(area.a)");
+        assert_eq!(
+            Selector::way().with_poly(vec![(40.0, -76.0), (41.0, -76.0), (41.0, -75.5)]).to_string(),
+            "way(poly:\"40 -76 41 -76 41 -75.5\")"
+        );
+        assert_eq!(
+            SpatialFilter::BBox { south: 1.5, west: 2.0, north: 3.0, east: 4.25 }.to_string(),
+            "(1.5,2,3,4.25)"
+        );
+    }
+
+    #[test]
+    fn statements() {
+        let union = Statement::Union(vec![
+            Statement::Select(Selector::node().with_tag(TagFilter::exists("highway")).with_output_set("a")),
+            Statement::Set("a".to_string()),
+            Statement::RecurseUp,
+        ]);
+
+        assert_eq!(union.to_string(), "(node[\"highway\"]->.a; .a; <;);");
+        assert_eq!(Statement::Out(vec![]).to_string(), "out;");
+        assert_eq!(
+            Statement::Out(vec![OutMode::Meta, OutMode::Geom, OutMode::Center, OutMode::Bb]).to_string(),
+            "out meta geom center bb;"
+        );
+        assert_eq!(Statement::Out(vec![OutMode::Ids, OutMode::Tags]).to_string(), "out ids tags;");
+    }
+
+    #[test]
+    fn escaped_area_name() {
+        let query = QueryBuilder::new()
+            .with_statement(Statement::Select(Selector::area()
+                .with_tag(TagFilter::equals("name", "Val d'Or \"Nord\""))
+                .with_output_set("searchArea")))
+            .build()
+            .unwrap();
+
+        assert_eq!(query, "[out:json];\narea[\"name\"=\"Val d'Or \\\"Nord\\\"\"]->.searchArea;");
+    }
+
+    #[test]
+    fn invalid_set_names() {
+        for set in ["", "1st", "search Area", "a);node(1", "área"] {
+            let area = QueryBuilder::new().with_statement(Statement::Select(Selector::way().with_area(set)));
+            let output = QueryBuilder::new().with_statement(Statement::Select(Selector::area().with_output_set(set)));
+            let named = QueryBuilder::new().with_statement(Statement::Union(vec![Statement::Set(set.to_string())]));
+
+            assert!(matches!(area.build(), Err(Error::InvalidFilter(_))), "{set:?}");
+            assert!(matches!(output.build(), Err(Error::InvalidFilter(_))), "{set:?}");
+            assert!(matches!(named.build(), Err(Error::InvalidFilter(_))), "{set:?}");
+        }
+
+        let valid = QueryBuilder::new().with_statement(Statement::Select(Selector::way().with_area("_search_Area2")));
+        assert!(valid.build().is_ok());
+    }
+
+    #[test]
+    fn invalid_coordinates() {
+        let selectors = [
+            Selector::way().with_bbox(f64::NAN, -76.0, 41.0, -75.0),
+            Selector::way().with_bbox(40.0, -76.0, 41.0, f64::INFINITY),
+            Selector::way().with_bbox(40.0, -190.0, 41.0, -75.0),
+            Selector::way().with_bbox(41.0, -76.0, 40.0, -75.0),
+            Selector::way().with_around(500.0, 91.0, -76.0),
+            Selector::way().with_around(f64::NAN, 40.0, -76.0),
+            Selector::way().with_around(-1.0, 40.0, -76.0),
+            Selector::way().with_poly(vec![(40.0, -76.0), (40.0, f64::NAN), (41.0, -76.0)]),
+        ];
+
+        for selector in selectors {
+            let query = QueryBuilder::new().with_statement(Statement::Select(selector.clone()));
+            assert!(matches!(query.build(), Err(Error::InvalidGeometry(_))), "{selector:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod engine_queries {
+
+    use osmgraph::api::RetryPolicy;
+    use osmgraph::Error;
+
+    use crate::common::{self, MockServer, Response};
+
+    const BODY: &str = r#"{"version":0.6,"generator":"mock","osm3s":{},"elements":[]}"#;
+
+    #[tokio::test]
+    async fn query_place() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+            .with_filters(vec!["primary".to_string(), "secondary".to_string()])
+            .query_place("Val d'Or \"Nord\" & Sud".to_string(), Some(8))
+            .await
+            .expect("Query should succeed!");
+
+        let query = server.requests()[0].form_field("data").expect("Request should have a data field!");
+        assert_eq!(query, concat!(
+            "[out:json];\n",
+            "area[\"name\"=\"Val d'Or \\\"Nord\\\" & Sud\"][\"admin_level\"=\"8\"]->.searchArea;\n",
+            "way[\"highway\"~\"primary|secondary\"](area.searchArea);\n",
+            "(._; >;);\n",
+            "out body;\n",
+            ">;\n",
+            "out skel qt;"
+        ));
+    }
+
+    #[tokio::test]
+    async fn query_poly() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+            .with_filters(vec![])
+            .query_poly(vec![(40.0, -76.0), (41.0, -76.0), (41.0, -75.0), (40.0, -76.0)])
+            .await
+            .expect("Query should succeed!");
+
+        let query = server.requests()[0].form_field("data").expect("Request should have a data field!");
+        assert_eq!(query, concat!(
+            "[out:json];\n",
+            "way(poly:\"40 -76 41 -76 41 -75 40 -76\");\n",
+            "(._; >;);\n",
+            "out body;\n",
+            ">;\n",
+            "out skel qt;"
+        ));
+    }
+
+    #[tokio::test]
+    async fn query_bbox() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+            .with_filters(vec!["residential".to_string()])
+            .query_bbox(40.78, -76.88, 40.82, -76.84)
+            .await
+            .expect("Query should succeed!");
+
+        let query = server.requests()[0].form_field("data").expect("Request should have a data field!");
+        assert_eq!(query, concat!(
+            "[out:json];\n",
+            "way[\"highway\"~\"residential\"](40.78,-76.88,40.82,-76.84);\n",
+            "(._; >;);\n",
+            "out body;\n",
+            ">;\n",
+            "out skel qt;"
+        ));
+    }
+
+    #[test]
+    fn query_around_blocking() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        let response = common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+            .with_filters(vec![])
+            .query_around_blocking(40.8, -76.86, 2000.0)
+            .expect("Query should succeed!");
+        assert_eq!(response, BODY);
+
+        let query = server.requests()[0].form_field("data").expect("Request should have a data field!");
+        assert!(query.contains("\nway(around:2000,40.8,-76.86);\n"));
+    }
+
+    #[tokio::test]
+    async fn invalid_geometry() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+        let engine = common::engine().with_url(server.interpreter_url());
+
+        assert!(matches!(engine.query_bbox(41.0, -76.0, 40.0, -75.0).await, Err(Error::InvalidGeometry(_))));
+        assert!(matches!(engine.query_bbox(40.0, -75.0, 41.0, -76.0).await, Err(Error::InvalidGeometry(_))));
+        assert!(matches!(engine.query_around(40.0, -76.0, 0.0).await, Err(Error::InvalidGeometry(_))));
+        assert!(matches!(engine.query_around(40.0, -76.0, f64::NAN).await, Err(Error::InvalidGeometry(_))));
+
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn settings() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        let engine = common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+            .with_filters(vec![])
+            .with_timeout(600)
+            .with_maxsize(1073741824)
+            .with_date("2020-01-01T00:00:00Z".to_string());
+
+        assert_eq!(engine.settings().timeout(), Some(600));
+
+        engine.query_bbox(40.0, -76.0, 41.0, -75.0)
+            .await
+            .expect("Query should succeed!");
+
+        let sent = server.requests()[0].form_field("data").expect("Request should have a data field!");
+        let built: String = engine.bbox_query(40.0, -76.0, 41.0, -75.0)
+            .expect("Bounding box should be valid!")
+            .build()
+            .unwrap();
+        assert_eq!(sent, built);
+        assert!(sent.starts_with("[out:json][timeout:600][maxsize:1073741824][date:\"2020-01-01T00:00:00Z\"];\n"));
+
+        //Queries sent by hand are left alone
+        engine.query("[out:json];node(1);out;".to_string())
+            .await
+            .expect("Query should succeed!");
+        assert_eq!(server.requests()[1].form_field("data").unwrap(), "[out:json];node(1);out;");
+    }
+}
@@ -0,0 +1,520 @@
+mod common;
+
+#[cfg(test)]
+mod retry {
+
+    use std::time::{Duration, Instant};
+
+    use osmgraph::api::RetryPolicy;
+    use osmgraph::Error;
+
+    use crate::common::{self, MockServer, Response};
+
+    const BODY: &str = r#"{"version":0.6,"generator":"mock","osm3s":{},"elements":[]}"#;
+
+    const BUSY: &str = concat!(
+        "Connected as: 1234\n",
+        "Current time: 2024-11-18T15:04:05Z\n",
+        "Rate limit: 2\n",
+        "Slot available after: 2024-11-18T15:04:06Z, in 1 seconds.\n",
+        "Slot available after: 2024-11-18T15:04:09Z, in 4 seconds.\n",
+        "Currently running queries (pid, space limit, time limit, start time):\n",
+    );
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy::new()
+            .with_base_delay(Duration::from_millis(10))
+            .with_jitter(false)
+    }
+
+    #[tokio::test]
+    async fn retry_until_success() {
+
+        let server = MockServer::start(|_, previous| match previous {
+            0 => Response::status(429),
+            1 => Response::status(504),
+            _ => Response::json(BODY)
+        });
+
+        let engine = common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(fast_policy());
+
+        let response: String = engine.query("[out:json];node(1);out;".to_string())
+            .await
+            .expect("Query should succeed after retrying!");
+
+        assert_eq!(response, BODY);
+
+        let requests = server.requests_to("/api/interpreter");
+        assert_eq!(requests.len(), 3);
+
+        //Exponential backoff: 10ms then 20ms
+        assert!(requests[1].received - requests[0].received >= Duration::from_millis(10));
+        assert!(requests[2].received - requests[1].received >= Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn give_up_after_max_retries() {
+
+        let server = MockServer::start(|_, _| Response::status(429));
+
+        let engine = common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(fast_policy().with_max_retries(2));
+
+        let result = engine.query("[out:json];node(1);out;".to_string()).await;
+
+        assert!(matches!(result, Err(Error::HttpStatus { status: 429, .. })));
+        assert_eq!(server.requests_to("/api/interpreter").len(), 3);
+    }
+
+    #[tokio::test]
+    async fn no_retry_on_bad_request() {
+
+        let server = MockServer::start(|_, _| Response::status(400));
+
+        let engine = common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(fast_policy());
+
+        let result = engine.query("[out:json];node(;out;".to_string()).await;
+
+        assert!(matches!(result, Err(Error::HttpStatus { status: 400, .. })));
+        assert_eq!(server.requests_to("/api/interpreter").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn wait_for_slot() {
+
+        let server = MockServer::start(|request, previous| match (request.path.as_str(), previous) {
+            ("/api/status", 0) => Response::text(BUSY),
+            ("/api/status", _) => Response::text(concat!(
+                "Connected as: 1234\n",
+                "Current time: 2024-11-18T15:04:06Z\n",
+                "Rate limit: 2\n",
+                "1 slots available now.\n",
+                "Slot available after: 2024-11-18T15:04:09Z, in 3 seconds.\n",
+                "Currently running queries (pid, space limit, time limit, start time):\n",
+            )),
+            _ => Response::json(BODY)
+        });
+
+        let engine = common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(fast_policy().with_slot_check(true));
+
+        let start = Instant::now();
+        engine.query("[out:json];node(1);out;".to_string())
+            .await
+            .expect("Query should succeed!");
+
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert!(start.elapsed() < Duration::from_secs(3));
+        assert_eq!(server.requests_to("/api/status").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn slot_checks_are_bounded() {
+
+        let server = MockServer::start(|request, _| match request.path.as_str() {
+            "/api/status" => Response::text(BUSY),
+            _ => Response::json(BODY)
+        });
+
+        let engine = common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(fast_policy().with_slot_check(true));
+
+        engine.query("[out:json];node(1);out;".to_string())
+            .await
+            .expect("Query should be sent once the checks run out!");
+
+        assert_eq!(server.requests_to("/api/status").len(), 5);
+        assert_eq!(server.requests_to("/api/interpreter").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn min_interval() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        let engine = common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(fast_policy().with_min_interval(Duration::from_millis(200)));
+
+        //Clones share the interval
+        let other = engine.clone();
+
+        let start = Instant::now();
+        engine.query("[out:json];node(1);out;".to_string()).await.expect("Query should succeed!");
+        other.query("[out:json];node(2);out;".to_string()).await.expect("Query should succeed!");
+
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert_eq!(server.requests_to("/api/interpreter").len(), 2);
+    }
+
+    #[test]
+    fn retry_blocking() {
+
+        let server = MockServer::start(|_, previous| match previous {
+            0 => Response::status(503),
+            _ => Response::json(BODY)
+        });
+
+        let engine = common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(fast_policy());
+
+        let response: String = engine.query_blocking("[out:json];node(1);out;".to_string())
+            .expect("Query should succeed after retrying!");
+
+        assert_eq!(response, BODY);
+        assert_eq!(server.requests_to("/api/interpreter").len(), 2);
+    }
+
+    #[test]
+    fn jitter_stays_in_range() {
+
+        let policy = RetryPolicy::new()
+            .with_base_delay(Duration::from_secs(1))
+            .with_max_delay(Duration::from_secs(5));
+
+        for _ in 0..100 {
+            let delay = policy.delay(1);
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2));
+        }
+
+        assert!(policy.delay(10) <= Duration::from_secs(5));
+    }
+}
+
+#[cfg(test)]
+mod endpoints {
+
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use osmgraph::api::RetryPolicy;
+    use osmgraph::Error;
+
+    use crate::common::{self, MockServer, Response};
+
+    const BODY: &str = r#"{"version":0.6,"generator":"mock","osm3s":{},"elements":[]}"#;
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy::new()
+            .with_base_delay(Duration::from_millis(10))
+            .with_jitter(false)
+    }
+
+    /// A url on which nothing is listening.
+    fn dead_url() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/api/interpreter", listener.local_addr().unwrap())
+    }
+
+    #[tokio::test]
+    async fn failover() {
+
+        let overloaded = MockServer::start(|_, _| Response::status(504));
+        let healthy = MockServer::start(|_, _| Response::json(BODY));
+
+        let dead = dead_url();
+        let engine = common::engine()
+            .with_urls(vec![dead.clone(), overloaded.interpreter_url(), healthy.interpreter_url()])
+            .with_policy(fast_policy());
+
+        let response = engine.query("[out:json];node(1);out;".to_string())
+            .await
+            .expect("Query should fail over to the healthy endpoint!");
+        assert_eq!(response, BODY);
+
+        let health = engine.endpoint_health();
+        assert_eq!(health[0].url(), dead);
+        assert_eq!(health[0].failures(), 1);
+        assert!(health[0].last_error().is_some());
+        assert_eq!(health[1].failures(), 1);
+        assert!(!health[1].is_healthy());
+        assert_eq!(health[2].successes(), 1);
+        assert!(health[2].is_healthy());
+
+        //Unhealthy endpoints are tried last on the next request
+        engine.query("[out:json];node(1);out;".to_string()).await.expect("Query should succeed!");
+        assert_eq!(overloaded.requests().len(), 1);
+        assert_eq!(healthy.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn failover_on_error_status() {
+
+        for status in [404, 500] {
+            let broken = MockServer::start(move |_, _| Response::status(status));
+            let healthy = MockServer::start(|_, _| Response::json(BODY));
+
+            let engine = common::engine()
+                .with_urls(vec![broken.interpreter_url(), healthy.interpreter_url()])
+                .with_policy(fast_policy());
+
+            let response = engine.query("[out:json];node(1);out;".to_string())
+                .await
+                .expect("Query should fail over to the healthy endpoint!");
+            assert_eq!(response, BODY);
+
+            let health = engine.endpoint_health();
+            assert_eq!(health[0].successes(), 0);
+            assert_eq!(health[0].failures(), 1);
+            assert_eq!(health[1].successes(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn bad_request_is_not_failed_over() {
+
+        let first = MockServer::start(|_, _| Response::status(400));
+        let second = MockServer::start(|_, _| Response::json(BODY));
+
+        let engine = common::engine()
+            .with_urls(vec![first.interpreter_url(), second.interpreter_url()])
+            .with_policy(fast_policy());
+
+        let result = engine.query("[out:json];node(1);out;".to_string()).await;
+        assert!(matches!(result, Err(Error::HttpStatus { status: 400, .. })));
+
+        assert!(second.requests().is_empty());
+        let health = engine.endpoint_health();
+        assert_eq!((health[0].successes(), health[0].failures()), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn no_endpoints() {
+
+        let engine = common::engine().with_urls(vec![]);
+        assert!(matches!(engine.query("[out:json];node(1);out;".to_string()).await, Err(Error::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn all_endpoints_down() {
+
+        let engine = common::engine()
+            .with_urls(vec![dead_url(), dead_url()])
+            .with_policy(fast_policy().with_max_retries(1));
+
+        assert!(engine.query("[out:json];node(1);out;".to_string()).await.is_err());
+
+        for health in engine.endpoint_health() {
+            assert_eq!(health.failures(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn round_robin() {
+
+        let first = MockServer::start(|_, _| Response::json(BODY));
+        let second = MockServer::start(|_, _| Response::json(BODY));
+
+        let engine = common::engine()
+            .with_urls(vec![first.interpreter_url(), second.interpreter_url()])
+            .with_round_robin(true)
+            .with_policy(fast_policy());
+
+        for _ in 0..4 {
+            engine.query("[out:json];node(1);out;".to_string()).await.expect("Query should succeed!");
+        }
+
+        assert_eq!(first.requests().len(), 2);
+        assert_eq!(second.requests().len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod typed {
+
+    use osmgraph::api::{QueryEngine, OverpassResponse, RetryPolicy};
+    use osmgraph::graph::OSMGraph;
+    use osmgraph::Error;
+
+    use crate::common::{self, MockServer, Response};
+
+    const BODY: &str = r#"{
+        "version": 0.6,
+        "generator": "mock",
+        "osm3s": {},
+        "elements": [
+            { "type": "node", "id": 1, "lat": 40.0, "lon": -76.0 },
+            { "type": "node", "id": 2, "lat": 40.001, "lon": -76.0 },
+            { "type": "node", "id": 3, "lat": 40.002, "lon": -76.0 },
+            { "type": "way", "id": 10, "nodes": [1, 2, 3], "tags": { "highway": "residential" } }
+        ]
+    }"#;
+
+    fn engine(server: &MockServer) -> QueryEngine {
+        common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+    }
+
+    #[tokio::test]
+    async fn fetch_place() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        let response: OverpassResponse = engine(&server)
+            .fetch_place("Selinsgrove".to_string(), Some(8))
+            .await
+            .expect("Fetch should succeed!");
+
+        assert_eq!(response.elements().len(), 4);
+        assert!(server.requests()[0].form_field("data").unwrap().contains("area[\"name\"=\"Selinsgrove\"]"));
+    }
+
+    #[tokio::test]
+    async fn graph_from_bbox() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        let graph: OSMGraph = engine(&server)
+            .graph_from_bbox(39.9, -76.1, 40.1, -75.9)
+            .await
+            .expect("Graph should be created!");
+
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 2);
+    }
+
+    #[test]
+    fn graph_from_poly_blocking() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        let graph: OSMGraph = engine(&server)
+            .graph_from_poly_blocking(vec![(39.9, -76.1), (40.1, -76.1), (40.1, -75.9), (39.9, -76.1)])
+            .expect("Graph should be created!");
+
+        assert_eq!(graph.node_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn parse_error() {
+
+        let server = MockServer::start(|_, _| Response::json(r#"{"elements": [{"type": "node"}]}"#));
+
+        let result = engine(&server).fetch_around(40.0, -76.0, 500.0).await;
+
+        assert!(matches!(result, Err(Error::Parse { .. })));
+    }
+
+    #[tokio::test]
+    async fn incomplete_response() {
+
+        let server = MockServer::start(|_, _| Response::json(
+            r#"{"elements": [], "remark": "runtime error: Query timed out in \"query\" at line 3 after 26 seconds."}"#
+        ));
+
+        let result = engine(&server).graph_from_place("Selinsgrove".to_string(), None).await;
+
+        assert!(matches!(result, Err(Error::Incomplete { .. })));
+
+        let result = engine(&server).fetch("[out:json];node(1);out;".to_string()).await;
+
+        assert!(matches!(result, Err(Error::Incomplete { remark, .. }) if remark.starts_with("runtime error")));
+    }
+}
+
+#[cfg(test)]
+mod responses {
+
+    use osmgraph::api::{HttpRequest, HttpResponse, QueryEngine, RetryPolicy, Transport, TransportFuture};
+    use osmgraph::Error;
+
+    /// Answers every request with the same response.
+    #[derive(Debug)]
+    struct FixedTransport {
+        status: u16,
+        content_type: &'static str,
+        body: &'static str
+    }
+
+    impl Transport for FixedTransport {
+        fn send(&self, _request: HttpRequest) -> TransportFuture<'_> {
+            Box::pin(async move {
+                Ok(HttpResponse::new(
+                    self.status,
+                    vec![("Content-Type".to_string(), self.content_type.to_string())],
+                    self.body.as_bytes().to_vec()
+                ))
+            })
+        }
+    }
+
+    async fn query(status: u16, content_type: &'static str, body: &'static str) -> Result<String, Error> {
+        QueryEngine::from_transport(FixedTransport { status, content_type, body })
+            .with_policy(RetryPolicy::none())
+            .query("[out:json];node(1);out;".to_string())
+            .await
+    }
+
+    #[tokio::test]
+    async fn success() {
+        let body = r#"{"version":0.6,"elements":[]}"#;
+        assert_eq!(query(200, "application/json", body).await.unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn runtime_remark_is_not_an_error() {
+        let body = r#"{"elements":[],"remark":"runtime remark: Timeout is 180 and maxsize is 536870912."}"#;
+        assert!(query(200, "application/json", body).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn http_status() {
+        let result = query(429, "text/html", "Too Many Requests").await;
+        assert!(matches!(result, Err(Error::HttpStatus { status: 429, .. })));
+    }
+
+    #[tokio::test]
+    async fn json_runtime_error() {
+        let body = r#"{
+          "elements": [{"type": "node", "id": 1, "lat": 40.0, "lon": -76.0}],
+          "remark": "runtime error: Query timed out in \"query\" at line 3 after 26 seconds."
+        }"#;
+
+        match query(200, "application/json", body).await {
+            Err(Error::Incomplete { remark, body: partial }) => {
+                assert!(remark.starts_with("runtime error: Query timed out"));
+                assert_eq!(partial, body);
+            },
+            other => panic!("Expected an incomplete response, got {other:?}")
+        }
+    }
+
+    #[tokio::test]
+    async fn xml_runtime_error() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <remark> runtime error: Query run out of memory using about 2048 MB of RAM. </remark>
+</osm>"#;
+
+        let result = query(200, "application/osm3s+xml", body).await;
+        assert!(matches!(result, Err(Error::Incomplete { .. })));
+    }
+
+    #[tokio::test]
+    async fn html_error_page() {
+        let body = r#"<!DOCTYPE html><html><body>
+<p><strong style="color:#FF0000">Error</strong>: line 2: parse error: ';' expected - ')' found. </p>
+</body></html>"#;
+
+        match query(200, "text/html; charset=utf-8", body).await {
+            Err(Error::Overpass(message)) => assert_eq!(message, "line 2: parse error: ';' expected - ')' found."),
+            other => panic!("Expected an overpass error, got {other:?}")
+        }
+    }
+
+    #[tokio::test]
+    async fn html_timeout() {
+        let body = r#"<p><strong style="color:#FF0000">Error</strong>: runtime error: Query timed out in "query" at line 3 after 2 seconds. </p>"#;
+        let result = query(200, "text/html", body).await;
+        assert!(matches!(result, Err(Error::Timeout(_))));
+    }
+}
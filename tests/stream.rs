@@ -0,0 +1,198 @@
+#[cfg(test)]
+mod stream {
+
+    use osmgraph::api::{AsyncElementReader, Element, ElementReader, OverpassResponse, WayFilter};
+    use osmgraph::graph::{GraphBuilder, OSMGraph, create_graph, create_graph_from_stream};
+    use osmgraph::Error;
+
+    const RESPONSE: &str = r#"{
+  "version": 0.6,
+  "generator": "Overpass API \"test\"",
+  "osm3s": { "timestamp_osm_base": "2024-11-18T00:00:00Z", "copyright": "{[,]}" },
+  "elements": [
+    { "type": "node", "id": 1, "lat": 40.0, "lon": -76.0, "tags": { "name": "a \"quoted\" }, name" } },
+    { "type": "node", "id": 2, "lat": 40.001, "lon": -76.0 },
+    { "type": "way", "id": 10, "nodes": [1, 2], "tags": { "highway": "residential" } }
+  ],
+  "remark": "runtime error: Query timed out"
+}"#;
+
+    /// Reads one byte at a time, to check that values split between reads are put back together.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((byte, rest)) if !buf.is_empty() => {
+                    buf[0] = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                },
+                _ => Ok(0)
+            }
+        }
+    }
+
+    #[test]
+    fn read_elements() {
+
+        let mut reader = ElementReader::new(Trickle(RESPONSE.as_bytes()));
+
+        let mut results: Vec<Result<Element, Error>> = reader.by_ref().collect();
+
+        //The remark reports a runtime error, which comes after the elements
+        match results.pop() {
+            Some(Err(Error::Incomplete { remark, body })) => {
+                assert_eq!(remark, "runtime error: Query timed out");
+                assert!(body.is_empty());
+            },
+            other => panic!("Expected an incomplete response, got {other:?}")
+        }
+        let elements: Vec<Element> = results.into_iter()
+            .collect::<Result<_, _>>()
+            .expect("Elements should be read!");
+        let expected: OverpassResponse = serde_json::from_str(RESPONSE).unwrap();
+        assert_eq!(&elements, expected.elements());
+
+        assert_eq!(reader.remark(), Some("runtime error: Query timed out"));
+        assert_eq!(reader.fields()["version"], 0.6);
+        assert_eq!(reader.fields()["generator"], "Overpass API \"test\"");
+        assert_eq!(reader.fields()["osm3s"]["copyright"], "{[,]}");
+    }
+
+    #[test]
+    fn read_file() {
+
+        let reader = ElementReader::open("./assets/test.json").expect("File should open!");
+        let ids: Vec<u64> = reader.map(|element| element.unwrap().id()).collect();
+
+        let response = OverpassResponse::load_blocking("./assets/test.json").unwrap();
+        let expected: Vec<u64> = response.elements().iter().map(|element| element.id()).collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn bad_element() {
+
+        let json = r#"{"elements": [
+            {"type": "node", "id": 1, "lat": 40.0, "lon": -76.0},
+            {"type": "node", "id": "two"},
+            {"type": "node", "id": 3, "lat": 40.0, "lon": -76.0}
+        ]}"#;
+
+        let results: Vec<Result<Element, Error>> = ElementReader::new(json.as_bytes()).collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        match &results[1] {
+            Err(Error::Parse { line, .. }) => assert_eq!(*line, 3),
+            other => panic!("Expected a parse error, got {other:?}")
+        }
+        assert_eq!(results[2].as_ref().unwrap().id(), 3);
+    }
+
+    #[test]
+    fn truncated() {
+
+        let json = &RESPONSE[..RESPONSE.find("\"way\"").unwrap()];
+
+        let results: Vec<Result<Element, Error>> = ElementReader::new(json.as_bytes()).collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok() && results[1].is_ok());
+        assert!(matches!(results[2], Err(Error::Parse { .. })));
+    }
+
+    #[test]
+    fn not_a_response() {
+
+        let results: Vec<Result<Element, Error>> = ElementReader::new(&b"[1, 2, 3]"[..]).collect();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(Error::Parse { line: 1, column: 1, .. })));
+    }
+
+    #[tokio::test]
+    async fn async_reader() {
+
+        let mut reader = AsyncElementReader::new(RESPONSE.as_bytes());
+
+        let mut ids: Vec<u64> = vec![];
+        let mut incomplete: usize = 0;
+        while let Some(element) = reader.next_element().await {
+            match element {
+                Ok(element) => ids.push(element.id()),
+                Err(Error::Incomplete { .. }) => incomplete += 1,
+                Err(e) => panic!("Elements should be read, got {e:?}")
+            }
+        }
+        assert_eq!(ids, vec![1, 2, 10]);
+        assert_eq!(incomplete, 1);
+        assert_eq!(reader.remark(), Some("runtime error: Query timed out"));
+    }
+
+    #[test]
+    fn incomplete_response() {
+
+        let result = create_graph_from_stream(ElementReader::new(RESPONSE.as_bytes()));
+        assert!(matches!(result, Err(Error::Incomplete { .. })));
+
+        //A harmless remark is not an error
+        let json = RESPONSE.replace("runtime error", "runtime remark");
+        let graph: OSMGraph = create_graph_from_stream(ElementReader::new(json.as_bytes()))
+            .expect("Graph should be created!");
+        assert_eq!(graph.edge_count(), 1);
+    }
+
+    #[test]
+    fn graph_from_stream() {
+
+        let response = OverpassResponse::load_blocking("./assets/test.json").unwrap();
+        let expected: OSMGraph = create_graph(response.elements()).unwrap();
+
+        let reader = ElementReader::open("./assets/test.json").unwrap();
+        let graph: OSMGraph = create_graph_from_stream(reader).expect("Graph should be created!");
+
+        assert_eq!(graph.edge_count(), expected.edge_count());
+        let mut lengths: Vec<f64> = graph.edge_weights().map(|edge| edge.dist()).collect();
+        let mut expected_lengths: Vec<f64> = expected.edge_weights().map(|edge| edge.dist()).collect();
+        lengths.sort_by(f64::total_cmp);
+        expected_lengths.sort_by(f64::total_cmp);
+        assert_eq!(lengths, expected_lengths);
+    }
+
+    #[test]
+    fn builder_out_of_order() {
+
+        //Ways first, with one node that only comes as geometry
+        let json = r#"{"elements": [
+            {"type": "way", "id": 10, "nodes": [1, 2, 3], "tags": {"highway": "service"},
+             "geometry": [null, null, {"lat": 40.002, "lon": -76.0}]},
+            {"type": "way", "id": 11, "nodes": [3, 4], "tags": {"building": "yes"}},
+            {"type": "node", "id": 1, "lat": 40.0, "lon": -76.0},
+            {"type": "node", "id": 2, "lat": 40.001, "lon": -76.0},
+            {"type": "node", "id": 2, "lat": 40.001, "lon": -76.0},
+            {"type": "node", "id": 4, "lat": 40.003, "lon": -76.0}
+        ]}"#;
+
+        let mut builder = GraphBuilder::new();
+        for element in ElementReader::new(json.as_bytes()) {
+            builder.add(element.unwrap()).unwrap();
+        }
+        let graph: OSMGraph = builder.build().expect("Graph should be created!");
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 2);
+
+        //With a filter, the node that is only on the building is left out
+        let mut builder = GraphBuilder::new().with_filter(WayFilter::highway(&[]));
+        for element in ElementReader::new(json.as_bytes()) {
+            builder.add(element.unwrap()).unwrap();
+        }
+        let graph: OSMGraph = builder.build().expect("Graph should be created!");
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 2);
+
+        let result = create_graph_from_stream(
+            ElementReader::new(json.as_bytes())
+                .filter(|element| !matches!(element, Ok(Element::Node { id: 2, .. })))
+        );
+        assert!(matches!(result, Err(Error::MissingNode { way: 10, node: 2 })));
+    }
+}
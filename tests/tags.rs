@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tags {
+
+    use osmgraph::api::Tags;
+
+    fn tags(pairs: &[(&str, &str)]) -> Tags {
+        pairs.iter().copied().collect()
+    }
+
+    #[test]
+    fn matching() {
+        let t = tags(&[("highway", "residential"), ("name", "Market Street")]);
+
+        assert!(t.contains_key("name"));
+        assert!(t.matches("highway", "residential"));
+        assert!(!t.matches("highway", "primary"));
+        assert!(t.matches_any("highway", &["primary", "residential"]));
+        assert!(!t.matches_any("railway", &["rail"]));
+        assert_eq!(t.get("oneway"), None);
+    }
+
+    #[test]
+    fn multi_values() {
+        let t = tags(&[("ref", "I 80; US 15;"), ("lanes", "2;3")]);
+
+        assert_eq!(t.values("ref"), vec!["I 80", "US 15"]);
+        assert_eq!(t.get_number("lanes"), Some(2.));
+        assert!(t.values("name").is_empty());
+    }
+
+    #[test]
+    fn booleans() {
+        let t = tags(&[("a", "yes"), ("b", "true"), ("c", "1"), ("d", "no"), ("e", "0"), ("f", "-1")]);
+
+        assert_eq!(t.get_bool("a"), Some(true));
+        assert_eq!(t.get_bool("b"), Some(true));
+        assert_eq!(t.get_bool("c"), Some(true));
+        assert_eq!(t.get_bool("d"), Some(false));
+        assert_eq!(t.get_bool("e"), Some(false));
+        assert_eq!(t.get_bool("f"), None);
+        assert_eq!(t.get_bool("g"), None);
+    }
+
+    #[test]
+    fn speeds() {
+        let t = tags(&[("a", "50"), ("b", "30 mph"), ("c", "20 km/h"), ("d", "none"), ("e", "10 knots")]);
+
+        assert_eq!(t.get_speed("a"), Some(50.));
+        assert!((t.get_speed("b").unwrap() - 48.28).abs() < 0.01);
+        assert_eq!(t.get_speed("c"), Some(20.));
+        assert_eq!(t.get_speed("d"), None);
+        assert!((t.get_speed("e").unwrap() - 18.52).abs() < 0.01);
+    }
+
+    #[test]
+    fn lengths() {
+        let t = tags(&[
+            ("a", "3.5"), ("b", "3.5 m"), ("c", "12'6\""), ("d", "7'"), ("e", "2 km"), ("f", "default")
+        ]);
+
+        assert_eq!(t.get_length("a"), Some(3.5));
+        assert_eq!(t.get_length("b"), Some(3.5));
+        assert!((t.get_length("c").unwrap() - 3.81).abs() < 1e-9);
+        assert!((t.get_length("d").unwrap() - 2.1336).abs() < 1e-9);
+        assert_eq!(t.get_length("e"), Some(2000.));
+        assert_eq!(t.get_length("f"), None);
+    }
+
+    #[test]
+    fn serialize() {
+        let json = r#"{"highway":"residential","maxspeed":"25 mph"}"#;
+
+        let t: Tags = serde_json::from_str(json).expect("Could not parse tags!");
+        assert_eq!(t.len(), 2);
+        assert_eq!(serde_json::to_string(&t).unwrap(), json);
+    }
+}
@@ -0,0 +1,213 @@
+mod common;
+
+#[cfg(test)]
+mod tiles {
+
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use osmgraph::api::{QueryEngine, OverpassResponse, RetryPolicy, TileOptions, TileStatus, TileProgress};
+    use osmgraph::graph::create_graph;
+    use osmgraph::Error;
+
+    use crate::common::{self, MockServer, Request, Response};
+
+    //Way 10 crosses from the south west tile into the south east tile
+    const SOUTH_WEST: &str = r#"{"version":0.6,"generator":"mock","osm3s":{},"elements":[
+        {"type":"node","id":1,"lat":40.2,"lon":-76.2},
+        {"type":"node","id":2,"lat":40.2,"lon":-75.8},
+        {"type":"node","id":3,"lat":40.2,"lon":-75.2},
+        {"type":"way","id":10,"nodes":[1,2,3],"tags":{"highway":"primary"}}
+    ]}"#;
+    const SOUTH_EAST: &str = r#"{"version":0.6,"generator":"mock","osm3s":{},"elements":[
+        {"type":"node","id":1,"lat":40.2,"lon":-76.2},
+        {"type":"node","id":2,"lat":40.2,"lon":-75.8},
+        {"type":"node","id":3,"lat":40.2,"lon":-75.2},
+        {"type":"node","id":4,"lat":40.3,"lon":-75.2},
+        {"type":"way","id":10,"nodes":[1,2,3],"tags":{"highway":"primary"}},
+        {"type":"way","id":11,"nodes":[3,4],"tags":{"highway":"residential"}}
+    ]}"#;
+    const EMPTY: &str = r#"{"version":0.6,"generator":"mock","osm3s":{},"elements":[]}"#;
+
+    fn respond(request: &Request) -> Response {
+        let query = request.form_field("data").unwrap_or_default();
+        if query.contains("(40,-76,40.5,-75.5)") {
+            Response::json(SOUTH_WEST)
+        } else if query.contains("(40,-75.5,40.5,-75)") {
+            Response::json(SOUTH_EAST)
+        } else {
+            Response::json(EMPTY)
+        }
+    }
+
+    fn engine(server: &MockServer) -> QueryEngine {
+        common::engine()
+            .with_url(server.interpreter_url())
+            .with_policy(RetryPolicy::none())
+    }
+
+    #[tokio::test]
+    async fn merge_tiles() {
+
+        let server = MockServer::start(|request, _| respond(request));
+
+        let progress: Arc<Mutex<Vec<TileProgress>>> = Arc::new(Mutex::new(vec![]));
+        let recorded = progress.clone();
+        let options = TileOptions::new()
+            .with_tile_size(0.5)
+            .with_progress(move |p| recorded.lock().unwrap().push(p.clone()));
+
+        let response: OverpassResponse = engine(&server)
+            .fetch_bbox_tiled(40.0, -76.0, 41.0, -75.0, &options)
+            .await
+            .expect("Tiled fetch should succeed!");
+
+        assert_eq!(server.requests().len(), 4);
+
+        //Nodes 1-4 and ways 10 and 11, each once
+        assert_eq!(response.elements().len(), 6);
+        let graph = create_graph(response.elements()).expect("Graph should be created!");
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 3);
+
+        let progress = progress.lock().unwrap();
+        assert_eq!(progress.len(), 4);
+        assert!(progress.iter().all(|p| p.total() == 4 && matches!(p.status(), TileStatus::Done { .. })));
+        assert_eq!(progress.iter().map(|p| p.completed()).max(), Some(4));
+
+        let mut indices: Vec<usize> = progress.iter().map(|p| p.index()).collect();
+        indices.sort();
+        assert_eq!(indices, vec![0, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn retry_failed_tile() {
+
+        let failed = Arc::new(AtomicBool::new(false));
+        let server = MockServer::start(move |request, _| {
+            let query = request.form_field("data").unwrap_or_default();
+            if query.contains("(40,-75.5,40.5,-75)") && !failed.swap(true, Ordering::SeqCst) {
+                return Response::status(504)
+            }
+            respond(request)
+        });
+
+        let retries: Arc<Mutex<Vec<usize>>> = Arc::new(Mutex::new(vec![]));
+        let recorded = retries.clone();
+        let options = TileOptions::new()
+            .with_tile_size(0.5)
+            .with_progress(move |p| if let TileStatus::Retrying(_) = p.status() {
+                recorded.lock().unwrap().push(p.index());
+            });
+
+        let engine = engine(&server)
+            .with_policy(RetryPolicy::none().with_base_delay(std::time::Duration::from_millis(10)));
+        let response = engine.fetch_bbox_tiled(40.0, -76.0, 41.0, -75.0, &options)
+            .await
+            .expect("Tiled fetch should succeed after retrying!");
+
+        assert_eq!(response.elements().len(), 6);
+        assert_eq!(*retries.lock().unwrap(), vec![1]);
+        assert_eq!(server.requests().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn failed_tile_fails_the_fetch() {
+
+        let server = MockServer::start(|request, _| {
+            let query = request.form_field("data").unwrap_or_default();
+            match query.contains("(40.5,-76,41,-75.5)") {
+                true => Response::status(400),
+                false => respond(request)
+            }
+        });
+
+        let result = engine(&server)
+            .fetch_bbox_tiled(40.0, -76.0, 41.0, -75.0, &TileOptions::new().with_tile_size(0.5).with_concurrency(1))
+            .await;
+
+        assert!(matches!(result, Err(Error::HttpStatus { status: 400, .. })));
+    }
+
+    #[test]
+    fn poly_skips_tiles_outside() {
+
+        let server = MockServer::start(|request, _| respond(request));
+
+        //The triangle misses the north east tile of the four tiles of its bounding box
+        let response = engine(&server)
+            .fetch_poly_tiled_blocking(
+                vec![(40.0, -76.0), (40.4, -76.0), (40.0, -75.6)],
+                &TileOptions::new().with_tile_size(0.25)
+            )
+            .expect("Tiled fetch should succeed!");
+
+        assert_eq!(response.elements().len(), 0);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        for request in requests {
+            let query = request.form_field("data").unwrap();
+            assert!(query.contains("(poly:\"40 -76 40.4 -76 40 -75.6 40 -76\")("));
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_do_not_multiply() {
+
+        let server = MockServer::start(|request, _| {
+            let query = request.form_field("data").unwrap_or_default();
+            match query.contains("(40,-75.5,40.5,-75)") {
+                true => Response::status(504),
+                false => respond(request)
+            }
+        });
+
+        //The engine would retry 3 times on its own, but tiles are only retried by the tile options
+        let engine = engine(&server)
+            .with_policy(RetryPolicy::new().with_max_retries(3).with_base_delay(std::time::Duration::from_millis(1)));
+        let result = engine
+            .fetch_bbox_tiled(40.0, -76.0, 41.0, -75.0, &TileOptions::new().with_tile_size(0.5).with_max_retries(1))
+            .await;
+
+        assert!(matches!(result, Err(Error::HttpStatus { status: 504, .. })));
+        let failing = server.requests().iter()
+            .filter(|request| request.form_field("data").unwrap_or_default().contains("(40,-75.5,40.5,-75)"))
+            .count();
+        assert_eq!(failing, 2);
+    }
+
+    #[tokio::test]
+    async fn too_many_tiles() {
+
+        for tile_size in [0.0001, f64::MIN_POSITIVE] {
+            let result = common::engine()
+                .fetch_bbox_tiled(40.0, -76.0, 41.0, -75.0, &TileOptions::new().with_tile_size(tile_size))
+                .await;
+            assert!(matches!(result, Err(Error::InvalidGeometry(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_tile_size() {
+
+        //Nothing is fetched, rather than the whole area as one tile
+        let server = MockServer::start(|_, _| Response::json(EMPTY));
+        for tile_size in [0.0, -0.1, f64::NAN, f64::INFINITY] {
+            let result = common::engine()
+                .with_url(server.interpreter_url())
+                .fetch_bbox_tiled(40.0, -76.0, 41.0, -75.0, &TileOptions::new().with_tile_size(tile_size))
+                .await;
+            assert!(matches!(result, Err(Error::InvalidGeometry(_))));
+        }
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn invalid_bbox() {
+        let result = common::engine()
+            .fetch_bbox_tiled(41.0, -76.0, 40.0, -75.0, &TileOptions::new())
+            .await;
+        assert!(matches!(result, Err(Error::InvalidGeometry(_))));
+    }
+}
@@ -0,0 +1,123 @@
+mod common;
+
+#[cfg(test)]
+mod transport {
+
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use osmgraph::api::{
+        HttpRequest, HttpResponse, Method, OverpassResponse, QueryEngine, RetryPolicy, Transport, TransportFuture
+    };
+    use osmgraph::Error;
+
+    use crate::common::{MockServer, Response, StdTransport};
+
+    const BODY: &str = r#"{"version":0.6,"generator":"fake","osm3s":{},"elements":[{"type":"node","id":1,"lat":40.0,"lon":-76.0}]}"#;
+
+    /// Answers from memory: the first `failures` requests fail without a response.
+    #[derive(Debug, Default)]
+    struct FakeTransport {
+        failures: usize,
+        requests: Arc<Mutex<Vec<HttpRequest>>>
+    }
+
+    impl Transport for FakeTransport {
+        fn send(&self, request: HttpRequest) -> TransportFuture<'_> {
+            Box::pin(async move {
+                let mut requests = self.requests.lock().unwrap();
+                requests.push(request);
+                match requests.len() <= self.failures {
+                    true => Err(Error::Transport("connection refused".into())),
+                    false => Ok(HttpResponse::new(
+                        200,
+                        vec![("Content-Type".to_string(), "application/json".to_string())],
+                        BODY.as_bytes().to_vec()
+                    ))
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn fake_transport() {
+
+        let requests: Arc<Mutex<Vec<HttpRequest>>> = Arc::default();
+        let engine = QueryEngine::from_transport(FakeTransport { failures: 0, requests: requests.clone() })
+            .with_url("http://fake/api/interpreter".to_string());
+
+        let response: OverpassResponse = engine.fetch("[out:json];node(1);out;".to_string())
+            .await
+            .expect("Fake transport should answer!");
+        assert_eq!(response.elements().len(), 1);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method(), Method::Post);
+        assert_eq!(requests[0].url(), "http://fake/api/interpreter");
+        assert_eq!(requests[0].body(), b"data=%5Bout%3Ajson%5D%3Bnode%281%29%3Bout%3B");
+        assert!(requests[0].headers().iter().any(|(name, value)| {
+            name == "Content-Type" && value == "application/x-www-form-urlencoded"
+        }));
+    }
+
+    #[tokio::test]
+    async fn transport_errors_are_retried() {
+
+        let requests: Arc<Mutex<Vec<HttpRequest>>> = Arc::default();
+        let engine = QueryEngine::from_transport(FakeTransport { failures: 2, requests: requests.clone() })
+            .with_policy(RetryPolicy::new().with_base_delay(Duration::from_millis(1)).with_jitter(false));
+
+        engine.query("[out:json];node(1);out;".to_string())
+            .await
+            .expect("Query should succeed after retrying!");
+
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    /// Sends every request as a GET with a User-Agent.
+    #[derive(Debug)]
+    struct GetTransport(StdTransport);
+
+    impl Transport for GetTransport {
+        fn send(&self, request: HttpRequest) -> TransportFuture<'_> {
+            self.0.send(request.to_get().with_header("User-Agent", "osmgraph-tests"))
+        }
+    }
+
+    #[test]
+    fn get_transport() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+
+        let engine = QueryEngine::from_transport(GetTransport(StdTransport))
+            .with_url(server.interpreter_url());
+
+        engine.query_blocking("[out:json];node(1);out;".to_string())
+            .expect("Query should succeed!");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].path, "/api/interpreter?data=%5Bout%3Ajson%5D%3Bnode%281%29%3Bout%3B");
+        assert_eq!(requests[0].header("user-agent"), Some("osmgraph-tests"));
+        assert!(requests[0].body.is_empty());
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn reqwest_transport() {
+
+        let server = MockServer::start(|_, _| Response::json(BODY));
+        let engine = QueryEngine::new().with_url(server.interpreter_url());
+
+        let response: OverpassResponse = engine.fetch_blocking("[out:json];node(1);out;".to_string())
+            .expect("Query should succeed!");
+        assert_eq!(response.elements().len(), 1);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].form_field("data").as_deref(), Some("[out:json];node(1);out;"));
+    }
+}
@@ -0,0 +1,124 @@
+mod common;
+
+#[cfg(test)]
+mod way_filter {
+
+    use osmgraph::Error;
+    use osmgraph::api::{OverpassResponse, TagFilter, Tags, WayFilter};
+    #[cfg(feature = "emulator")]
+    use osmgraph::emulator::Emulator;
+    use osmgraph::graph::{OSMGraph, create_graph, create_graph_with_filter};
+
+    #[cfg(feature = "emulator")]
+    use crate::common;
+
+    fn tags(tags: &[(&str, &str)]) -> Tags {
+        tags.iter().copied().collect()
+    }
+
+    fn response() -> OverpassResponse {
+        OverpassResponse::load_blocking("./assets/test.json").expect("Was not able to load json!")
+    }
+
+    #[test]
+    fn selectors() {
+        let filter = WayFilter::all(vec![
+            WayFilter::any(vec![
+                WayFilter::tag(TagFilter::exists("railway")),
+                WayFilter::tag(TagFilter::exists("waterway")),
+            ]),
+            WayFilter::any(vec![
+                WayFilter::tag(TagFilter::not_exists("tunnel")),
+                WayFilter::tag(TagFilter::equals("tunnel", "no")),
+            ]),
+        ]);
+
+        let selectors: Vec<String> = filter.selectors().iter().map(|s| s.to_string()).collect();
+        assert_eq!(selectors, vec![
+            r#"way["railway"][!"tunnel"]"#,
+            r#"way["railway"]["tunnel"="no"]"#,
+            r#"way["waterway"][!"tunnel"]"#,
+            r#"way["waterway"]["tunnel"="no"]"#,
+        ]);
+
+        assert_eq!(WayFilter::all(vec![]).selectors().len(), 1);
+        assert!(WayFilter::any(vec![]).selectors().is_empty());
+        assert_eq!(WayFilter::highway(&[]).selectors()[0].to_string(), r#"way["highway"]"#);
+    }
+
+    #[test]
+    fn matching() {
+        let matcher = WayFilter::all(vec![
+            WayFilter::highway(&["service"]),
+            WayFilter::tag(TagFilter::not_equals("access", "private")),
+            WayFilter::tag(TagFilter::not_regex("service", "parking")),
+        ]).matcher().unwrap();
+
+        assert!(matcher.matches(Some(&tags(&[("highway", "service")]))));
+        assert!(matcher.matches(Some(&tags(&[("highway", "service"), ("service", "alley")]))));
+        assert!(!matcher.matches(Some(&tags(&[("highway", "service"), ("service", "parking_aisle")]))));
+        assert!(!matcher.matches(Some(&tags(&[("highway", "service"), ("access", "private")]))));
+        assert!(!matcher.matches(Some(&tags(&[("highway", "service_road")]))));
+        assert!(!matcher.matches(None));
+
+        assert!(WayFilter::all(vec![]).matcher().unwrap().matches(None));
+        assert!(!WayFilter::any(vec![]).matcher().unwrap().matches(Some(&tags(&[("highway", "service")]))));
+
+        let invalid = WayFilter::tag(TagFilter::regex("highway", "(unclosed"));
+        assert!(matches!(invalid.matcher(), Err(Error::InvalidFilter(_))));
+    }
+
+    #[test]
+    fn graph_with_filter() {
+        let response = response();
+
+        //Ways without a highway tag make it into the graph
+        let rail: OSMGraph = create_graph_with_filter(response.elements(), &WayFilter::tag(TagFilter::equals("railway", "rail")))
+            .expect("Was unable to parse graph!");
+        assert!(rail.edge_count() > 0);
+        assert!(rail.edge_weights().all(|edge| edge.highway_type().is_empty() && edge.tags().get("railway") == Some("rail")));
+
+        //Only the nodes on the kept ways are in the graph
+        let on_edges: Vec<u64> = rail.edge_weights().flat_map(|edge| edge.nodes()).collect();
+        assert!(rail.node_weights().all(|node| on_edges.contains(&node.id())));
+
+        //The highway filter gives the same edges as create_graph
+        let all: OSMGraph = create_graph(response.elements()).unwrap();
+        let highways: OSMGraph = create_graph_with_filter(response.elements(), &WayFilter::highway(&[])).unwrap();
+        assert_eq!(all.edge_count(), highways.edge_count());
+        assert!(highways.node_count() < all.node_count());
+
+        let service: OSMGraph = create_graph_with_filter(response.elements(), &WayFilter::all(vec![
+            WayFilter::highway(&["service"]),
+            WayFilter::tag(TagFilter::not_equals("service", "parking_aisle")),
+        ])).unwrap();
+        assert!(service.edge_count() > 0);
+        assert!(service.edge_weights().all(|edge| {
+            edge.highway_type() == "service" && edge.tags().get("service") != Some("parking_aisle")
+        }));
+    }
+
+    #[cfg(feature = "emulator")]
+    #[test]
+    fn engine() {
+        let server = Emulator::from_response(&response()).serve().expect("Was not able to start the emulator!");
+
+        let filter = WayFilter::any(vec![
+            WayFilter::tag(TagFilter::exists("railway")),
+            WayFilter::tag(TagFilter::regex("waterway", "^(river|stream)$")),
+        ]);
+        let engine = common::engine()
+            .with_url(server.url())
+            .with_way_filter(filter.clone());
+        assert_eq!(engine.way_filter(), Some(&filter));
+
+        let graph: OSMGraph = engine.graph_from_bbox_blocking(40.70, -76.95, 40.90, -76.75)
+            .expect("Was not able to build a graph!");
+        assert!(graph.edge_weights().any(|edge| edge.tags().get("railway").is_some()));
+        assert!(graph.edge_weights().any(|edge| edge.tags().get("waterway") == Some("stream")));
+        assert!(graph.edge_weights().all(|edge| edge.tags().get("waterway") != Some("ditch")));
+
+        //Setting highway values again drops the way filter
+        assert_eq!(engine.with_filters(vec!["primary".to_string()]).way_filter(), None);
+    }
+}